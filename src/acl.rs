@@ -0,0 +1,754 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::token::Secret;
+
+pub struct Acl;
+
+#[derive(Serialize)]
+struct DcQuery<'a> {
+    dc: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PolicyPayload<'a> {
+    name: &'a str,
+    rules: String,
+}
+
+impl Acl {
+    /// Creates the initial management token on a cluster with ACLs enabled
+    /// but not yet bootstrapped. Fails with [`AlreadyBootstrapped`] otherwise.
+    pub async fn bootstrap(client: &Client) -> Result<Token, anyhow::Error> {
+        Self::send_bootstrap(client, None).await
+    }
+
+    /// Like [`Acl::bootstrap`], with a chosen secret for the token.
+    pub async fn bootstrap_with_secret(
+        client: &Client,
+        secret: &Secret,
+    ) -> Result<Token, anyhow::Error> {
+        let payload = serde_json::json!({ "BootstrapSecret": secret.expose() });
+        Self::send_bootstrap(client, Some(payload)).await
+    }
+
+    async fn send_bootstrap(
+        client: &Client,
+        payload: Option<serde_json::Value>,
+    ) -> Result<Token, anyhow::Error> {
+        let rs = client
+            .send(Method::PUT, "v1/acl/bootstrap", &(), payload, None)
+            .await?;
+        if rs.status == 403
+            && let Some(reset_index) = reset_index(&rs.text())
+        {
+            return Err(AlreadyBootstrapped { reset_index }.into());
+        }
+        rs.decode()
+    }
+
+    /// Creates a policy named `name` granting `rules`.
+    pub async fn create_policy(
+        client: &Client,
+        name: &str,
+        rules: &Rules,
+    ) -> Result<Policy, anyhow::Error> {
+        Self::write_policy(client, "v1/acl/policy".to_string(), name, rules).await
+    }
+
+    /// Replaces the name and rules of the policy `id`.
+    pub async fn update_policy(
+        client: &Client,
+        id: &str,
+        name: &str,
+        rules: &Rules,
+    ) -> Result<Policy, anyhow::Error> {
+        Self::write_policy(client, format!("v1/acl/policy/{id}"), name, rules).await
+    }
+
+    async fn write_policy(
+        client: &Client,
+        path: String,
+        name: &str,
+        rules: &Rules,
+    ) -> Result<Policy, anyhow::Error> {
+        let payload = PolicyPayload {
+            name,
+            rules: rules.to_hcl(),
+        };
+        client
+            .send(
+                Method::PUT,
+                &path,
+                &(),
+                Some(serde_json::to_value(payload)?),
+                None,
+            )
+            .await?
+            .decode()
+    }
+
+    /// Policy `id`, `None` if it doesn't exist.
+    pub async fn read_policy(client: &Client, id: &str) -> Result<Option<Policy>, anyhow::Error> {
+        let path = format!("v1/acl/policy/{id}");
+        let rs = client.send(Method::GET, &path, &(), None, None).await?;
+        // Consul reports unknown policies as 403 "ACL not found".
+        if rs.status == 404 || (rs.status == 403 && rs.text().contains("ACL not found")) {
+            return Ok(None);
+        }
+        rs.decode()
+    }
+
+    pub async fn delete_policy(client: &Client, id: &str) -> Result<(), anyhow::Error> {
+        let path = format!("v1/acl/policy/{id}");
+        client
+            .send(Method::DELETE, &path, &(), None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Token the client authenticates with.
+    pub async fn token_self(client: &Client) -> Result<Token, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/acl/token/self", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Exchanges a bearer token with an auth method for a Consul token.
+    pub async fn login(client: &Client, login: Login) -> Result<Token, anyhow::Error> {
+        let query = DcQuery {
+            dc: login.dc.as_deref(),
+        };
+        client
+            .send(
+                Method::POST,
+                "v1/acl/login",
+                &query,
+                Some(serde_json::to_value(&login)?),
+                None,
+            )
+            .await?
+            .decode()
+    }
+
+    /// Destroys the token the client authenticates with, which must have
+    /// been created by [`Acl::login`].
+    pub async fn logout(client: &Client) -> Result<(), anyhow::Error> {
+        client
+            .send(Method::POST, "v1/acl/logout", &(), None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Bootstrap was refused because it already happened.
+///
+/// To bootstrap again, write [`AlreadyBootstrapped::reset_index`] to the
+/// `acl-bootstrap-reset` file in the leader's data directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyBootstrapped {
+    reset_index: u64,
+}
+
+impl AlreadyBootstrapped {
+    pub fn reset_index(&self) -> u64 {
+        self.reset_index
+    }
+}
+
+impl fmt::Display for AlreadyBootstrapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ACL bootstrap no longer allowed (reset index: {})",
+            self.reset_index
+        )
+    }
+}
+
+impl std::error::Error for AlreadyBootstrapped {}
+
+/// Reset index from Consul's `... (reset index: 13)` message.
+fn reset_index(message: &str) -> Option<u64> {
+    let (_, rest) = message.split_once("reset index: ")?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// Auth method login, e.g. with a Kubernetes service account JWT.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Login {
+    auth_method: String,
+    bearer_token: Secret,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    meta: HashMap<String, String>,
+    #[serde(skip)]
+    dc: Option<String>,
+}
+
+impl Login {
+    pub fn new<M, T>(auth_method: M, bearer_token: T) -> Self
+    where
+        M: Into<String>,
+        T: Into<Secret>,
+    {
+        Self {
+            auth_method: auth_method.into(),
+            bearer_token: bearer_token.into(),
+            meta: HashMap::new(),
+            dc: None,
+        }
+    }
+
+    /// Metadata stored on the created token.
+    pub fn meta<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.dc = Some(dc.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Token {
+    #[serde(rename = "AccessorID")]
+    accessor_id: String,
+    #[serde(rename = "SecretID", default)]
+    secret_id: Secret,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    policies: Option<Vec<Link>>,
+    #[serde(default)]
+    roles: Option<Vec<Link>>,
+    #[serde(default)]
+    local: bool,
+    #[serde(default)]
+    auth_method: String,
+    #[serde(default)]
+    expiration_time: Option<String>,
+    #[serde(default)]
+    create_index: u64,
+    #[serde(default)]
+    modify_index: u64,
+}
+
+impl Token {
+    pub fn accessor_id(&self) -> &str {
+        &self.accessor_id
+    }
+
+    /// Secret sent as `X-Consul-Token`.
+    pub fn secret_id(&self) -> &Secret {
+        &self.secret_id
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn policies(&self) -> &[Link] {
+        self.policies.as_deref().unwrap_or_default()
+    }
+
+    pub fn roles(&self) -> &[Link] {
+        self.roles.as_deref().unwrap_or_default()
+    }
+
+    pub fn local(&self) -> bool {
+        self.local
+    }
+
+    /// Auth method that created the token, empty for other tokens.
+    pub fn auth_method(&self) -> &str {
+        &self.auth_method
+    }
+
+    /// Expiry as an RFC 3339 timestamp, for tokens with a TTL.
+    pub fn expiration_time(&self) -> Option<&str> {
+        self.expiration_time.as_deref()
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+}
+
+/// Policy or role attached to a token.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Link {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+}
+
+impl Link {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Policy {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    rules: String,
+    #[serde(default)]
+    create_index: u64,
+    #[serde(default)]
+    modify_index: u64,
+}
+
+impl Policy {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Rules as stored by Consul.
+    pub fn rules(&self) -> &str {
+        &self.rules
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+    Write,
+    Deny,
+    List,
+}
+
+impl Access {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Deny => "deny",
+            Access::List => "list",
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    resource: &'static str,
+    name: Option<String>,
+    access: Access,
+}
+
+/// Builder for the ACL rules language accepted in a policy's `Rules` field,
+/// e.g. by [`Acl::create_policy`].
+///
+/// A rule for a resource and name that is already present replaces the
+/// earlier one in place, so the HCL and JSON output always agree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, rule: Rule) -> Self {
+        let existing = self
+            .rules
+            .iter_mut()
+            .find(|r| r.resource == rule.resource && r.name == rule.name);
+        match existing {
+            Some(existing) => existing.access = rule.access,
+            None => self.rules.push(rule),
+        }
+        self
+    }
+
+    fn named<S>(self, resource: &'static str, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.push(Rule {
+            resource,
+            name: Some(name.into()),
+            access,
+        })
+    }
+
+    fn global(self, resource: &'static str, access: Access) -> Self {
+        self.push(Rule {
+            resource,
+            name: None,
+            access,
+        })
+    }
+
+    pub fn key<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("key", name, access)
+    }
+
+    pub fn key_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("key_prefix", prefix, access)
+    }
+
+    pub fn service<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("service", name, access)
+    }
+
+    pub fn service_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("service_prefix", prefix, access)
+    }
+
+    pub fn node<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("node", name, access)
+    }
+
+    pub fn node_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("node_prefix", prefix, access)
+    }
+
+    pub fn agent<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("agent", name, access)
+    }
+
+    pub fn agent_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("agent_prefix", prefix, access)
+    }
+
+    pub fn session<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("session", name, access)
+    }
+
+    pub fn session_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("session_prefix", prefix, access)
+    }
+
+    pub fn event<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("event", name, access)
+    }
+
+    pub fn event_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("event_prefix", prefix, access)
+    }
+
+    pub fn query<S>(self, name: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("query", name, access)
+    }
+
+    pub fn query_prefix<S>(self, prefix: S, access: Access) -> Self
+    where
+        S: Into<String>,
+    {
+        self.named("query_prefix", prefix, access)
+    }
+
+    pub fn operator(self, access: Access) -> Self {
+        self.global("operator", access)
+    }
+
+    pub fn keyring(self, access: Access) -> Self {
+        self.global("keyring", access)
+    }
+
+    pub fn mesh(self, access: Access) -> Self {
+        self.global("mesh", access)
+    }
+
+    pub fn acl(self, access: Access) -> Self {
+        self.global("acl", access)
+    }
+
+    pub fn to_hcl(&self) -> String {
+        let mut out = String::new();
+        for rule in &self.rules {
+            match &rule.name {
+                Some(name) => out.push_str(&format!(
+                    "{} \"{}\" {{\n  policy = \"{}\"\n}}\n",
+                    rule.resource,
+                    escape_hcl(name),
+                    rule.access
+                )),
+                None => out.push_str(&format!("{} = \"{}\"\n", rule.resource, rule.access)),
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut out = serde_json::Map::new();
+        for rule in &self.rules {
+            let access = serde_json::Value::from(rule.access.as_str());
+            match &rule.name {
+                Some(name) => {
+                    let entry = out
+                        .entry(rule.resource)
+                        .or_insert_with(|| serde_json::json!({}));
+                    if let Some(map) = entry.as_object_mut() {
+                        map.insert(name.clone(), serde_json::json!({ "policy": access }));
+                    }
+                }
+                None => {
+                    out.insert(rule.resource.to_string(), access);
+                }
+            }
+        }
+        serde_json::Value::Object(out)
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hcl())
+    }
+}
+
+fn escape_hcl(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                out.push(c);
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_reset_index() {
+        let message = "Permission denied: ACL bootstrap no longer allowed (reset index: 13)";
+        assert_eq!(reset_index(message), Some(13));
+        assert_eq!(reset_index("Permission denied"), None);
+    }
+
+    #[test]
+    fn it_parses_logins() {
+        let login = Login::new("kubernetes", "eyJhbGciOi").meta("pod", "web-0");
+        assert_eq!(
+            serde_json::to_value(&login).unwrap(),
+            serde_json::json!({
+                "AuthMethod": "kubernetes",
+                "BearerToken": "eyJhbGciOi",
+                "Meta": {"pod": "web-0"},
+            })
+        );
+        assert!(!format!("{login:?}").contains("eyJhbGciOi"));
+        let token: Token = serde_json::from_value(serde_json::json!({
+            "AccessorID": "926e2bd2-b344-d91b-0c83-ae89f372cd9b",
+            "SecretID": "b78d37c7-0ca7-5f4d-99ee-6d9975ce4586",
+            "Description": "token created via login",
+            "Roles": [{"ID": "3356c67c-5535-403a-ad79-c1d5f9df8fc7", "Name": "demo"}],
+            "Local": true,
+            "AuthMethod": "kubernetes",
+            "ExpirationTime": "2026-10-16T12:00:00Z",
+            "CreateIndex": 36,
+            "ModifyIndex": 36
+        }))
+        .unwrap();
+        assert_eq!(
+            token.secret_id().expose(),
+            "b78d37c7-0ca7-5f4d-99ee-6d9975ce4586"
+        );
+        assert!(!format!("{token:?}").contains("b78d37c7"));
+        assert_eq!(token.roles()[0].name(), "demo");
+        assert!(token.policies().is_empty());
+        assert_eq!(token.auth_method(), "kubernetes");
+        assert!(token.local());
+    }
+
+    #[test]
+    fn it_builds_rules() {
+        let rules = Rules::new()
+            .key_prefix("app/\"${x}\"", Access::Write)
+            .service("web", Access::Read)
+            .operator(Access::Deny);
+        assert_eq!(
+            rules.to_hcl(),
+            "key_prefix \"app/\\\"$${x}\\\"\" {\n  policy = \"write\"\n}\n\
+             service \"web\" {\n  policy = \"read\"\n}\n\
+             operator = \"deny\"\n"
+        );
+        assert_eq!(
+            rules.to_json(),
+            serde_json::json!({
+                "key_prefix": {"app/\"${x}\"": {"policy": "write"}},
+                "service": {"web": {"policy": "read"}},
+                "operator": "deny",
+            })
+        );
+    }
+
+    #[test]
+    fn it_replaces_duplicate_rules() {
+        let rules = Rules::new()
+            .key("app", Access::Read)
+            .operator(Access::Read)
+            .key_prefix("app", Access::Read)
+            .key("app", Access::Deny)
+            .operator(Access::Write);
+        assert_eq!(
+            rules.to_hcl(),
+            "key \"app\" {\n  policy = \"deny\"\n}\n\
+             operator = \"write\"\n\
+             key_prefix \"app\" {\n  policy = \"read\"\n}\n"
+        );
+        assert_eq!(
+            rules.to_json(),
+            serde_json::json!({
+                "key": {"app": {"policy": "deny"}},
+                "key_prefix": {"app": {"policy": "read"}},
+                "operator": "write",
+            })
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_manages_policies() {
+        use crate::mock::{MockAgent, Reply};
+
+        let policy = |rules: &str| {
+            Reply::json(serde_json::json!({
+                "ID": "e359bd81-baca-903e-7e64-1ccd9fdc78f5",
+                "Name": "app",
+                "Rules": rules,
+                "CreateIndex": 14,
+                "ModifyIndex": 14,
+            }))
+        };
+        let id = "e359bd81-baca-903e-7e64-1ccd9fdc78f5";
+        let path = format!("v1/acl/policy/{id}");
+        let rules = Rules::new().key_prefix("app/", Access::Write);
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("PUT", "v1/acl/policy", policy(&rules.to_hcl()));
+        agent.expect("PUT", &path, policy(&rules.to_hcl()));
+        agent.expect("GET", &path, policy(&rules.to_hcl()));
+        agent.expect("DELETE", &path, Reply::json(true.into()));
+        agent.expect("GET", &path, Reply::new(403, "ACL not found"));
+        let client = agent.client().unwrap();
+        let created = Acl::create_policy(&client, "app", &rules).await.unwrap();
+        assert_eq!(created.id(), id);
+        Acl::update_policy(&client, id, "app", &rules)
+            .await
+            .unwrap();
+        let read = Acl::read_policy(&client, id).await.unwrap().unwrap();
+        assert_eq!(read.rules(), rules.to_hcl());
+        Acl::delete_policy(&client, id).await.unwrap();
+        assert!(Acl::read_policy(&client, id).await.unwrap().is_none());
+        let body: serde_json::Value = serde_json::from_slice(agent.requests()[0].body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"Name": "app", "Rules": rules.to_hcl()})
+        );
+        agent.verify().unwrap();
+    }
+}
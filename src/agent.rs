@@ -0,0 +1,1058 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::health::{AgentService, CheckStatus, HealthCheck};
+use crate::token::Secret;
+
+pub struct Agent;
+
+#[derive(Default, Serialize)]
+struct MembersQuery<'a> {
+    wan: Option<bool>,
+    segment: Option<&'a str>,
+}
+
+#[derive(Default, Serialize)]
+struct FilterQuery<'a> {
+    filter: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CheckUpdate<'a> {
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a str>,
+}
+
+#[derive(Default, Serialize)]
+struct JoinQuery {
+    wan: Option<bool>,
+}
+
+#[derive(Default, Serialize)]
+struct ForceLeaveQuery {
+    prune: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct MaintenanceQuery<'a> {
+    enable: bool,
+    reason: Option<&'a str>,
+}
+
+impl Agent {
+    pub async fn self_info(client: &Client) -> Result<AgentSelf, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/agent/self", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    pub async fn members(client: &Client, wan: bool) -> Result<Vec<Member>, anyhow::Error> {
+        let query = MembersQuery {
+            wan: wan.then_some(true),
+            segment: None,
+        };
+        client
+            .send(Method::GET, "v1/agent/members", &query, None, None)
+            .await?
+            .decode()
+    }
+
+    /// LAN members of a network segment (Enterprise); `_all` lists the
+    /// members of every segment.
+    pub async fn segment_members(
+        client: &Client,
+        segment: &str,
+    ) -> Result<Vec<Member>, anyhow::Error> {
+        let query = MembersQuery {
+            wan: None,
+            segment: Some(segment),
+        };
+        client
+            .send(Method::GET, "v1/agent/members", &query, None, None)
+            .await?
+            .decode()
+    }
+
+    /// Services registered with the local agent keyed by ID, optionally
+    /// narrowed by a filter expression.
+    pub async fn services(
+        client: &Client,
+        filter: Option<&str>,
+    ) -> Result<HashMap<String, AgentService>, anyhow::Error> {
+        client
+            .send(
+                Method::GET,
+                "v1/agent/services",
+                &FilterQuery { filter },
+                None,
+                None,
+            )
+            .await?
+            .decode()
+    }
+
+    /// Checks registered with the local agent keyed by check ID.
+    pub async fn checks(client: &Client) -> Result<HashMap<String, HealthCheck>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/agent/checks", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Sets the status of a TTL check and resets its TTL; `output` replaces
+    /// the check output. See [`crate::heartbeat::CheckHeartbeat`] for the
+    /// usual loop.
+    pub async fn update_check(
+        client: &Client,
+        check_id: &str,
+        status: CheckStatus,
+        output: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let path = format!("v1/agent/check/update/{check_id}");
+        let payload = serde_json::to_value(CheckUpdate { status, output })?;
+        client
+            .send(Method::PUT, &path, &(), Some(payload), None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Local health of every instance of `name` registered with the agent.
+    pub async fn service_health_by_name(
+        client: &Client,
+        name: &str,
+    ) -> Result<Vec<AgentServiceHealth>, anyhow::Error> {
+        let path = format!("v1/agent/health/service/name/{name}");
+        let rs = client.send(Method::GET, &path, &(), None, None).await?;
+        match rs.status {
+            404 => Ok(vec![]),
+            // The status code mirrors the aggregated status.
+            200 | 429 | 503 => Ok(serde_json::from_slice(&rs.body)?),
+            _ => rs.decode(),
+        }
+    }
+
+    pub async fn service_health_by_id(
+        client: &Client,
+        id: &str,
+    ) -> Result<Option<AgentServiceHealth>, anyhow::Error> {
+        let path = format!("v1/agent/health/service/id/{id}");
+        let rs = client.send(Method::GET, &path, &(), None, None).await?;
+        match rs.status {
+            404 => Ok(None),
+            200 | 429 | 503 => Ok(Some(serde_json::from_slice(&rs.body)?)),
+            _ => rs.decode(),
+        }
+    }
+
+    /// Puts the whole node into (or takes it out of) maintenance mode.
+    pub async fn maintenance(
+        client: &Client,
+        enable: bool,
+        reason: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let query = MaintenanceQuery { enable, reason };
+        client
+            .send(Method::PUT, "v1/agent/maintenance", &query, None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn service_maintenance(
+        client: &Client,
+        service_id: &str,
+        enable: bool,
+        reason: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let path = format!("v1/agent/service/maintenance/{service_id}");
+        let query = MaintenanceQuery { enable, reason };
+        client
+            .send(Method::PUT, &path, &query, None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Point-in-time snapshot of the agent's in-memory telemetry.
+    pub async fn metrics(client: &Client) -> Result<Metrics, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/agent/metrics", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Host information of the agent's machine; requires `operator:read`.
+    pub async fn host(client: &Client) -> Result<HostInfo, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/agent/host", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Replaces one of the agent's ACL tokens at runtime, e.g. after
+    /// bootstrap. Persisted only with `acl.enable_token_persistence`.
+    pub async fn update_token(
+        client: &Client,
+        kind: TokenKind,
+        token: &Secret,
+    ) -> Result<(), anyhow::Error> {
+        let path = format!("v1/agent/token/{}", kind.as_str());
+        let payload = serde_json::json!({ "Token": token.expose() });
+        client
+            .send(Method::PUT, &path, &(), Some(payload), None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Makes the agent join the cluster through the agent at `address`,
+    /// over the WAN pool with `wan` (servers only).
+    pub async fn join(client: &Client, address: &str, wan: bool) -> Result<(), anyhow::Error> {
+        let path = format!("v1/agent/join/{address}");
+        let query = JoinQuery {
+            wan: wan.then_some(true),
+        };
+        client
+            .send(Method::PUT, &path, &query, None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Moves a failed `node` to the left state; with `prune` it is removed
+    /// from the member list right away instead of after the reap interval.
+    pub async fn force_leave(
+        client: &Client,
+        node: &str,
+        prune: bool,
+    ) -> Result<(), anyhow::Error> {
+        let path = format!("v1/agent/force-leave/{node}");
+        let query = ForceLeaveQuery {
+            prune: prune.then_some(true),
+        };
+        client
+            .send(Method::PUT, &path, &query, None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn reload(client: &Client) -> Result<(), anyhow::Error> {
+        client
+            .send(Method::PUT, "v1/agent/reload", &(), None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Agent token slot updated by [`Agent::update_token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Used for requests that don't carry a token.
+    Default,
+    /// Used by the agent for its own internal operations.
+    Agent,
+    /// Grants `agent:write` when the servers are unreachable.
+    AgentRecovery,
+    /// Used by secondary datacenters to replicate ACLs.
+    Replication,
+    /// Used to register services and checks from config files.
+    ConfigFileServiceRegistration,
+    Dns,
+}
+
+impl TokenKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKind::Default => "default",
+            TokenKind::Agent => "agent",
+            TokenKind::AgentRecovery => "agent_recovery",
+            TokenKind::Replication => "replication",
+            TokenKind::ConfigFileServiceRegistration => "config_file_service_registration",
+            TokenKind::Dns => "dns",
+        }
+    }
+}
+
+/// Release of a Consul agent, without pre-release or build suffixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AgentVersion {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl AgentVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    pub fn major(&self) -> u64 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u64 {
+        self.patch
+    }
+}
+
+impl FromStr for AgentVersion {
+    type Err = anyhow::Error;
+
+    /// Parses versions like `1.17.2`, `v1.18.0-dev` or `1.16.1+ent`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let release = s
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default();
+        let mut parts = release.split('.').map(str::parse::<u64>);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), patch, None) => {
+                let patch = patch.transpose()?.unwrap_or(0);
+                Ok(Self::new(major, minor, patch))
+            }
+            _ => anyhow::bail!("Invalid Consul version {s:?}"),
+        }
+    }
+}
+
+impl fmt::Display for AgentVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// API feature that needs a minimum agent version; see `Client::require`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Capability {
+    pub(crate) name: &'static str,
+    pub(crate) since: AgentVersion,
+}
+
+impl Capability {
+    pub(crate) const PEERING: Capability = Capability {
+        name: "Cluster peering",
+        since: AgentVersion::new(1, 13, 0),
+    };
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AgentSelf {
+    config: AgentConfig,
+    member: Member,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+}
+
+impl AgentSelf {
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    pub fn member(&self) -> &Member {
+        &self.member
+    }
+
+    pub fn meta(&self) -> Option<&HashMap<String, String>> {
+        self.meta.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AgentConfig {
+    datacenter: String,
+    #[serde(default)]
+    primary_datacenter: String,
+    node_name: String,
+    #[serde(rename = "NodeID", default)]
+    node_id: String,
+    server: bool,
+    version: String,
+    #[serde(default)]
+    revision: String,
+}
+
+impl AgentConfig {
+    pub fn datacenter(&self) -> &str {
+        &self.datacenter
+    }
+
+    pub fn primary_datacenter(&self) -> &str {
+        &self.primary_datacenter
+    }
+
+    pub fn node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn server(&self) -> bool {
+        self.server
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AgentServiceHealth {
+    aggregated_status: String,
+    service: AgentService,
+    #[serde(default)]
+    checks: Vec<HealthCheck>,
+}
+
+impl AgentServiceHealth {
+    /// Worst status of the instance's checks: `passing`, `warning` or
+    /// `critical`; `maintenance` while in maintenance mode.
+    pub fn aggregated_status(&self) -> &str {
+        &self.aggregated_status
+    }
+
+    pub fn service(&self) -> &AgentService {
+        &self.service
+    }
+
+    pub fn checks(&self) -> &[HealthCheck] {
+        &self.checks
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberStatus {
+    None,
+    Alive,
+    Leaving,
+    Left,
+    Failed,
+    Unknown(u8),
+}
+
+impl From<u8> for MemberStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => MemberStatus::None,
+            1 => MemberStatus::Alive,
+            2 => MemberStatus::Leaving,
+            3 => MemberStatus::Left,
+            4 => MemberStatus::Failed,
+            other => MemberStatus::Unknown(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Member {
+    name: String,
+    addr: String,
+    port: u16,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    status: u8,
+}
+
+impl Member {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    pub fn status(&self) -> MemberStatus {
+        self.status.into()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.status() == MemberStatus::Alive
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Metrics {
+    timestamp: String,
+    #[serde(default)]
+    gauges: Vec<Gauge>,
+    #[serde(default)]
+    counters: Vec<Sample>,
+    #[serde(default)]
+    samples: Vec<Sample>,
+}
+
+impl Metrics {
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    pub fn gauges(&self) -> &[Gauge] {
+        &self.gauges
+    }
+
+    pub fn counters(&self) -> &[Sample] {
+        &self.counters
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn gauge(&self, name: &str) -> Option<&Gauge> {
+        self.gauges.iter().find(|g| g.name == name)
+    }
+
+    pub fn counter(&self, name: &str) -> Option<&Sample> {
+        self.counters.iter().find(|c| c.name == name)
+    }
+
+    pub fn sample(&self, name: &str) -> Option<&Sample> {
+        self.samples.iter().find(|s| s.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Gauge {
+    name: String,
+    value: f64,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+impl Gauge {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+}
+
+/// Aggregated counter or timer sample over the current interval.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Sample {
+    name: String,
+    count: u64,
+    rate: f64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    stddev: f64,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+impl Sample {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.stddev
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HostInfo {
+    memory: HostMemory,
+    #[serde(rename = "CPU", default)]
+    cpu: Vec<HostCpu>,
+    host: HostDetails,
+    disk: HostDisk,
+    collection_time: i64,
+    #[serde(default)]
+    errors: Option<Vec<String>>,
+}
+
+impl HostInfo {
+    pub fn memory(&self) -> &HostMemory {
+        &self.memory
+    }
+
+    pub fn cpu(&self) -> &[HostCpu] {
+        &self.cpu
+    }
+
+    pub fn host(&self) -> &HostDetails {
+        &self.host
+    }
+
+    pub fn disk(&self) -> &HostDisk {
+        &self.disk
+    }
+
+    /// Collection time in nanoseconds since the Unix epoch.
+    pub fn collection_time(&self) -> i64 {
+        self.collection_time
+    }
+
+    /// Collectors that failed on the agent; their sections are zeroed.
+    pub fn errors(&self) -> &[String] {
+        self.errors.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostMemory {
+    total: u64,
+    available: u64,
+    used: u64,
+    used_percent: f64,
+    free: u64,
+}
+
+impl HostMemory {
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn available(&self) -> u64 {
+        self.available
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn used_percent(&self) -> f64 {
+        self.used_percent
+    }
+
+    pub fn free(&self) -> u64 {
+        self.free
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostCpu {
+    cpu: i32,
+    #[serde(default)]
+    vendor_id: String,
+    #[serde(default)]
+    model_name: String,
+    cores: i32,
+    mhz: f64,
+}
+
+impl HostCpu {
+    pub fn cpu(&self) -> i32 {
+        self.cpu
+    }
+
+    pub fn vendor_id(&self) -> &str {
+        &self.vendor_id
+    }
+
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    pub fn cores(&self) -> i32 {
+        self.cores
+    }
+
+    pub fn mhz(&self) -> f64 {
+        self.mhz
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostDetails {
+    hostname: String,
+    uptime: u64,
+    boot_time: u64,
+    #[serde(default)]
+    os: String,
+    #[serde(default)]
+    platform: String,
+    #[serde(default)]
+    platform_version: String,
+    #[serde(default)]
+    kernel_version: String,
+    #[serde(default)]
+    kernel_arch: String,
+}
+
+impl HostDetails {
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Uptime in seconds.
+    pub fn uptime(&self) -> u64 {
+        self.uptime
+    }
+
+    /// Boot time in seconds since the Unix epoch.
+    pub fn boot_time(&self) -> u64 {
+        self.boot_time
+    }
+
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    pub fn platform_version(&self) -> &str {
+        &self.platform_version
+    }
+
+    pub fn kernel_version(&self) -> &str {
+        &self.kernel_version
+    }
+
+    pub fn kernel_arch(&self) -> &str {
+        &self.kernel_arch
+    }
+}
+
+/// Usage of the filesystem holding the agent's data directory.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostDisk {
+    path: String,
+    #[serde(default)]
+    fstype: String,
+    total: u64,
+    free: u64,
+    used: u64,
+    used_percent: f64,
+}
+
+impl HostDisk {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn fstype(&self) -> &str {
+        &self.fstype
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn free(&self) -> u64 {
+        self.free
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn used_percent(&self) -> f64 {
+        self.used_percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_versions() {
+        let version: AgentVersion = "1.17.2".parse().unwrap();
+        assert_eq!(version, AgentVersion::new(1, 17, 2));
+        assert_eq!(
+            "v1.18.0-dev".parse::<AgentVersion>().unwrap(),
+            AgentVersion::new(1, 18, 0)
+        );
+        assert_eq!(
+            "1.16.1+ent".parse::<AgentVersion>().unwrap(),
+            AgentVersion::new(1, 16, 1)
+        );
+        assert!(AgentVersion::new(1, 9, 0) < AgentVersion::new(1, 13, 0));
+        assert!("latest".parse::<AgentVersion>().is_err());
+        assert_eq!(version.to_string(), "1.17.2");
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_joins_and_force_leaves() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("PUT", "v1/agent/join/10.0.0.5", Reply::new(200, ""));
+        agent.expect("PUT", "v1/agent/force-leave/web-3", Reply::new(200, ""));
+        agent.expect(
+            "PUT",
+            "v1/agent/force-leave/web-4",
+            Reply::new(500, "agent: No node found with name 'web-4'"),
+        );
+        let client = Client::new(agent.url()).unwrap();
+        Agent::join(&client, "10.0.0.5", true).await.unwrap();
+        Agent::force_leave(&client, "web-3", true).await.unwrap();
+        assert!(Agent::force_leave(&client, "web-4", false).await.is_err());
+        let queries: Vec<_> = agent
+            .requests()
+            .iter()
+            .map(|r| r.query().to_string())
+            .collect();
+        assert_eq!(queries, ["wan=true", "prune=true", ""]);
+    }
+
+    #[tokio::test]
+    async fn it_reads_agent_version() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let version = client.agent_version().await.unwrap();
+        assert!(version >= AgentVersion::new(1, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn it_lists_segment_members() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let members = Agent::members(&client, false).await.unwrap();
+        let segments = Agent::segment_members(&client, "_all").await.unwrap();
+        assert_eq!(segments.len(), members.len());
+    }
+
+    #[tokio::test]
+    async fn it_inventories() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let info = Agent::self_info(&client).await.unwrap();
+        let members = Agent::members(&client, false).await.unwrap();
+        assert!(
+            members
+                .iter()
+                .any(|m| m.name() == info.config().node_name() && m.is_alive())
+        );
+        Agent::reload(&client).await.unwrap();
+        // Service maintenance on an own service leaves the node, and the
+        // tests running against it, alone.
+        let service = serde_json::json!({"ID": "inventory-1", "Name": "inventory"});
+        client
+            .send(
+                Method::PUT,
+                "v1/agent/service/register",
+                &(),
+                Some(service),
+                None,
+            )
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        Agent::service_maintenance(&client, "inventory-1", true, Some("draining"))
+            .await
+            .unwrap();
+        let entries = crate::health::Health::service("inventory")
+            .get(&client)
+            .await
+            .unwrap();
+        assert!(entries[0].checks().iter().any(|c| {
+            c.check_id() == "_service_maintenance:inventory-1" && c.notes() == "draining"
+        }));
+        client
+            .send(
+                Method::PUT,
+                "v1/agent/service/deregister/inventory-1",
+                &(),
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_reads_local_services() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let services = Agent::services(&client, Some("Service != \"consul\""))
+            .await
+            .unwrap();
+        assert!(services.values().all(|s| s.service() != "consul"));
+        let health = Agent::service_health_by_name(&client, "missing")
+            .await
+            .unwrap();
+        assert!(health.is_empty());
+        let health = Agent::service_health_by_id(&client, "missing")
+            .await
+            .unwrap();
+        assert!(health.is_none());
+
+        let put = |path: String, body: Option<serde_json::Value>| {
+            let client = client.clone();
+            async move {
+                client
+                    .send(Method::PUT, &path, &(), body, None)
+                    .await
+                    .unwrap()
+                    .error_for_status()
+                    .unwrap();
+            }
+        };
+        let service = serde_json::json!({
+            "ID": "local-1",
+            "Name": "local",
+            "Check": {"TTL": "60s"},
+        });
+        put("v1/agent/service/register".into(), Some(service)).await;
+        // TTL checks start out critical (503), then follow their updates.
+        for (update, status) in [
+            (None, "critical"),
+            (Some("pass"), "passing"),
+            (Some("warn"), "warning"),
+        ] {
+            if let Some(update) = update {
+                put(format!("v1/agent/check/{update}/service:local-1"), None).await;
+            }
+            let health = Agent::service_health_by_id(&client, "local-1")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(health.aggregated_status(), status);
+            assert_eq!(health.checks()[0].status(), status);
+            let health = Agent::service_health_by_name(&client, "local")
+                .await
+                .unwrap();
+            assert_eq!(health.len(), 1);
+            assert_eq!(health[0].aggregated_status(), status);
+        }
+        put("v1/agent/service/deregister/local-1".into(), None).await;
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_decodes_service_health_statuses() {
+        use crate::mock::{MockAgent, Reply};
+
+        let health = |status: &str| {
+            serde_json::json!({
+                "AggregatedStatus": status,
+                "Service": {"ID": "local-1", "Service": "local"},
+                "Checks": [],
+            })
+        };
+        let agent = MockAgent::start().await.unwrap();
+        for (code, status) in [(200, "passing"), (429, "warning"), (503, "critical")] {
+            let body = health(status).to_string();
+            let path = "v1/agent/health/service/id/local-1";
+            agent.expect("GET", path, Reply::new(code, body.clone()));
+            let body = serde_json::json!([health(status)]).to_string();
+            let path = "v1/agent/health/service/name/local";
+            agent.expect("GET", path, Reply::new(code, body));
+        }
+        agent.expect(
+            "GET",
+            "v1/agent/health/service/id/local-1",
+            Reply::new(500, "rpc error"),
+        );
+        let client = Client::new(agent.url()).unwrap();
+        for status in ["passing", "warning", "critical"] {
+            let health = Agent::service_health_by_id(&client, "local-1")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(health.aggregated_status(), status);
+            let health = Agent::service_health_by_name(&client, "local")
+                .await
+                .unwrap();
+            assert_eq!(health[0].aggregated_status(), status);
+        }
+        assert!(
+            Agent::service_health_by_id(&client, "local-1")
+                .await
+                .is_err()
+        );
+        agent.verify().unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_reads_telemetry() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let metrics = Agent::metrics(&client).await.unwrap();
+        assert!(
+            metrics
+                .gauges()
+                .iter()
+                .any(|g| g.name().ends_with("runtime.num_goroutines"))
+        );
+        let host = Agent::host(&client).await.unwrap();
+        assert!(!host.host().hostname().is_empty());
+        assert!(host.memory().total() > 0);
+    }
+}
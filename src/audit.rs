@@ -0,0 +1,249 @@
+//! Application-side trail of writes, enabled with the `audit` feature.
+//!
+//! With `ClientBuilder::audit` set, every PUT, POST, PATCH and DELETE the
+//! client sends, including KV writes and transactions, is reported to an
+//! [`AuditSink`] once its outcome is known:
+//!
+//! ```ignore
+//! let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+//! let client = Client::builder("http://localhost:8500")
+//!     .token(token)
+//!     .audit(tx)
+//!     .audit_accessor("2b778dd9-f5f1-6f29-b4b4-9a5fa948757a")
+//!     .build()?;
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use reqwest::Method;
+
+use crate::Response;
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    timestamp: SystemTime,
+    method: Method,
+    path: String,
+    dc: Option<String>,
+    accessor: Option<String>,
+    request_id: Option<String>,
+    outcome: Outcome,
+}
+
+impl AuditEntry {
+    /// When the outcome was known.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Request path, e.g. `/v1/kv/app/config`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn dc(&self) -> Option<&str> {
+        self.dc.as_deref()
+    }
+
+    /// Accessor ID of the token, as set with `ClientBuilder::audit_accessor`.
+    pub fn accessor(&self) -> Option<&str> {
+        self.accessor.as_deref()
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Consul answered with this status.
+    Status(u16),
+    /// Consul answered 200 with `false`: a CAS write, lock acquire or
+    /// release was not applied.
+    Refused,
+    /// No response was received.
+    Failed(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Status(status) => write!(f, "{status}"),
+            Outcome::Refused => f.write_str("refused"),
+            Outcome::Failed(error) => write!(f, "failed: {error}"),
+        }
+    }
+}
+
+/// Receiver of audit entries. It is called inline after each write, so
+/// slow sinks such as files should hand entries off, e.g. through a channel.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, entry: &AuditEntry);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditEntry) + Send + Sync,
+{
+    fn record(&self, entry: &AuditEntry) {
+        self(entry)
+    }
+}
+
+impl AuditSink for tokio::sync::mpsc::UnboundedSender<AuditEntry> {
+    fn record(&self, entry: &AuditEntry) {
+        // A closed receiver means nobody listens anymore.
+        let _ = self.send(entry.clone());
+    }
+}
+
+/// Logs entries as `tracing` events with the `consul::audit` target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+impl AuditSink for TracingSink {
+    fn record(&self, entry: &AuditEntry) {
+        tracing::info!(
+            target: "consul::audit",
+            method = %entry.method,
+            path = entry.path,
+            dc = entry.dc.as_deref(),
+            accessor = entry.accessor.as_deref(),
+            request_id = entry.request_id.as_deref(),
+            outcome = %entry.outcome,
+            "Consul write"
+        );
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Audit {
+    pub(crate) sink: Arc<dyn AuditSink>,
+    pub(crate) accessor: Option<String>,
+}
+
+impl Audit {
+    pub(crate) fn is_write(method: &Method) -> bool {
+        matches!(
+            *method,
+            Method::PUT | Method::POST | Method::PATCH | Method::DELETE
+        )
+    }
+
+    pub(crate) fn record(
+        &self,
+        method: Method,
+        url: &url::Url,
+        request_id: Option<&str>,
+        rs: &Result<Response, anyhow::Error>,
+    ) {
+        let outcome = match rs {
+            Ok(rs) if rs.status == 200 && rs.body.trim_ascii() == b"false" => Outcome::Refused,
+            Ok(rs) => Outcome::Status(rs.status),
+            Err(e) => Outcome::Failed(format!("{e:#}")),
+        };
+        let dc = url
+            .query_pairs()
+            .find(|(k, _)| k == "dc")
+            .map(|(_, v)| v.into_owned());
+        self.sink.record(&AuditEntry {
+            timestamp: SystemTime::now(),
+            method,
+            path: url.path().to_string(),
+            dc,
+            accessor: self.accessor.clone(),
+            request_id: request_id.map(str::to_owned),
+            outcome,
+        });
+    }
+}
+
+impl fmt::Debug for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Audit")
+            .field("accessor", &self.accessor)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_records_outcomes() {
+        let entries = Arc::new(std::sync::Mutex::new(vec![]));
+        let seen = entries.clone();
+        let audit = Audit {
+            sink: Arc::new(move |entry: &AuditEntry| seen.lock().unwrap().push(entry.clone())),
+            accessor: None,
+        };
+        assert!(Audit::is_write(&Method::DELETE));
+        assert!(!Audit::is_write(&Method::GET));
+        let url = url::Url::parse("http://localhost:8500/v1/txn").unwrap();
+        audit.record(
+            Method::PUT,
+            &url,
+            Some("req-1"),
+            &Err(anyhow::anyhow!("refused")),
+        );
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries[0].path(), "/v1/txn");
+        assert_eq!(entries[0].request_id(), Some("req-1"));
+        assert_eq!(entries[0].outcome().to_string(), "failed: refused");
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_records_writes() {
+        use crate::mock::{MockAgent, Reply};
+        use crate::{Client, Kv};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("PUT", "v1/kv/app/config", Reply::json(true.into()));
+        agent.expect("GET", "v1/kv/app/config", Reply::kv("app/config", b"1"));
+        agent.expect("DELETE", "v1/kv/app/config", Reply::new(500, "boom"));
+        agent.expect("PUT", "v1/kv/app/config", Reply::json(false.into()));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = Client::builder(agent.url())
+            .datacenter("dc1")
+            .audit_accessor("accessor-1")
+            .audit(tx)
+            .build()
+            .unwrap();
+        Kv::new("app/config")
+            .put_string(&client, "1")
+            .await
+            .unwrap();
+        Kv::new("app/config").get(&client).await.unwrap();
+        Kv::new("app/config").delete(&client).await.unwrap();
+        let put = rx.recv().await.unwrap();
+        assert_eq!(put.method(), Method::PUT);
+        assert_eq!(put.path(), "/v1/kv/app/config");
+        assert_eq!(put.dc(), Some("dc1"));
+        assert_eq!(put.accessor(), Some("accessor-1"));
+        assert_eq!(put.outcome(), &Outcome::Status(200));
+        let delete = rx.recv().await.unwrap();
+        assert_eq!(delete.method(), Method::DELETE);
+        assert_eq!(delete.outcome(), &Outcome::Status(500));
+        let written = Kv::new("app/config")
+            .cas(7)
+            .put_with_result(&client)
+            .await
+            .unwrap();
+        assert!(!written);
+        assert_eq!(rx.recv().await.unwrap().outcome(), &Outcome::Refused);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Synchronous client built on `reqwest::blocking`, for CLI tools and build
+//! scripts that don't run a tokio runtime.
+//!
+//! Requests are described with the same builders as the async API and handed
+//! to the client, e.g. `client.get(Kv::new("key"))`. Like any
+//! `reqwest::blocking` client it must not be used from within an async
+//! context.
+//!
+//! Only a subset of the API is covered: KV reads, writes and listings,
+//! service health, and the `leader` and `peers` status calls, plus any
+//! [`Endpoint`] through [`Client::execute`]. The client has no token,
+//! failover, retry, rate limit or middleware support; use the async
+//! [`crate::Client`] for anything else.
+
+use std::io::Read;
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::Serialize;
+
+use crate::endpoint::Endpoint;
+use crate::health::{Health, ServiceEntry};
+use crate::{Helper, Kv, Record, Response};
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: url::Url,
+    client: reqwest::blocking::Client,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ClientBuilder {
+    url: String,
+    timeout: Option<Duration>,
+}
+
+impl Client {
+    pub fn new<S>(url: S) -> Result<Self, anyhow::Error>
+    where
+        S: Into<String>,
+    {
+        Self::builder(url).build()
+    }
+
+    pub fn builder<S>(url: S) -> ClientBuilder
+    where
+        S: Into<String>,
+    {
+        ClientBuilder::new(url)
+    }
+
+    pub fn send_request(&self, method: Method, kv: Kv) -> Result<Response, anyhow::Error> {
+        self.send_endpoint(method, kv)
+    }
+
+    /// Sends a request described by an [`Endpoint`], see [`crate::endpoint`].
+    pub fn execute<E>(&self, endpoint: E) -> Result<Response, anyhow::Error>
+    where
+        E: Endpoint,
+    {
+        self.send_endpoint(endpoint.method(), endpoint)
+    }
+
+    pub fn get(&self, kv: Kv) -> Result<Option<Record>, anyhow::Error> {
+        let rs = self.send_request(Method::GET, kv)?;
+        if rs.status == 404 {
+            return Ok(None);
+        };
+        let mut key: Vec<Record> = rs.error_for_status()?.try_into()?;
+        Ok(key.pop())
+    }
+
+    /// Raw value of a key as a reader, without buffering it in memory.
+    pub fn get_reader(&self, kv: Kv) -> Result<Option<impl Read>, anyhow::Error> {
+        let kv = kv.raw(true);
+        let url = crate::request_url(&self.url, &kv.path, &kv.query)?;
+        let rs = self
+            .client
+            .get(url)
+            .apply_if(kv.timeout.or(self.timeout), |k, v| k.timeout(v))
+            .send()?;
+        if rs.status() == 404 {
+            return Ok(None);
+        };
+        if !rs.status().is_success() {
+            let status = rs.status().as_u16();
+            let path = rs.url().path().to_string();
+            let body = rs.bytes()?;
+            return Err(crate::error::status_error(
+                &Method::GET,
+                &path,
+                status,
+                &body,
+            ));
+        }
+        Ok(Some(rs))
+    }
+
+    pub fn put(&self, kv: Kv) -> Result<Response, anyhow::Error> {
+        self.send_request(Method::PUT, kv)
+    }
+
+    pub fn delete(&self, kv: Kv) -> Result<Response, anyhow::Error> {
+        self.send_request(Method::DELETE, kv)
+    }
+
+    pub fn list(&self, kv: Kv) -> Result<Vec<Record>, anyhow::Error> {
+        let rs = self.send_request(Method::GET, kv.recurse(true))?;
+        if rs.status == 404 {
+            return Ok(vec![]);
+        };
+        rs.error_for_status()?.try_into()
+    }
+
+    pub fn health(&self, health: Health) -> Result<Vec<ServiceEntry>, anyhow::Error> {
+        let limit = health.output_limit;
+        let rs = self.send_endpoint(Method::GET, health)?;
+        Health::entries(rs, limit)
+    }
+
+    /// Raft leader address, or `None` while the cluster has no leader.
+    pub fn leader(&self) -> Result<Option<String>, anyhow::Error> {
+        let leader: String = self
+            .send(Method::GET, "v1/status/leader", &(), None, None)?
+            .decode()?;
+        Ok((!leader.is_empty()).then_some(leader))
+    }
+
+    pub fn peers(&self) -> Result<Vec<String>, anyhow::Error> {
+        self.send(Method::GET, "v1/status/peers", &(), None, None)?
+            .decode()
+    }
+
+    fn send_endpoint<E>(&self, method: Method, mut endpoint: E) -> Result<Response, anyhow::Error>
+    where
+        E: Endpoint,
+    {
+        let payload = endpoint.take_payload();
+        let body = endpoint.take_body();
+        self.send_with_timeout(
+            method,
+            &endpoint.path(),
+            endpoint.query(),
+            payload,
+            body,
+            endpoint.timeout(),
+        )
+    }
+
+    fn send<Q>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &Q,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+    ) -> Result<Response, anyhow::Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.send_with_timeout(method, path, query, payload, body, None)
+    }
+
+    fn send_with_timeout<Q>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &Q,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+        timeout: Option<Duration>,
+    ) -> Result<Response, anyhow::Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let url = crate::request_url(&self.url, path, query)?;
+        let timeout = timeout
+            .or(self.timeout)
+            .map(|timeout| timeout + crate::blocking_wait(&url));
+        let raw = crate::is_raw(&url);
+        let rs = self
+            .client
+            .request(method.clone(), url)
+            .apply_if(timeout, |k, v| k.timeout(v))
+            .apply_if(payload, |k, v| k.json(&v))
+            .apply_if(body, |k, v| k.body(v))
+            .send()?;
+        let status = rs.status().as_u16();
+        let index = crate::consul_index(rs.headers());
+        let url = rs.url().clone();
+        let body = rs.bytes()?;
+        Ok(Response::new(method, &url, status, index, body, raw))
+    }
+}
+
+impl ClientBuilder {
+    pub fn new<S>(url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            url: url.into(),
+            timeout: None,
+        }
+    }
+
+    /// Default timeout for every request. Blocking queries get their `wait`
+    /// added on top so they are not cut off mid-poll.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, anyhow::Error> {
+        // reqwest's blocking client defaults to a 30s timeout, which would
+        // cut blocking queries short; timeouts are applied per request.
+        let mut client = reqwest::blocking::Client::builder().timeout(None);
+        let mut url = self.url.parse()?;
+        if let Some(path) = crate::unix_socket(std::slice::from_ref(&url))? {
+            #[cfg(unix)]
+            {
+                client = client.unix_socket(path);
+            }
+            url = crate::SOCKET_BASE.parse()?;
+        }
+        let client = client.build()?;
+        Ok(Client {
+            url,
+            client,
+            timeout: self.timeout,
+        })
+    }
+}
+
+impl Helper for reqwest::blocking::RequestBuilder {
+    fn apply_if<T, F>(self, val: Option<T>, fun: F) -> Self
+    where
+        Self: Sized,
+        F: FnOnce(Self, T) -> Self,
+    {
+        if let Some(val) = val {
+            fun(self, val)
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for path in ["blocking/key0", "blocking/key1"] {
+            client
+                .put(Kv::new(path).body(b"value".to_vec()))
+                .unwrap()
+                .error_for_status()
+                .unwrap();
+        }
+        assert_eq!(client.list(Kv::new("blocking/")).unwrap().len(), 2);
+        let record = client.get(Kv::new("blocking/key0")).unwrap().unwrap();
+        assert_eq!(record.value_as_slice().unwrap(), b"value");
+        let mut value = String::new();
+        client
+            .get_reader(Kv::new("blocking/key1"))
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut value)
+            .unwrap();
+        assert_eq!(value, "value");
+        for path in ["blocking/key0", "blocking/key1"] {
+            client.delete(Kv::new(path)).unwrap();
+        }
+        assert!(client.get(Kv::new("blocking/key0")).unwrap().is_none());
+        assert!(client.leader().unwrap().is_some());
+        assert!(!client.health(Health::service("consul")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+
+use crate::{Client, Response};
+
+const MAX_WAIT: Duration = Duration::from_secs(300);
+const MAX_AGE: Duration = Duration::from_secs(5);
+
+struct Entry<V> {
+    index: Option<u64>,
+    value: Option<Arc<V>>,
+    fetched: Instant,
+}
+
+/// Read-through cache mapping user keys to Consul lookups.
+///
+/// Values are deserialized from the raw response body once per
+/// `X-Consul-Index`, so revalidating an unchanged key is cheap. Entries
+/// older than [`CachedGetter::max_age`], 5 seconds by default, are
+/// revalidated on the next [`CachedGetter::get_or_fetch`].
+pub struct CachedGetter<K, V, F> {
+    fetch: F,
+    max_age: Duration,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+}
+
+impl<K, V, F, Fut> CachedGetter<K, V, F>
+where
+    K: Eq + Hash + Clone,
+    V: DeserializeOwned,
+    F: Fn(&K) -> Fut,
+    Fut: Future<Output = Result<Response, anyhow::Error>>,
+{
+    pub fn new(fetch: F) -> Self {
+        Self {
+            fetch,
+            max_age: MAX_AGE,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long a value is served without asking Consul again.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub async fn get_or_fetch(&self, key: &K) -> Result<Option<Arc<V>>, anyhow::Error> {
+        if let Some(entry) = self.entries().get(key)
+            && entry.fetched.elapsed() < self.max_age
+        {
+            return Ok(entry.value.clone());
+        }
+        self.revalidate(key).await
+    }
+
+    pub async fn revalidate(&self, key: &K) -> Result<Option<Arc<V>>, anyhow::Error> {
+        let rs = (self.fetch)(key).await?;
+        let index = rs.index();
+        if let Some(entry) = self.entries().get_mut(key)
+            && index.is_some()
+            && entry.index == index
+        {
+            entry.fetched = Instant::now();
+            return Ok(entry.value.clone());
+        }
+        let value = match rs.status {
+            404 => None,
+            200 => Some(Arc::new(serde_json::from_slice::<V>(&rs.body)?)),
+            _ => return Err(rs.into_error()),
+        };
+        self.entries().insert(
+            key.clone(),
+            Entry {
+                index,
+                value: value.clone(),
+                fetched: Instant::now(),
+            },
+        );
+        Ok(value)
+    }
+
+    pub fn index(&self, key: &K) -> Option<u64> {
+        self.entries().get(key).and_then(|entry| entry.index)
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.entries().remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<K, Entry<V>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A cached read and the task keeping it fresh. The generation tells that
+/// task apart from one started for an earlier entry under the same key.
+#[derive(Debug)]
+struct CachedResponse {
+    response: Response,
+    last_read: Instant,
+    generation: u64,
+    poller: Option<tokio::task::AbortHandle>,
+}
+
+/// GET response cache behind [`crate::ClientBuilder::response_cache`].
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    idle: Duration,
+    generations: AtomicU64,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(idle: Duration) -> Self {
+        Self {
+            idle,
+            generations: AtomicU64::new(0),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) async fn get(
+        &self,
+        client: &Client,
+        url: url::Url,
+        timeout: Option<Duration>,
+    ) -> Result<Response, anyhow::Error> {
+        let key = url.to_string();
+        if let Some(entry) = self.entries().get_mut(&key) {
+            entry.last_read = Instant::now();
+            // No request was sent, so there is no request ID to report.
+            let mut rs = entry.response.clone();
+            rs.request_id = None;
+            return Ok(rs);
+        }
+        let rs = client
+            .execute(Method::GET, url.clone(), None, None, timeout)
+            .await?;
+        if cacheable(&rs) {
+            let mut entries = self.entries();
+            match entries.get_mut(&key) {
+                Some(entry) => {
+                    entry.response = rs.clone();
+                    entry.last_read = Instant::now();
+                }
+                None => {
+                    let generation = self.generations.fetch_add(1, Ordering::Relaxed);
+                    let poller =
+                        tokio::spawn(revalidate(client.clone(), key.clone(), url, generation));
+                    let entry = CachedResponse {
+                        response: rs.clone(),
+                        last_read: Instant::now(),
+                        generation,
+                        poller: Some(poller.abort_handle()),
+                    };
+                    entries.insert(key, entry);
+                }
+            }
+        }
+        Ok(rs)
+    }
+
+    /// Drops the reads a write to `url` may have changed: the path itself,
+    /// its parent prefixes and the keys below it. Transactions drop every
+    /// KV read.
+    pub(crate) fn invalidate(&self, url: &url::Url) {
+        let written = url.path();
+        self.entries().retain(|_, entry| {
+            let cached = entry.response.path.as_str();
+            let keep = if written == "/v1/txn" {
+                !cached.starts_with("/v1/kv/")
+            } else {
+                !written.starts_with(cached) && !cached.starts_with(written)
+            };
+            if !keep && let Some(poller) = &entry.poller {
+                poller.abort();
+            }
+            keep
+        });
+    }
+
+    /// Pollers of current entries, to check invalidated ones are gone.
+    #[cfg(all(test, feature = "mock"))]
+    fn pollers(&self) -> Vec<tokio::task::AbortHandle> {
+        self.entries()
+            .values()
+            .filter_map(|entry| entry.poller.clone())
+            .collect()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, CachedResponse>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn cacheable(rs: &Response) -> bool {
+    rs.index.is_some() && matches!(rs.status, 200 | 404)
+}
+
+/// Long-polls `url` while its entry is read, and exits once the entry is
+/// gone or replaced by a newer generation.
+async fn revalidate(client: Client, key: String, url: url::Url, generation: u64) {
+    let Some(cache) = client.cache.clone() else {
+        return;
+    };
+    let remove = |cache: &ResponseCache| {
+        let mut entries = cache.entries();
+        if entries
+            .get(&key)
+            .is_some_and(|e| e.generation == generation)
+        {
+            entries.remove(&key);
+        }
+    };
+    loop {
+        let index = match cache.entries().get(&key) {
+            Some(entry) if entry.generation != generation => return,
+            Some(entry) if entry.last_read.elapsed() < cache.idle => entry.response.index,
+            _ => None,
+        };
+        let Some(index) = index else {
+            remove(&cache);
+            return;
+        };
+        let mut poll = url.clone();
+        poll.query_pairs_mut()
+            .append_pair("index", &index.to_string())
+            .append_pair(
+                "wait",
+                &format!("{}ms", cache.idle.min(MAX_WAIT).as_millis()),
+            );
+        match client.execute(Method::GET, poll, None, None, None).await {
+            Ok(rs) if cacheable(&rs) => {
+                if rs.index != Some(index)
+                    && let Some(entry) = cache.entries().get_mut(&key)
+                    && entry.generation == generation
+                {
+                    entry.response = rs;
+                }
+            }
+            _ => {
+                remove(&cache);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kv;
+
+    #[tokio::test]
+    async fn it_caches() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("cache/key0")
+            .payload(serde_json::json!({"Some":"Value"}))
+            .put(&client)
+            .await
+            .unwrap();
+        let cache = CachedGetter::new(|key: &String| {
+            let client = client.clone();
+            let kv = Kv::new(format!("cache/{key}")).raw(true);
+            async move { kv.send_request(Method::GET, &client).await }
+        });
+        let key = "key0".to_string();
+        let value: Arc<serde_json::Value> = cache.get_or_fetch(&key).await.unwrap().unwrap();
+        assert_eq!(value["Some"], "Value");
+        let again = cache.revalidate(&key).await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&value, &again));
+        let missing = cache.get_or_fetch(&"missing".to_string()).await.unwrap();
+        assert!(missing.is_none());
+        Kv::new("cache/key0").delete(&client).await.unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_expires_cached_values() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/cache/key", Reply::new(200, "1").index(1));
+        agent.expect("GET", "v1/kv/cache/key", Reply::new(200, "1").index(1));
+        agent.expect("GET", "v1/kv/cache/key", Reply::new(200, "2").index(2));
+        let client = Client::new(agent.url()).unwrap();
+        let cache = CachedGetter::new(|key: &String| {
+            let client = client.clone();
+            let kv = Kv::new(format!("cache/{key}")).raw(true);
+            async move { kv.send_request(Method::GET, &client).await }
+        })
+        .max_age(Duration::from_millis(50));
+        let key = "key".to_string();
+        let value: Arc<u64> = cache.get_or_fetch(&key).await.unwrap().unwrap();
+        let cached = cache.get_or_fetch(&key).await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&value, &cached));
+        assert_eq!(agent.requests().len(), 1);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let unchanged = cache.get_or_fetch(&key).await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&value, &unchanged));
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let changed = cache.get_or_fetch(&key).await.unwrap().unwrap();
+        assert_eq!(*changed, 2);
+        assert_eq!(cache.index(&key), Some(2));
+        agent.verify().unwrap();
+    }
+
+    #[test]
+    fn it_invalidates_written_paths() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        for path in ["v1/kv/app/a", "v1/kv/app/", "v1/kv/app/a/b", "v1/kv/other"] {
+            let url: url::Url = format!("http://localhost:8500/{path}").parse().unwrap();
+            let response = Response::new(Method::GET, &url, 200, Some(1), Default::default(), true);
+            let entry = CachedResponse {
+                response,
+                last_read: Instant::now(),
+                generation: 0,
+                poller: None,
+            };
+            cache.entries().insert(url.to_string(), entry);
+        }
+        let cached = |cache: &ResponseCache| {
+            let mut paths: Vec<_> = cache
+                .entries()
+                .values()
+                .map(|entry| entry.response.path.clone())
+                .collect();
+            paths.sort();
+            paths
+        };
+        cache.invalidate(&"http://localhost:8500/v1/kv/app/a?cas=1".parse().unwrap());
+        assert_eq!(cached(&cache), ["/v1/kv/other"]);
+        cache.invalidate(&"http://localhost:8500/v1/txn".parse().unwrap());
+        assert!(cached(&cache).is_empty());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_stops_pollers_of_invalidated_reads() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let path = "v1/kv/cache/polled";
+        let client = Client::builder(agent.url())
+            .response_cache(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let mut pollers = vec![];
+        for index in 1..=3 {
+            agent.expect("GET", path, Reply::new(200, "1").index(index));
+            let hung = Reply::new(200, "1")
+                .index(index)
+                .delay(Duration::from_secs(60));
+            agent.expect("GET", path, hung);
+            agent.expect("PUT", path, Reply::json(true.into()));
+            let rs = Kv::new("cache/polled")
+                .raw(true)
+                .send_request(Method::GET, &client);
+            assert_eq!(rs.await.unwrap().raw(), "1");
+            // Let the poller send its blocking query before the write.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            pollers.extend(client.cache.as_ref().unwrap().pollers());
+            Kv::new("cache/polled")
+                .body(b"2".to_vec())
+                .put(&client)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(pollers.len(), 3);
+        assert!(pollers.iter().all(|poller| poller.is_finished()));
+        agent.expect("GET", path, Reply::new(200, "2").index(4));
+        let hung = Reply::new(200, "2").index(4).delay(Duration::from_secs(60));
+        agent.expect("GET", path, hung);
+        let rs = Kv::new("cache/polled")
+            .raw(true)
+            .send_request(Method::GET, &client);
+        assert_eq!(rs.await.unwrap().raw(), "2");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(client.cache.as_ref().unwrap().pollers().len(), 1);
+        agent.verify().unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_applies_request_timeouts_to_cached_reads() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let slow = Reply::new(200, "1").index(1).delay(Duration::from_secs(5));
+        agent.expect("GET", "v1/kv/cache/slow", slow);
+        let client = Client::builder(agent.url())
+            .response_cache(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let rs = Kv::new("cache/slow")
+            .raw(true)
+            .timeout(Duration::from_millis(100))
+            .send_request(Method::GET, &client);
+        let rs = tokio::time::timeout(Duration::from_secs(2), rs)
+            .await
+            .unwrap();
+        assert!(rs.is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_drops_request_ids_from_cache_hits() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let path = "v1/kv/cache/hit";
+        agent.expect("GET", path, Reply::new(200, "1").index(1));
+        let hung = Reply::new(200, "1").index(1).delay(Duration::from_secs(60));
+        agent.expect("GET", path, hung);
+        let client = Client::builder(agent.url())
+            .response_cache(Duration::from_secs(60))
+            .request_id(|| "req-1".to_string())
+            .build()
+            .unwrap();
+        let read = || {
+            Kv::new("cache/hit")
+                .raw(true)
+                .send_request(Method::GET, &client)
+        };
+        assert_eq!(read().await.unwrap().request_id(), Some("req-1"));
+        assert_eq!(read().await.unwrap().request_id(), None);
+    }
+
+    #[tokio::test]
+    async fn it_revalidates_responses() {
+        let client = Client::builder("http://localhost:8500")
+            .response_cache(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        Kv::new("cache/key1")
+            .body(b"1".to_vec())
+            .put(&client)
+            .await
+            .unwrap();
+        let rs = Kv::new("cache/key1")
+            .raw(true)
+            .send_request(Method::GET, &client);
+        assert_eq!(rs.await.unwrap().raw(), "1");
+        Kv::new("cache/key1")
+            .body(b"2".to_vec())
+            .put(&client)
+            .await
+            .unwrap();
+        let rs = Kv::new("cache/key1")
+            .raw(true)
+            .send_request(Method::GET, &client);
+        assert_eq!(rs.await.unwrap().raw(), "2");
+        let responses = client.transfer_stats().responses();
+        let rs = Kv::new("cache/key1")
+            .raw(true)
+            .send_request(Method::GET, &client);
+        assert_eq!(rs.await.unwrap().raw(), "2");
+        assert_eq!(client.transfer_stats().responses(), responses);
+        Kv::new("cache/key1").delete(&client).await.unwrap();
+        assert!(Kv::new("cache/key1").get(&client).await.unwrap().is_none());
+    }
+}
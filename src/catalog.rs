@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::health::{CheckStatus, Node, ServiceAddress, Weights};
+
+pub struct Catalog;
+
+/// Query for the nodes of a datacenter, built by [`Catalog::nodes`].
+#[derive(Default)]
+pub struct CatalogNodes {
+    query: NodesQuery,
+}
+
+#[derive(Default, Serialize)]
+struct NodesQuery {
+    dc: Option<String>,
+    near: Option<String>,
+}
+
+impl CatalogNodes {
+    pub fn dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.dc = Some(dc.into());
+        self
+    }
+
+    /// Sorts nodes by estimated round trip time from `node`; `_agent`
+    /// means the agent serving the request. Nodes without network
+    /// coordinates come last.
+    pub fn near<S>(mut self, node: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.near = Some(node.into());
+        self
+    }
+
+    pub async fn get(self, client: &Client) -> Result<Vec<Node>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/catalog/nodes", &self.query, None, None)
+            .await?
+            .decode()
+    }
+}
+
+impl Catalog {
+    /// Known datacenters, sorted by estimated round trip time from the
+    /// agent's datacenter.
+    pub async fn datacenters(client: &Client) -> Result<Vec<String>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/catalog/datacenters", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Registers an external node, optionally with a service and checks.
+    pub async fn register(
+        client: &Client,
+        registration: &Registration,
+    ) -> Result<(), anyhow::Error> {
+        client
+            .send(
+                Method::PUT,
+                "v1/catalog/register",
+                &(),
+                Some(serde_json::to_value(registration)?),
+                None,
+            )
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Removes a node, or only one of its services or checks.
+    pub async fn deregister(
+        client: &Client,
+        deregistration: &Deregistration,
+    ) -> Result<(), anyhow::Error> {
+        client
+            .send(
+                Method::PUT,
+                "v1/catalog/deregister",
+                &(),
+                Some(serde_json::to_value(deregistration)?),
+                None,
+            )
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Nodes of a datacenter, sorted by name unless [`CatalogNodes::near`]
+    /// is set.
+    pub fn nodes() -> CatalogNodes {
+        CatalogNodes::default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Registration {
+    node: String,
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datacenter: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    tagged_addresses: HashMap<String, String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    node_meta: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<ServiceRegistration>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checks: Vec<CheckRegistration>,
+    skip_node_update: bool,
+}
+
+impl Registration {
+    pub fn new<N, A>(node: N, address: A) -> Self
+    where
+        N: Into<String>,
+        A: Into<String>,
+    {
+        Self {
+            node: node.into(),
+            address: address.into(),
+            datacenter: None,
+            tagged_addresses: HashMap::new(),
+            node_meta: HashMap::new(),
+            service: None,
+            checks: vec![],
+            skip_node_update: false,
+        }
+    }
+
+    pub fn datacenter<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.datacenter = Some(dc.into());
+        self
+    }
+
+    /// Node address by kind, e.g. `lan` or `wan`.
+    pub fn tagged_address<K, A>(mut self, kind: K, address: A) -> Self
+    where
+        K: Into<String>,
+        A: Into<String>,
+    {
+        self.tagged_addresses.insert(kind.into(), address.into());
+        self
+    }
+
+    pub fn node_meta<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.node_meta.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn service(mut self, service: ServiceRegistration) -> Self {
+        self.service = Some(service);
+        self
+    }
+
+    pub fn check(mut self, check: CheckRegistration) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Leaves an existing node's address, tagged addresses and metadata
+    /// untouched.
+    pub fn skip_node_update(mut self, value: bool) -> Self {
+        self.skip_node_update = value;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceRegistration {
+    #[serde(rename = "ID", skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    service: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    tagged_addresses: HashMap<String, ServiceAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weights: Option<Weights>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    meta: HashMap<String, String>,
+}
+
+/// Service block of an agent config file. Keys are accepted in the
+/// documented snake_case and in the API's PascalCase.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceDefinition {
+    #[serde(alias = "ID", alias = "Id")]
+    id: Option<String>,
+    #[serde(alias = "Name")]
+    name: String,
+    #[serde(default, alias = "Tags")]
+    tags: Vec<String>,
+    #[serde(alias = "Address")]
+    address: Option<String>,
+    #[serde(default, alias = "TaggedAddresses")]
+    tagged_addresses: HashMap<String, AddressDefinition>,
+    #[serde(alias = "Port")]
+    port: Option<u16>,
+    #[serde(alias = "Weights")]
+    weights: Option<WeightsDefinition>,
+    #[serde(default, alias = "Meta")]
+    meta: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddressDefinition {
+    #[serde(alias = "Address")]
+    address: String,
+    #[serde(alias = "Port")]
+    port: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WeightsDefinition {
+    #[serde(alias = "Passing")]
+    passing: u32,
+    #[serde(default = "one", alias = "Warning")]
+    warning: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Definition fields a [`ServiceRegistration`] can't carry. Dropping checks
+/// would register the service as always healthy.
+const UNSUPPORTED_FIELDS: [&str; 6] = [
+    "check",
+    "checks",
+    "connect",
+    "kind",
+    "proxy",
+    "enable_tag_override",
+];
+
+impl ServiceRegistration {
+    /// Reads a service definition in the format of Consul config files,
+    /// either wrapped as `{"service": {...}}` or bare. Only JSON files are
+    /// supported. Definitions with fields a registration can't carry, such
+    /// as checks or Connect settings, or with unknown fields are rejected.
+    pub fn from_json_file<P>(path: P) -> Result<Self, anyhow::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let contents = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+        let value = serde_json::from_slice(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON in {}: {e}", path.display()))?;
+        Self::from_value(value)
+    }
+
+    /// Service definition from an already parsed config document; see
+    /// [`ServiceRegistration::from_json_file`].
+    pub fn from_value(mut value: serde_json::Value) -> Result<Self, anyhow::Error> {
+        if let Some(object) = value.as_object_mut() {
+            if object.contains_key("services") || object.contains_key("Services") {
+                anyhow::bail!("Expected a single service definition, found a list");
+            }
+            let wrapper = ["service", "Service"]
+                .into_iter()
+                .find(|key| object.get(*key).is_some_and(serde_json::Value::is_object));
+            if let Some(key) = wrapper {
+                if let Some(other) = object.keys().find(|k| *k != key) {
+                    anyhow::bail!("Unexpected {other:?} next to the service definition");
+                }
+                value = object.remove(key).unwrap_or_default();
+            }
+        }
+        if let Some(object) = value.as_object()
+            && let Some(field) = object.keys().find(|key| {
+                let key = key.to_ascii_lowercase().replace('_', "");
+                UNSUPPORTED_FIELDS
+                    .iter()
+                    .any(|field| field.replace('_', "") == key)
+            })
+        {
+            anyhow::bail!("Service definition field {field:?} is not supported");
+        }
+        let definition: ServiceDefinition = serde_json::from_value(value)?;
+        Ok(Self {
+            id: definition.id,
+            service: definition.name,
+            tags: definition.tags,
+            address: definition.address,
+            tagged_addresses: definition
+                .tagged_addresses
+                .into_iter()
+                .map(|(kind, a)| (kind, ServiceAddress::new(a.address, a.port)))
+                .collect(),
+            port: definition.port,
+            weights: definition
+                .weights
+                .map(|w| Weights::new(w.passing, w.warning)),
+            meta: definition.meta,
+        })
+    }
+
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: None,
+            service: service.into(),
+            tags: vec![],
+            address: None,
+            tagged_addresses: HashMap::new(),
+            port: None,
+            weights: None,
+            meta: HashMap::new(),
+        }
+    }
+
+    /// Instance ID, defaulting to the service name.
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn address<S>(mut self, address: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Service address by kind, e.g. `lan_ipv4`, `wan` or `virtual`.
+    pub fn tagged_address<K, A>(mut self, kind: K, address: A, port: u16) -> Self
+    where
+        K: Into<String>,
+        A: Into<String>,
+    {
+        let address = ServiceAddress::new(address, port);
+        self.tagged_addresses.insert(kind.into(), address);
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// DNS SRV weights while the instance is passing or warning.
+    pub fn weights(mut self, passing: u32, warning: u32) -> Self {
+        self.weights = Some(Weights::new(passing, warning));
+        self
+    }
+
+    pub fn meta<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Check with a status maintained by the caller, or probed by an external
+/// monitor such as consul-esm through its `definition`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CheckRegistration {
+    #[serde(rename = "CheckID")]
+    check_id: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(rename = "ServiceID", skip_serializing_if = "Option::is_none")]
+    service_id: Option<String>,
+    #[serde(skip_serializing_if = "CheckDefinition::is_empty")]
+    definition: CheckDefinition,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CheckDefinition {
+    #[serde(rename = "HTTP", skip_serializing_if = "Option::is_none")]
+    http: Option<String>,
+    #[serde(rename = "TCP", skip_serializing_if = "Option::is_none")]
+    tcp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+}
+
+impl CheckDefinition {
+    fn is_empty(&self) -> bool {
+        self.http.is_none() && self.tcp.is_none()
+    }
+}
+
+impl CheckRegistration {
+    pub fn new<I, N>(check_id: I, name: N) -> Self
+    where
+        I: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            check_id: check_id.into(),
+            name: name.into(),
+            status: None,
+            notes: None,
+            service_id: None,
+            definition: CheckDefinition::default(),
+        }
+    }
+
+    pub fn status(mut self, status: CheckStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn notes<S>(mut self, notes: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notes = Some(notes.into());
+        self
+    }
+
+    /// Attaches the check to a service instead of the node.
+    pub fn service_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.service_id = Some(id.into());
+        self
+    }
+
+    pub fn http<S>(mut self, url: S, interval: Duration) -> Self
+    where
+        S: Into<String>,
+    {
+        self.definition.http = Some(url.into());
+        self.definition.interval = Some(format!("{}ms", interval.as_millis()));
+        self
+    }
+
+    /// Probes `address` as `host:port`.
+    pub fn tcp<S>(mut self, address: S, interval: Duration) -> Self
+    where
+        S: Into<String>,
+    {
+        self.definition.tcp = Some(address.into());
+        self.definition.interval = Some(format!("{}ms", interval.as_millis()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.definition.timeout = Some(format!("{}ms", timeout.as_millis()));
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Deregistration {
+    node: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datacenter: Option<String>,
+    #[serde(rename = "ServiceID", skip_serializing_if = "Option::is_none")]
+    service_id: Option<String>,
+    #[serde(rename = "CheckID", skip_serializing_if = "Option::is_none")]
+    check_id: Option<String>,
+}
+
+impl Deregistration {
+    /// Removes the node with all its services and checks, unless narrowed
+    /// down with `service_id` or `check_id`.
+    pub fn node<S>(node: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            node: node.into(),
+            datacenter: None,
+            service_id: None,
+            check_id: None,
+        }
+    }
+
+    pub fn datacenter<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.datacenter = Some(dc.into());
+        self
+    }
+
+    pub fn service_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.service_id = Some(id.into());
+        self
+    }
+
+    pub fn check_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.check_id = Some(id.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kv;
+    use crate::agent::Agent;
+    use crate::health::Health;
+
+    #[test]
+    fn it_reads_service_definitions() {
+        let path = std::env::temp_dir().join(format!(
+            "consulite-service-definition-{}.json",
+            std::process::id()
+        ));
+        let definition = serde_json::json!({
+            "service": {
+                "id": "web-1",
+                "name": "web",
+                "tags": ["v1"],
+                "port": 8080,
+                "tagged_addresses": {"wan": {"address": "203.0.113.10", "port": 80}},
+                "weights": {"passing": 10},
+                "meta": {"team": "edge"}
+            }
+        });
+        std::fs::write(&path, definition.to_string()).unwrap();
+        let registration = ServiceRegistration::from_json_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected = serde_json::json!({
+            "ID": "web-1",
+            "Service": "web",
+            "Tags": ["v1"],
+            "TaggedAddresses": {"wan": {"Address": "203.0.113.10", "Port": 80}},
+            "Port": 8080,
+            "Weights": {"Passing": 10, "Warning": 1},
+            "Meta": {"team": "edge"}
+        });
+        assert_eq!(serde_json::to_value(&registration).unwrap(), expected);
+        let bare = ServiceRegistration::from_value(serde_json::json!({"Name": "db", "Port": 5432}))
+            .unwrap();
+        assert_eq!(serde_json::to_value(&bare).unwrap()["Service"], "db");
+        assert!(ServiceRegistration::from_value(serde_json::json!({"services": []})).is_err());
+        for definition in [
+            serde_json::json!({"service": {"name": "web", "checks": [{"ttl": "10s"}]}}),
+            serde_json::json!({"Name": "web", "EnableTagOverride": true}),
+            serde_json::json!({"name": "web", "prot": 8080}),
+            serde_json::json!({"service": {"name": "web"}, "datacenter": "dc1"}),
+        ] {
+            assert!(ServiceRegistration::from_value(definition).is_err());
+        }
+        assert!(ServiceRegistration::from_json_file("/nonexistent/web.json").is_err());
+    }
+
+    #[tokio::test]
+    async fn it_registers_external_services() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let registration = Registration::new("rds", "db.example.internal")
+            .node_meta("external-node", "true")
+            .tagged_address("wan", "203.0.113.10")
+            .service(
+                ServiceRegistration::new("postgres")
+                    .id("postgres-rds")
+                    .tag("primary")
+                    .port(5432)
+                    .weights(10, 1)
+                    .tagged_address("wan", "203.0.113.10", 5432),
+            )
+            .check(
+                CheckRegistration::new("postgres-rds:alive", "RDS alive")
+                    .service_id("postgres-rds")
+                    .status(CheckStatus::Passing),
+            );
+        Catalog::register(&client, &registration).await.unwrap();
+        let entries = Health::service("postgres")
+            .passing(true)
+            .get(&client)
+            .await
+            .unwrap();
+        assert_eq!(entries[0].address(), "db.example.internal");
+        assert_eq!(entries[0].port(), 5432);
+        let service = entries[0].service();
+        assert_eq!(service.weights(), Weights::new(10, 1));
+        let wan = &service.tagged_addresses().unwrap()["wan"];
+        assert_eq!((wan.address(), wan.port()), ("203.0.113.10", 5432));
+        let node = entries[0].node().tagged_addresses().unwrap();
+        assert_eq!(node["wan"], "203.0.113.10");
+        Catalog::deregister(&client, &Deregistration::node("rds"))
+            .await
+            .unwrap();
+        let entries = Health::service("postgres").get(&client).await.unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_sorts_nodes_near_the_agent() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let agent = Agent::self_info(&client).await.unwrap();
+        let agent = agent.config().node_name().to_string();
+        // External nodes have no coordinates, so `near` moves them after the
+        // agent even though they sort first by name.
+        let registration = Registration::new("0-near-external", "192.0.2.20");
+        Catalog::register(&client, &registration).await.unwrap();
+        let nodes = Catalog::nodes().get(&client).await.unwrap();
+        assert_eq!(nodes[0].node(), "0-near-external");
+        let nodes = Catalog::nodes().near("_agent").get(&client).await.unwrap();
+        assert_eq!(nodes[0].node(), agent);
+        assert_eq!(nodes.last().unwrap().node(), "0-near-external");
+        Catalog::deregister(&client, &Deregistration::node("0-near-external"))
+            .await
+            .unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_queries_nodes_near() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let nodes = serde_json::json!([
+            {"ID": "b", "Node": "node-b", "Address": "10.0.0.2", "Datacenter": "dc2"},
+            {"ID": "a", "Node": "node-a", "Address": "10.0.0.1", "Datacenter": "dc2"},
+        ]);
+        agent.expect("GET", "v1/catalog/nodes", Reply::json(nodes));
+        let client = Client::new(agent.url()).unwrap();
+        let nodes = Catalog::nodes()
+            .dc("dc2")
+            .near("node-b")
+            .get(&client)
+            .await
+            .unwrap();
+        let names: Vec<_> = nodes.iter().map(|node| node.node()).collect();
+        assert_eq!(names, ["node-b", "node-a"]);
+        agent.verify().unwrap();
+        let requests = agent.requests();
+        assert_eq!(requests[0].query(), "dc=dc2&near=node-b");
+    }
+
+    #[tokio::test]
+    async fn it_applies_default_datacenter() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let datacenters = Catalog::datacenters(&client).await.unwrap();
+        assert!(!datacenters.is_empty());
+        let nodes = Catalog::nodes()
+            .dc(&datacenters[0])
+            .get(&client)
+            .await
+            .unwrap();
+        assert!(!nodes.is_empty());
+        let client = Client::builder("http://localhost:8500")
+            .datacenter(&datacenters[0])
+            .build()
+            .unwrap();
+        Kv::new("catalog/key0")
+            .body(b"1".to_vec())
+            .put(&client)
+            .await
+            .unwrap();
+        let record = Kv::new("catalog/key0").get(&client).await.unwrap();
+        assert!(record.is_some());
+        let rs = Kv::new("catalog/key0").dc("missing").get(&client).await;
+        assert!(rs.is_err());
+        Kv::new("catalog/key0").delete(&client).await.unwrap();
+    }
+}
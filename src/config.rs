@@ -0,0 +1,215 @@
+//! Typed configuration kept in KV.
+//!
+//! A [`ConfigStore`] binds a struct either to a single key holding it as
+//! JSON, or to a prefix with one JSON-encoded key per top-level field, which
+//! keeps fields editable one at a time in the UI or with `consul kv put`.
+
+use std::marker::PhantomData;
+
+use base64::prelude::*;
+use futures_util::{Stream, StreamExt};
+use reqwest::Method;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::Format;
+use crate::{Client, Kv, Record, Response, TXN_MAX_OPS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Blob,
+    Fields,
+}
+
+#[derive(Debug)]
+pub struct ConfigStore<T> {
+    client: Client,
+    path: String,
+    layout: Layout,
+    _config: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for ConfigStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            path: self.path.clone(),
+            layout: self.layout,
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<T> ConfigStore<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Stores the config as a JSON document at `key`.
+    pub fn blob<S>(client: &Client, key: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client: client.clone(),
+            path: key.into(),
+            layout: Layout::Blob,
+            _config: PhantomData,
+        }
+    }
+
+    /// Stores each top-level field of the config at `<prefix>/<field>`.
+    pub fn fields<S>(client: &Client, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let prefix = prefix.into();
+        Self {
+            client: client.clone(),
+            path: format!("{}/", prefix.trim_end_matches('/')),
+            layout: Layout::Fields,
+            _config: PhantomData,
+        }
+    }
+
+    /// `None` if nothing is stored yet.
+    pub async fn load(&self) -> Result<Option<T>, anyhow::Error> {
+        let rs = self.kv().send_request(Method::GET, &self.client).await?;
+        self.decode(rs)
+    }
+
+    /// Writes the config; in field mode all fields are written in one
+    /// transaction.
+    pub async fn save(&self, config: &T) -> Result<(), anyhow::Error> {
+        match self.layout {
+            Layout::Blob => {
+                self.kv()
+                    .put_serialized(&self.client, config, Format::Json)
+                    .await?
+                    .error_for_status()?;
+            }
+            Layout::Fields => {
+                let serde_json::Value::Object(fields) = serde_json::to_value(config)? else {
+                    anyhow::bail!("Config for {} is not a struct or map", self.path);
+                };
+                if fields.len() > TXN_MAX_OPS {
+                    anyhow::bail!(
+                        "Config for {} has more than {TXN_MAX_OPS} fields",
+                        self.path
+                    );
+                }
+                let ops: Vec<_> = fields
+                    .iter()
+                    .map(|(field, value)| {
+                        serde_json::json!({"KV": {
+                            "Verb": "set",
+                            "Key": format!("{}{field}", self.path),
+                            "Value": BASE64_STANDARD.encode(value.to_string()),
+                        }})
+                    })
+                    .collect();
+                self.client
+                    .send(Method::PUT, "v1/txn", &(), Some(ops.into()), None)
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Yields the stored config and then every changed version of it;
+    /// states in which nothing is stored are skipped.
+    pub fn watch(&self) -> impl Stream<Item = Result<T, anyhow::Error>> + use<T> {
+        Self::updates(self.clone())
+    }
+
+    fn updates(store: Self) -> impl Stream<Item = Result<T, anyhow::Error>> {
+        store
+            .kv()
+            .watch(&store.client)
+            .into_stream()
+            .filter_map(move |event| {
+                let config = event.and_then(|e| store.decode(e.into_response()));
+                async move { config.transpose() }
+            })
+    }
+
+    fn kv(&self) -> Kv {
+        Kv::new(&self.path).recurse(self.layout == Layout::Fields)
+    }
+
+    fn decode(&self, rs: Response) -> Result<Option<T>, anyhow::Error> {
+        if rs.status == 404 {
+            return Ok(None);
+        }
+        let records: Vec<Record> = rs.error_for_status()?.try_into()?;
+        match self.layout {
+            Layout::Blob => records.first().map(Record::value_as).transpose(),
+            Layout::Fields => {
+                let mut fields = serde_json::Map::new();
+                for record in &records {
+                    let Some(field) = record.key().strip_prefix(&self.path) else {
+                        continue;
+                    };
+                    if field.is_empty() || field.contains('/') {
+                        continue;
+                    }
+                    fields.insert(
+                        field.to_string(),
+                        serde_json::from_slice(&record.value_as_slice()?)?,
+                    );
+                }
+                Ok(Some(serde_json::from_value(fields.into())?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct AppConfig {
+        replicas: u32,
+        image: String,
+        debug: bool,
+    }
+
+    #[tokio::test]
+    async fn it_stores_configs() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let config = AppConfig {
+            replicas: 3,
+            image: "web:1.2".to_string(),
+            debug: false,
+        };
+        for store in [
+            ConfigStore::<AppConfig>::blob(&client, "config/blob"),
+            ConfigStore::<AppConfig>::fields(&client, "config/fields"),
+        ] {
+            assert_eq!(store.load().await.unwrap(), None);
+            store.save(&config).await.unwrap();
+            assert_eq!(store.load().await.unwrap().unwrap(), config);
+            let mut updates = Box::pin(store.watch());
+            assert_eq!(updates.next().await.unwrap().unwrap(), config);
+            let changed = AppConfig {
+                replicas: 5,
+                ..config.clone()
+            };
+            store.save(&changed).await.unwrap();
+            assert_eq!(updates.next().await.unwrap().unwrap(), changed);
+        }
+        let replicas = Kv::new("config/fields/replicas")
+            .get(&client)
+            .await
+            .unwrap();
+        assert_eq!(replicas.unwrap().value_as_string().unwrap(), "5");
+        Kv::new("config/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+}
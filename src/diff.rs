@@ -0,0 +1,128 @@
+//! Comparing two KV prefixes, e.g. staging and prod, or the same prefix in
+//! two datacenters to verify replication.
+
+use std::collections::BTreeMap;
+
+use crate::{Client, Kv, Record};
+
+/// Difference for one key, relative to the compared prefixes.
+#[derive(Debug, Clone)]
+pub enum KvDiff {
+    /// Only under the other prefix.
+    Added { key: String, record: Record },
+    /// Only under this prefix.
+    Removed { key: String, record: Record },
+    /// Under both, with a different value or flags.
+    Changed {
+        key: String,
+        from: Record,
+        to: Record,
+    },
+}
+
+impl KvDiff {
+    /// Key relative to the compared prefixes.
+    pub fn key(&self) -> &str {
+        match self {
+            KvDiff::Added { key, .. }
+            | KvDiff::Removed { key, .. }
+            | KvDiff::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl Kv {
+    /// Changes that turn the keys under this prefix into those under
+    /// `other`, sorted by relative key. Set `dc` on either side to compare
+    /// datacenters.
+    pub async fn diff(self, client: &Client, other: Kv) -> Result<Vec<KvDiff>, anyhow::Error> {
+        self.diff_with(client, other, client).await
+    }
+
+    /// Like [`Kv::diff`], reading `other` through a different client, e.g.
+    /// one for another cluster.
+    pub async fn diff_with(
+        self,
+        client: &Client,
+        other: Kv,
+        other_client: &Client,
+    ) -> Result<Vec<KvDiff>, anyhow::Error> {
+        let ours = entries(self, client).await?;
+        let mut theirs = entries(other, other_client).await?;
+        let mut diff = vec![];
+        for (key, from) in ours {
+            match theirs.remove(&key) {
+                None => diff.push(KvDiff::Removed { key, record: from }),
+                Some(to) if from.value != to.value || from.flags != to.flags => {
+                    diff.push(KvDiff::Changed { key, from, to })
+                }
+                Some(_) => {}
+            }
+        }
+        diff.extend(
+            theirs
+                .into_iter()
+                .map(|(key, record)| KvDiff::Added { key, record }),
+        );
+        diff.sort_by(|a, b| a.key().cmp(b.key()));
+        Ok(diff)
+    }
+}
+
+/// Records under the prefix keyed relative to it.
+pub(crate) async fn entries(
+    kv: Kv,
+    client: &Client,
+) -> Result<BTreeMap<String, Record>, anyhow::Error> {
+    let prefix = kv
+        .path
+        .strip_prefix("v1/kv/")
+        .unwrap_or(&kv.path)
+        .to_string();
+    let records = kv.list(client).await?;
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let key = record.key().strip_prefix(&prefix).unwrap_or(record.key());
+            (key.to_string(), record)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_diffs_prefixes() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for (key, value) in [
+            ("diff/a/x", "1"),
+            ("diff/a/y", "2"),
+            ("diff/a/z", "3"),
+            ("diff/b/y", "2"),
+            ("diff/b/z", "4"),
+            ("diff/b/w", "5"),
+        ] {
+            Kv::new(key).put_string(&client, value).await.unwrap();
+        }
+        let diff = Kv::new("diff/a/")
+            .diff(&client, Kv::new("diff/b/"))
+            .await
+            .unwrap();
+        let keys: Vec<_> = diff.iter().map(KvDiff::key).collect();
+        assert_eq!(keys, ["w", "x", "z"]);
+        assert!(matches!(&diff[0], KvDiff::Added { record, .. } if record.key() == "diff/b/w"));
+        assert!(matches!(&diff[1], KvDiff::Removed { .. }));
+        let KvDiff::Changed { from, to, .. } = &diff[2] else {
+            panic!("expected a change, got {:?}", diff[2]);
+        };
+        assert_eq!(from.value_as_string().unwrap(), "3");
+        assert_eq!(to.value_as_string().unwrap(), "4");
+        Kv::new("diff/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+}
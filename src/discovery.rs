@@ -0,0 +1,557 @@
+//! Compiled discovery chains from `v1/discovery-chain/:service`.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+
+#[derive(Default)]
+pub struct DiscoveryChain {
+    path: String,
+    query: ChainQuery,
+    overrides: ChainOverrides,
+}
+
+#[derive(Default, Serialize)]
+struct ChainQuery {
+    dc: Option<String>,
+    #[serde(rename = "compile-dc")]
+    compile_dc: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ChainOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    override_connect_timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    override_protocol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    override_mesh_gateway: Option<MeshGateway>,
+}
+
+impl ChainOverrides {
+    fn is_empty(&self) -> bool {
+        self.override_connect_timeout.is_none()
+            && self.override_protocol.is_none()
+            && self.override_mesh_gateway.is_none()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ChainResponse {
+    chain: CompiledDiscoveryChain,
+}
+
+impl DiscoveryChain {
+    pub fn service<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let path = format!("v1/discovery-chain/{}", name.into());
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.dc = Some(dc.into());
+        self
+    }
+
+    /// Datacenter to compile the chain for, as seen from a proxy there.
+    pub fn compile_dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.compile_dc = Some(dc.into());
+        self
+    }
+
+    pub fn override_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.overrides.override_connect_timeout = Some(format!("{}ms", timeout.as_millis()));
+        self
+    }
+
+    pub fn override_protocol<S>(mut self, protocol: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.overrides.override_protocol = Some(protocol.into());
+        self
+    }
+
+    pub fn override_mesh_gateway<S>(mut self, mode: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.overrides.override_mesh_gateway = Some(MeshGateway { mode: mode.into() });
+        self
+    }
+
+    /// Compiles the chain; overrides switch the request to a POST as the
+    /// endpoint requires.
+    pub async fn get(self, client: &Client) -> Result<CompiledDiscoveryChain, anyhow::Error> {
+        let (method, payload) = if self.overrides.is_empty() {
+            (Method::GET, None)
+        } else {
+            (Method::POST, Some(serde_json::to_value(&self.overrides)?))
+        };
+        let rs: ChainResponse = client
+            .send(method, &self.path, &self.query, payload, None)
+            .await?
+            .decode()?;
+        Ok(rs.chain)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CompiledDiscoveryChain {
+    service_name: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    partition: String,
+    datacenter: String,
+    #[serde(default)]
+    customization_hash: String,
+    #[serde(default)]
+    default: bool,
+    protocol: String,
+    #[serde(default)]
+    service_meta: Option<HashMap<String, String>>,
+    start_node: String,
+    #[serde(default)]
+    nodes: HashMap<String, DiscoveryGraphNode>,
+    #[serde(default)]
+    targets: HashMap<String, DiscoveryTarget>,
+}
+
+impl CompiledDiscoveryChain {
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
+    pub fn datacenter(&self) -> &str {
+        &self.datacenter
+    }
+
+    /// Hash of the overrides applied while compiling; empty without any.
+    pub fn customization_hash(&self) -> &str {
+        &self.customization_hash
+    }
+
+    /// Whether the chain was synthesized without any config entries.
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    pub fn service_meta(&self) -> Option<&HashMap<String, String>> {
+        self.service_meta.as_ref()
+    }
+
+    pub fn start_node(&self) -> Option<&DiscoveryGraphNode> {
+        self.nodes.get(&self.start_node)
+    }
+
+    pub fn node(&self, name: &str) -> Option<&DiscoveryGraphNode> {
+        self.nodes.get(name)
+    }
+
+    pub fn nodes(&self) -> &HashMap<String, DiscoveryGraphNode> {
+        &self.nodes
+    }
+
+    pub fn target(&self, id: &str) -> Option<&DiscoveryTarget> {
+        self.targets.get(id)
+    }
+
+    pub fn targets(&self) -> &HashMap<String, DiscoveryTarget> {
+        &self.targets
+    }
+
+    /// Targets reachable from the start node through routes, splits and
+    /// resolver failovers, in the order they are first encountered.
+    pub fn reachable_targets(&self) -> Vec<&DiscoveryTarget> {
+        let mut seen = HashSet::new();
+        let mut found = HashSet::new();
+        let mut targets = vec![];
+        let mut pending = vec![self.start_node.as_str()];
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(name) else {
+                continue;
+            };
+            let mut next: Vec<&str> = node.routes().iter().map(|r| r.next_node()).collect();
+            next.extend(node.splits().iter().map(|s| s.next_node()));
+            pending.extend(next.into_iter().rev());
+            let Some(resolver) = node.resolver() else {
+                continue;
+            };
+            let ids = std::iter::once(resolver.target()).chain(resolver.failover_targets());
+            for id in ids {
+                if found.insert(id)
+                    && let Some(target) = self.targets.get(id)
+                {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Router,
+    Splitter,
+    Resolver,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoveryGraphNode {
+    r#type: NodeType,
+    name: String,
+    #[serde(default)]
+    routes: Option<Vec<DiscoveryRoute>>,
+    #[serde(default)]
+    splits: Option<Vec<DiscoverySplit>>,
+    #[serde(default)]
+    resolver: Option<DiscoveryResolver>,
+}
+
+impl DiscoveryGraphNode {
+    pub fn node_type(&self) -> NodeType {
+        self.r#type
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn routes(&self) -> &[DiscoveryRoute] {
+        self.routes.as_deref().unwrap_or_default()
+    }
+
+    pub fn splits(&self) -> &[DiscoverySplit] {
+        self.splits.as_deref().unwrap_or_default()
+    }
+
+    pub fn resolver(&self) -> Option<&DiscoveryResolver> {
+        self.resolver.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoveryRoute {
+    #[serde(default)]
+    definition: serde_json::Value,
+    next_node: String,
+}
+
+impl DiscoveryRoute {
+    /// The `ServiceRoute` from the service-router config entry, as is.
+    pub fn definition(&self) -> &serde_json::Value {
+        &self.definition
+    }
+
+    pub fn next_node(&self) -> &str {
+        &self.next_node
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoverySplit {
+    weight: f32,
+    next_node: String,
+}
+
+impl DiscoverySplit {
+    /// Share of traffic in percent.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    pub fn next_node(&self) -> &str {
+        &self.next_node
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoveryResolver {
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    connect_timeout: GoDuration,
+    target: String,
+    #[serde(default)]
+    failover: Option<DiscoveryFailover>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiscoveryFailover {
+    #[serde(default)]
+    targets: Vec<String>,
+}
+
+impl DiscoveryResolver {
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout.get()
+    }
+
+    /// ID of the primary target, a key of [`CompiledDiscoveryChain::targets`].
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn failover_targets(&self) -> impl Iterator<Item = &str> {
+        self.failover
+            .iter()
+            .flat_map(|f| f.targets.iter().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MeshGateway {
+    #[serde(default)]
+    mode: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TargetSubset {
+    #[serde(default)]
+    filter: String,
+    #[serde(default)]
+    only_passing: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoveryTarget {
+    #[serde(rename = "ID")]
+    id: String,
+    service: String,
+    #[serde(default)]
+    service_subset: String,
+    #[serde(default)]
+    namespace: String,
+    #[serde(default)]
+    partition: String,
+    #[serde(default)]
+    datacenter: String,
+    #[serde(default)]
+    peer: String,
+    #[serde(default)]
+    mesh_gateway: Option<MeshGateway>,
+    #[serde(default)]
+    subset: TargetSubset,
+    #[serde(default)]
+    connect_timeout: GoDuration,
+    #[serde(rename = "SNI", default)]
+    sni: String,
+    #[serde(default)]
+    name: String,
+}
+
+impl DiscoveryTarget {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn service_subset(&self) -> &str {
+        &self.service_subset
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
+    pub fn datacenter(&self) -> &str {
+        &self.datacenter
+    }
+
+    /// Cluster peer the target lives in; empty for local targets.
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    pub fn mesh_gateway_mode(&self) -> &str {
+        self.mesh_gateway.as_ref().map_or("", |m| &m.mode)
+    }
+
+    /// Bexpr filter selecting the subset's instances.
+    pub fn subset_filter(&self) -> &str {
+        &self.subset.filter
+    }
+
+    pub fn only_passing(&self) -> bool {
+        self.subset.only_passing
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout.get()
+    }
+
+    pub fn sni(&self) -> &str {
+        &self.sni
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Durations appear either as Go strings or as integer nanoseconds,
+/// depending on the Consul version.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(untagged)]
+enum GoDuration {
+    #[default]
+    Unset,
+    Nanos(u64),
+    Text(String),
+}
+
+impl GoDuration {
+    fn get(&self) -> Option<Duration> {
+        match self {
+            Self::Unset => None,
+            Self::Nanos(nanos) => Some(Duration::from_nanos(*nanos)),
+            Self::Text(text) => crate::parse_duration(text),
+        }
+        .filter(|d| !d.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_walks_chain() {
+        let rs: ChainResponse = serde_json::from_value(serde_json::json!({"Chain": {
+            "ServiceName": "web",
+            "Namespace": "default",
+            "Datacenter": "dc1",
+            "Protocol": "http",
+            "StartNode": "router:web.default.default",
+            "Nodes": {
+                "router:web.default.default": {
+                    "Type": "router",
+                    "Name": "web.default.default",
+                    "Routes": [
+                        {"Definition": {"Match": {"HTTP": {"PathPrefix": "/admin"}}}, "NextNode": "resolver:admin.default.default.dc1"},
+                        {"Definition": {}, "NextNode": "splitter:web.default.default"}
+                    ]
+                },
+                "splitter:web.default.default": {
+                    "Type": "splitter",
+                    "Name": "web.default.default",
+                    "Splits": [
+                        {"Weight": 90, "NextNode": "resolver:v1.web.default.default.dc1"},
+                        {"Weight": 10, "NextNode": "resolver:v2.web.default.default.dc1"}
+                    ]
+                },
+                "resolver:admin.default.default.dc1": {
+                    "Type": "resolver",
+                    "Name": "admin.default.default.dc1",
+                    "Resolver": {"ConnectTimeout": "5s", "Target": "admin.default.default.dc1"}
+                },
+                "resolver:v1.web.default.default.dc1": {
+                    "Type": "resolver",
+                    "Name": "v1.web.default.default.dc1",
+                    "Resolver": {
+                        "ConnectTimeout": "5s",
+                        "Target": "v1.web.default.default.dc1",
+                        "Failover": {"Targets": ["v1.web.default.default.dc2"]}
+                    }
+                },
+                "resolver:v2.web.default.default.dc1": {
+                    "Type": "resolver",
+                    "Name": "v2.web.default.default.dc1",
+                    "Resolver": {"Default": true, "ConnectTimeout": "5s", "Target": "v2.web.default.default.dc1"}
+                }
+            },
+            "Targets": {
+                "admin.default.default.dc1": {"ID": "admin.default.default.dc1", "Service": "admin", "Datacenter": "dc1"},
+                "v1.web.default.default.dc1": {
+                    "ID": "v1.web.default.default.dc1",
+                    "Service": "web",
+                    "ServiceSubset": "v1",
+                    "Datacenter": "dc1",
+                    "Subset": {"Filter": "Service.Meta.version == v1", "OnlyPassing": true},
+                    "MeshGateway": {"Mode": "local"},
+                    "ConnectTimeout": 5000000000u64,
+                    "SNI": "v1.web.default.dc1.internal.example.consul"
+                },
+                "v1.web.default.default.dc2": {"ID": "v1.web.default.default.dc2", "Service": "web", "ServiceSubset": "v1", "Datacenter": "dc2"},
+                "v2.web.default.default.dc1": {"ID": "v2.web.default.default.dc1", "Service": "web", "ServiceSubset": "v2", "Datacenter": "dc1"}
+            }
+        }}))
+        .unwrap();
+        let chain = rs.chain;
+        assert_eq!(chain.start_node().unwrap().node_type(), NodeType::Router);
+        let ids: Vec<&str> = chain.reachable_targets().iter().map(|t| t.id()).collect();
+        assert_eq!(
+            ids,
+            [
+                "admin.default.default.dc1",
+                "v1.web.default.default.dc1",
+                "v1.web.default.default.dc2",
+                "v2.web.default.default.dc1",
+            ]
+        );
+        let target = chain.target("v1.web.default.default.dc1").unwrap();
+        assert_eq!(target.connect_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(target.mesh_gateway_mode(), "local");
+        assert!(target.only_passing());
+        let resolver = chain
+            .node("resolver:v1.web.default.default.dc1")
+            .and_then(|n| n.resolver())
+            .unwrap();
+        assert_eq!(resolver.connect_timeout(), Some(Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,131 @@
+//! Extension point for Consul endpoints the crate doesn't wrap.
+//!
+//! An [`Endpoint`] describes a request; [`Endpoint::execute`] sends it with
+//! everything the [`Client`] applies to its own requests (failover, tokens,
+//! middleware, default datacenter, timeouts). Within the crate the request
+//! builders, [`Kv`](crate::Kv) and [`Health`](crate::health::Health),
+//! implement it; the fixed-shape endpoints of the other modules are plain
+//! functions over the client:
+//!
+//! ```no_run
+//! use std::borrow::Cow;
+//!
+//! use consulite::endpoint::Endpoint;
+//!
+//! struct Members;
+//!
+//! impl Endpoint for Members {
+//!     type Query = ();
+//!
+//!     fn path(&self) -> Cow<'_, str> {
+//!         "v1/agent/members".into()
+//!     }
+//!
+//!     fn query(&self) -> &() {
+//!         &()
+//!     }
+//! }
+//!
+//! # async fn run(client: &consulite::Client) -> Result<(), anyhow::Error> {
+//! let members = Members.execute(client).await?.json();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::Serialize;
+
+use crate::{Client, Response};
+
+pub trait Endpoint: Send + Sync {
+    type Query: Serialize + Sync + ?Sized;
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    /// Path relative to the agent address, e.g. `v1/kv/key`.
+    fn path(&self) -> Cow<'_, str>;
+
+    fn query(&self) -> &Self::Query;
+
+    /// JSON request body, taken when the request is sent.
+    fn take_payload(&mut self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Raw request body, used when there is no payload; taken when the
+    /// request is sent.
+    fn take_body(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Overrides the client's default timeout.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn execute(
+        self,
+        client: &Client,
+    ) -> impl Future<Output = Result<Response, anyhow::Error>> + Send
+    where
+        Self: Sized,
+    {
+        let method = self.method();
+        send(self, method, client)
+    }
+}
+
+/// Sends `endpoint` with `method` in place of its own.
+pub(crate) async fn send<E>(
+    mut endpoint: E,
+    method: Method,
+    client: &Client,
+) -> Result<Response, anyhow::Error>
+where
+    E: Endpoint,
+{
+    let payload = endpoint.take_payload();
+    let body = endpoint.take_body();
+    client
+        .send_with_timeout(
+            method,
+            &endpoint.path(),
+            endpoint.query(),
+            payload,
+            body,
+            endpoint.timeout(),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Peers;
+
+    impl Endpoint for Peers {
+        type Query = ();
+
+        fn path(&self) -> Cow<'_, str> {
+            "v1/status/peers".into()
+        }
+
+        fn query(&self) -> &() {
+            &()
+        }
+    }
+
+    #[tokio::test]
+    async fn it_executes_custom_endpoints() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let peers: Vec<String> = Peers.execute(&client).await.unwrap().decode().unwrap();
+        assert!(!peers.is_empty());
+    }
+}
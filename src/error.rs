@@ -0,0 +1,225 @@
+//! Errors for unsuccessful responses.
+//!
+//! Non-2xx responses become an [`ApiError`], a [`PermissionDenied`] for 403
+//! or a [`RateLimited`] for 429, carrying the reason Consul gave in the body.
+//! The request method and path are attached as context. Calls into APIs the
+//! agent is too old for fail with [`UnsupportedByAgent`] before a request is
+//! sent. Read-modify-write helpers that keep losing the CAS race fail with
+//! [`CasConflict`]. Every type here is reachable via
+//! `anyhow::Error::downcast_ref`.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::agent::AgentVersion;
+use crate::token::PermissionDenied;
+
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Reason from the response body, e.g. `rpc error: Permission denied`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unexpected status {}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Error for 429 responses when the client's retry policy gave up or none
+/// is configured.
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    retry_after: Option<Duration>,
+    message: String,
+}
+
+impl RateLimited {
+    /// Delay advised by the `Retry-After` header.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rate limited: {}", self.message)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+#[derive(Debug, Clone)]
+pub struct UnsupportedByAgent {
+    feature: &'static str,
+    required: AgentVersion,
+    agent: AgentVersion,
+}
+
+impl UnsupportedByAgent {
+    pub(crate) fn new(feature: &'static str, required: AgentVersion, agent: AgentVersion) -> Self {
+        Self {
+            feature,
+            required,
+            agent,
+        }
+    }
+
+    pub fn feature(&self) -> &str {
+        self.feature
+    }
+
+    /// Oldest agent version supporting the feature.
+    pub fn required(&self) -> AgentVersion {
+        self.required
+    }
+
+    pub fn agent(&self) -> AgentVersion {
+        self.agent
+    }
+}
+
+impl fmt::Display for UnsupportedByAgent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requires Consul {} or newer, the agent runs {}",
+            self.feature, self.required, self.agent
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedByAgent {}
+
+#[derive(Debug, Clone)]
+pub struct CasConflict {
+    path: String,
+    attempts: u32,
+}
+
+impl CasConflict {
+    pub(crate) fn new(path: &str, attempts: u32) -> Self {
+        Self {
+            path: path.to_string(),
+            attempts,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+impl fmt::Display for CasConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Gave up on {} after {} conflicting CAS writes",
+            self.path, self.attempts
+        )
+    }
+}
+
+impl std::error::Error for CasConflict {}
+
+pub(crate) fn rate_limited(
+    method: &reqwest::Method,
+    path: &str,
+    retry_after: Option<Duration>,
+    body: &[u8],
+) -> anyhow::Error {
+    let message = message(body);
+    anyhow::Error::new(RateLimited {
+        retry_after,
+        message,
+    })
+    .context(format!("{method} {path} failed"))
+}
+
+/// Error for a response with `status` to `method` on `path`.
+pub(crate) fn status_error(
+    method: &reqwest::Method,
+    path: &str,
+    status: u16,
+    body: &[u8],
+) -> anyhow::Error {
+    let message = message(body);
+    let error = if status == 403 {
+        anyhow::Error::new(PermissionDenied::new(message))
+    } else {
+        anyhow::Error::new(ApiError { status, message })
+    };
+    error.context(format!("{method} {path} failed"))
+}
+
+/// Adds the correlation ID of the failed request, if any.
+pub(crate) fn with_request_id(error: anyhow::Error, request_id: Option<&str>) -> anyhow::Error {
+    match request_id {
+        Some(id) => error.context(format!("Request {id}")),
+        None => error,
+    }
+}
+
+/// Error message from a plain text or JSON body.
+fn message(body: &[u8]) -> String {
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice(body) {
+        let reason = ["error", "Error", "message", "Message"]
+            .iter()
+            .find_map(|field| fields.get(*field)?.as_str());
+        if let Some(reason) = reason {
+            return reason.to_string();
+        }
+    }
+    String::from_utf8_lossy(body).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_messages() {
+        let error = status_error(
+            &reqwest::Method::PUT,
+            "/v1/kv/key",
+            500,
+            b"rpc error: Permission denied\n",
+        );
+        let api = error.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(api.status(), 500);
+        assert_eq!(api.message(), "rpc error: Permission denied");
+        assert_eq!(
+            format!("{error:#}"),
+            "PUT /v1/kv/key failed: Unexpected status 500: rpc error: Permission denied"
+        );
+        let error = status_error(
+            &reqwest::Method::GET,
+            "/v1/acl/token/self",
+            403,
+            br#"{"error": "ACL not found"}"#,
+        );
+        let denied = error.downcast_ref::<PermissionDenied>().unwrap();
+        assert_eq!(denied.message(), "ACL not found");
+    }
+}
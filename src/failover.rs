@@ -0,0 +1,161 @@
+//! Failover between several agent addresses.
+//!
+//! Requests are built against the first address and rebased onto whichever
+//! address is tried. An address that fails to connect is skipped for a
+//! cooldown period, unless every address is in cooldown.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Always prefer the first healthy address in the configured order.
+    #[default]
+    PrimaryWithFallback,
+    /// Spread requests over all healthy addresses.
+    RoundRobin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    url: url::Url,
+    healthy: bool,
+}
+
+impl EndpointStatus {
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+
+    /// `false` while the address is in cooldown after a connection error.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    urls: Vec<url::Url>,
+    strategy: Strategy,
+    cooldown: Duration,
+    next: AtomicUsize,
+    down_until: Mutex<Vec<Option<Instant>>>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(urls: Vec<url::Url>, strategy: Strategy, cooldown: Duration) -> Self {
+        let down_until = Mutex::new(vec![None; urls.len()]);
+        Self {
+            urls,
+            strategy,
+            cooldown,
+            next: AtomicUsize::new(0),
+            down_until,
+        }
+    }
+
+    /// Base that request URLs are built against.
+    pub(crate) fn primary(&self) -> &url::Url {
+        &self.urls[0]
+    }
+
+    /// Indexes of the addresses to try, healthy ones first.
+    pub(crate) fn order(&self) -> Vec<usize> {
+        let len = self.urls.len();
+        let start = match self.strategy {
+            Strategy::PrimaryWithFallback => 0,
+            Strategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % len,
+        };
+        let now = Instant::now();
+        let down_until = self.down_until.lock().unwrap();
+        let (mut healthy, down): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (start + i) % len)
+            .partition(|&i| down_until[i].is_none_or(|until| until <= now));
+        healthy.extend(down);
+        healthy
+    }
+
+    /// `url` moved from the primary address onto address `index`.
+    pub(crate) fn rebase(&self, url: &url::Url, index: usize) -> Result<url::Url, anyhow::Error> {
+        if index == 0 {
+            return Ok(url.clone());
+        }
+        let Some(rest) = url.as_str().strip_prefix(self.primary().as_str()) else {
+            anyhow::bail!("{url} is not below {}", self.primary());
+        };
+        Ok(self.urls[index].join(rest)?)
+    }
+
+    pub(crate) fn mark_down(&self, index: usize) {
+        self.down_until.lock().unwrap()[index] = Some(Instant::now() + self.cooldown);
+    }
+
+    pub(crate) fn mark_up(&self, index: usize) {
+        self.down_until.lock().unwrap()[index] = None;
+    }
+
+    pub(crate) fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        let down_until = self.down_until.lock().unwrap();
+        self.urls
+            .iter()
+            .zip(down_until.iter())
+            .map(|(url, until)| EndpointStatus {
+                url: url.clone(),
+                healthy: until.is_none_or(|until| until <= now),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, Kv};
+
+    fn endpoints(strategy: Strategy) -> Endpoints {
+        let urls = ["http://a:8500", "http://b:8500", "http://c:8500"]
+            .iter()
+            .map(|u| u.parse().unwrap())
+            .collect();
+        Endpoints::new(urls, strategy, Duration::from_secs(30))
+    }
+
+    #[test]
+    fn it_orders_endpoints() {
+        let primary = endpoints(Strategy::PrimaryWithFallback);
+        assert_eq!(primary.order(), [0, 1, 2]);
+        primary.mark_down(0);
+        assert_eq!(primary.order(), [1, 2, 0]);
+        primary.mark_up(0);
+        assert_eq!(primary.order(), [0, 1, 2]);
+        let round_robin = endpoints(Strategy::RoundRobin);
+        assert_eq!(round_robin.order(), [0, 1, 2]);
+        assert_eq!(round_robin.order(), [1, 2, 0]);
+        round_robin.mark_down(0);
+        assert_eq!(round_robin.order(), [2, 1, 0]);
+        let url = "http://a:8500/v1/kv/key?dc=dc1".parse().unwrap();
+        assert_eq!(
+            primary.rebase(&url, 2).unwrap().as_str(),
+            "http://c:8500/v1/kv/key?dc=dc1"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_fails_over() {
+        let client = Client::builder("http://127.0.0.1:1")
+            .address("http://localhost:8500")
+            .build()
+            .unwrap();
+        Kv::new("failover/key0")
+            .body(b"1".to_vec())
+            .put(&client)
+            .await
+            .unwrap();
+        let status = client.endpoint_status();
+        assert!(!status[0].is_healthy());
+        assert!(status[1].is_healthy());
+        Kv::new("failover/key0").delete(&client).await.unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::ops::Not;
+
+/// Expression for the `filter` query parameter.
+///
+/// ```
+/// use consulite::filter::Filter;
+///
+/// let filter = Filter::selector("Service.Tags")
+///     .contains("primary")
+///     .and(Filter::selector("Service.Meta.env").eq("prod"));
+/// assert_eq!(
+///     filter.to_string(),
+///     r#"(Service.Tags contains "primary") and (Service.Meta.env == "prod")"#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter(String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector(String);
+
+impl Filter {
+    pub fn selector<S>(selector: S) -> Selector
+    where
+        S: Into<String>,
+    {
+        Selector(selector.into())
+    }
+
+    /// Uses an expression verbatim, without any escaping.
+    pub fn raw<S>(expr: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(expr.into())
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Self(format!("({}) and ({})", self.0, other.0))
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Self(format!("({}) or ({})", self.0, other.0))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Self(format!("not ({})", self.0))
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Filter> for String {
+    fn from(value: Filter) -> Self {
+        value.0
+    }
+}
+
+impl Selector {
+    fn binary(self, op: &str, value: &str) -> Filter {
+        Filter(format!("{} {op} {}", self.0, quote(value)))
+    }
+
+    pub fn eq(self, value: &str) -> Filter {
+        self.binary("==", value)
+    }
+
+    pub fn ne(self, value: &str) -> Filter {
+        self.binary("!=", value)
+    }
+
+    pub fn contains(self, value: &str) -> Filter {
+        self.binary("contains", value)
+    }
+
+    pub fn not_contains(self, value: &str) -> Filter {
+        self.binary("not contains", value)
+    }
+
+    pub fn matches(self, pattern: &str) -> Filter {
+        self.binary("matches", pattern)
+    }
+
+    pub fn not_matches(self, pattern: &str) -> Filter {
+        self.binary("not matches", pattern)
+    }
+
+    /// `"<value>" in <selector>`
+    pub fn has(self, value: &str) -> Filter {
+        Filter(format!("{} in {}", quote(value), self.0))
+    }
+
+    /// `"<value>" not in <selector>`
+    pub fn lacks(self, value: &str) -> Filter {
+        Filter(format!("{} not in {}", quote(value), self.0))
+    }
+
+    pub fn is_empty(self) -> Filter {
+        Filter(format!("{} is empty", self.0))
+    }
+
+    pub fn is_not_empty(self) -> Filter {
+        Filter(format!("{} is not empty", self.0))
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_escapes() {
+        let filter = !Filter::selector("Service.Meta.note")
+            .eq("say \"hi\" \\o/")
+            .or(Filter::selector("Service.Tags").has("primary"));
+        assert_eq!(
+            filter.as_str(),
+            r#"not ((Service.Meta.note == "say \"hi\" \\o/") or ("primary" in Service.Tags))"#
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Serialization formats for KV values.
+//!
+//! Values written with [`Kv::put_serialized`](crate::Kv::put_serialized)
+//! carry their format in the key's flags, so
+//! [`Kv::get_as`](crate::Kv::get_as) and [`Record::value_as`](crate::Record::value_as)
+//! pick the matching decoder. Keys with other flags are read as JSON.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Flags marking a MessagePack value ("MP").
+#[cfg(feature = "msgpack")]
+const MESSAGE_PACK_FLAGS: u64 = 0x4d50;
+
+/// Variants depend on enabled features, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    Json,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl Format {
+    /// Format recorded in a key's flags.
+    pub fn from_flags(flags: u64) -> Self {
+        match flags {
+            #[cfg(feature = "msgpack")]
+            MESSAGE_PACK_FLAGS => Format::MessagePack,
+            _ => Format::Json,
+        }
+    }
+
+    /// Flags written along with values in this format.
+    pub fn flags(self) -> u64 {
+        match self {
+            Format::Json => 0,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => MESSAGE_PACK_FLAGS,
+        }
+    }
+
+    pub fn serialize<T>(self, value: &T) -> Result<Vec<u8>, anyhow::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Format::Json => Ok(serde_json::to_vec(value)?),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => Ok(rmp_serde::to_vec_named(value)?),
+        }
+    }
+
+    pub fn deserialize<T>(self, value: &[u8]) -> Result<T, anyhow::Error>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            Format::Json => Ok(serde_json::from_slice(value)?),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => Ok(rmp_serde::from_slice(value)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{Client, Kv};
+
+    #[test]
+    fn it_round_trips() {
+        let value = BTreeMap::from([("port".to_string(), 8080)]);
+        let bytes = Format::Json.serialize(&value).unwrap();
+        let format = Format::from_flags(Format::Json.flags());
+        assert_eq!(
+            format.deserialize::<BTreeMap<String, i32>>(&bytes).unwrap(),
+            value
+        );
+        #[cfg(feature = "msgpack")]
+        {
+            let bytes = Format::MessagePack.serialize(&value).unwrap();
+            let format = Format::from_flags(Format::MessagePack.flags());
+            assert_eq!(format, Format::MessagePack);
+            assert_eq!(
+                format.deserialize::<BTreeMap<String, i32>>(&bytes).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn it_stores_message_pack() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let value = vec![1u8, 2, 3];
+        Kv::new("format/msgpack")
+            .put_serialized(&client, &value, Format::MessagePack)
+            .await
+            .unwrap();
+        let record = Kv::new("format/msgpack")
+            .get(&client)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.format(), Format::MessagePack);
+        let stored: Option<Vec<u8>> = Kv::new("format/msgpack").get_as(&client).await.unwrap();
+        assert_eq!(stored, Some(value));
+        Kv::new("format/msgpack").delete(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_stores_json() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("format/json")
+            .put_serialized(&client, &["a", "b"], Format::Json)
+            .await
+            .unwrap();
+        let stored: Option<Vec<String>> = Kv::new("format/json").get_as(&client).await.unwrap();
+        assert_eq!(stored.unwrap(), ["a", "b"]);
+        Kv::new("format/json").delete(&client).await.unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! Client-side load balancing for tonic gRPC clients.
+//!
+//! [`GrpcChannel`] builds a [`tonic::transport::Channel`] over the passing
+//! instances of a service. Instances are kept up to date with blocking
+//! health queries in a background task, which adds and removes endpoints
+//! of the channel as they change:
+//!
+//! ```ignore
+//! let channel = GrpcChannel::new(Health::service("orders").tag("grpc"))
+//!     .configure(|endpoint| endpoint.timeout(Duration::from_secs(5)))
+//!     .connect(&client);
+//! let mut orders = OrdersClient::new(channel);
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tonic::transport::channel::Change;
+use tonic::transport::{Channel, Endpoint};
+
+use crate::Client;
+use crate::health::{Health, ServiceEntry};
+
+type Configure = Arc<dyn Fn(Endpoint) -> Endpoint + Send + Sync>;
+
+pub struct GrpcChannel {
+    health: Health,
+    scheme: &'static str,
+    configure: Option<Configure>,
+    capacity: usize,
+}
+
+impl GrpcChannel {
+    pub fn new(health: Health) -> Self {
+        Self {
+            health,
+            scheme: "http",
+            configure: None,
+            capacity: 64,
+        }
+    }
+
+    /// Connects to the instances over TLS; set up the TLS config with
+    /// [`GrpcChannel::configure`].
+    pub fn https(mut self) -> Self {
+        self.scheme = "https";
+        self
+    }
+
+    /// Applied to the endpoint of every instance, e.g. for timeouts or TLS.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(Endpoint) -> Endpoint + Send + Sync + 'static,
+    {
+        self.configure = Some(Arc::new(configure));
+        self
+    }
+
+    /// Size of the buffer of endpoint changes sent to the channel.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Starts resolving instances and returns the balanced channel. Calls
+    /// wait while no instance is known.
+    pub fn connect(self, client: &Client) -> Channel {
+        let (channel, tx) = Channel::balance_channel(self.capacity);
+        self.discover(client, tx);
+        channel
+    }
+
+    /// Sends endpoint changes for the instances to `tx` until it closes.
+    fn discover(self, client: &Client, tx: mpsc::Sender<Change<String, Endpoint>>) {
+        let Self {
+            health,
+            scheme,
+            configure,
+            ..
+        } = self;
+        let service = health.path.clone();
+        let mut instances = health.follow(client, ServiceEntry::authority);
+        tokio::spawn(async move {
+            let mut current = HashSet::new();
+            loop {
+                tokio::select! {
+                    _ = tx.closed() => return,
+                    changed = instances.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+                let next: HashSet<String> = instances.borrow_and_update().iter().cloned().collect();
+                let (added, removed) = diff(&current, &next);
+                let mut changes: Vec<_> = removed.into_iter().map(Change::Remove).collect();
+                for authority in added {
+                    let uri = format!("{scheme}://{authority}");
+                    match Endpoint::from_shared(uri) {
+                        Ok(endpoint) => {
+                            let endpoint = match &configure {
+                                Some(configure) => configure(endpoint),
+                                None => endpoint,
+                            };
+                            changes.push(Change::Insert(authority, endpoint));
+                        }
+                        Err(e) => tracing::warn!("Invalid instance {authority} of {service}: {e}"),
+                    }
+                }
+                for change in changes {
+                    if tx.send(change).await.is_err() {
+                        return;
+                    }
+                }
+                current = next;
+            }
+        });
+    }
+}
+
+impl fmt::Debug for GrpcChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcChannel")
+            .field("service", &self.health.path)
+            .field("scheme", &self.scheme)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Authorities to add and to remove to get from `current` to `next`.
+fn diff(current: &HashSet<String>, next: &HashSet<String>) -> (Vec<String>, Vec<String>) {
+    let mut added: Vec<_> = next.difference(current).cloned().collect();
+    let mut removed: Vec<_> = current.difference(next).cloned().collect();
+    added.sort();
+    removed.sort();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(authorities: &[&str]) -> HashSet<String> {
+        authorities.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn it_diffs_instances() {
+        let current = set(&["10.0.0.1:50051", "10.0.0.2:50051"]);
+        let next = set(&["10.0.0.2:50051", "10.0.0.3:50051"]);
+        let (added, removed) = diff(&current, &next);
+        assert_eq!(added, ["10.0.0.3:50051"]);
+        assert_eq!(removed, ["10.0.0.1:50051"]);
+        assert_eq!(diff(&next, &next), (vec![], vec![]));
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_updates_endpoints() {
+        use crate::mock::{MockAgent, Reply};
+
+        let entry = |node: &str, address: &str| {
+            serde_json::json!({
+                "Node": {"Node": node, "Address": address},
+                "Service": {"ID": "orders", "Service": "orders", "Port": 50051},
+                "Checks": [],
+            })
+        };
+        let agent = MockAgent::start().await.unwrap();
+        let first = serde_json::json!([entry("n1", "10.0.0.1"), entry("n2", "fd00::2")]);
+        let second = serde_json::json!([entry("n2", "fd00::2"), entry("n3", "10.0.0.3")]);
+        agent.expect(
+            "GET",
+            "v1/health/service/orders",
+            Reply::json(first).index(1),
+        );
+        agent.expect(
+            "GET",
+            "v1/health/service/orders",
+            Reply::json(second).index(2),
+        );
+        let client = Client::new(agent.url()).unwrap();
+        let (tx, mut rx) = mpsc::channel(8);
+        GrpcChannel::new(Health::service("orders")).discover(&client, tx);
+        let mut changes = vec![];
+        for _ in 0..4 {
+            changes.push(match rx.recv().await.unwrap() {
+                Change::Insert(authority, _) => format!("+{authority}"),
+                Change::Remove(authority) => format!("-{authority}"),
+            });
+        }
+        assert_eq!(
+            changes,
+            [
+                "+10.0.0.1:50051",
+                "+[fd00::2]:50051",
+                "-10.0.0.1:50051",
+                "+10.0.0.3:50051",
+            ]
+        );
+    }
+}
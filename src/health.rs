@@ -0,0 +1,833 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::endpoint::{self, Endpoint};
+use crate::watch::Watch;
+use crate::{Client, Response, random_unit};
+
+#[derive(Default, Clone)]
+pub struct Health {
+    pub(crate) path: String,
+    pub(crate) query: HealthQuery,
+    pub(crate) output_limit: Option<usize>,
+}
+
+#[derive(Default, Clone, Serialize)]
+pub struct HealthQuery {
+    dc: Option<String>,
+    tag: Option<String>,
+    passing: Option<bool>,
+    filter: Option<String>,
+    near: Option<String>,
+    index: Option<u64>,
+    wait: Option<String>,
+}
+
+impl Health {
+    pub fn service<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let path = format!("v1/health/service/{}", name.into());
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Checks in the given state, across all nodes.
+    pub fn state(state: State) -> Self {
+        let path = format!("v1/health/state/{}", state.as_str());
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Checks registered on a node, both node and service level.
+    pub fn node<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let path = format!("v1/health/node/{}", name.into());
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.dc = Some(dc.into());
+        self
+    }
+
+    pub fn tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.tag = Some(tag.into());
+        self
+    }
+
+    pub fn passing(mut self, value: bool) -> Self {
+        self.query.passing = Some(value);
+        self
+    }
+
+    pub fn filter<S>(mut self, expr: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.filter = Some(expr.into());
+        self
+    }
+
+    /// Sorts results by estimated round trip time from `node`; `_agent`
+    /// means the agent serving the request.
+    pub fn near<S>(mut self, node: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.near = Some(node.into());
+        self
+    }
+
+    pub fn index(mut self, index: u64) -> Self {
+        self.query.index = Some(index);
+        self
+    }
+
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.query.wait = Some(format!("{}ms", wait.as_millis()));
+        self
+    }
+
+    /// Truncates check `Output` to at most `bytes` in decoded results.
+    pub fn output_limit(mut self, bytes: usize) -> Self {
+        self.output_limit = Some(bytes);
+        self
+    }
+
+    pub async fn send_request(
+        self,
+        method: reqwest::Method,
+        client: &Client,
+    ) -> Result<Response, anyhow::Error> {
+        endpoint::send(self, method, client).await
+    }
+
+    pub async fn get(self, client: &Client) -> Result<Vec<ServiceEntry>, anyhow::Error> {
+        let limit = self.output_limit;
+        let rs = self.send_request(Method::GET, client).await?;
+        Self::entries(rs, limit)
+    }
+
+    /// Checks returned by [`Health::state`] and [`Health::node`] queries.
+    pub async fn checks(self, client: &Client) -> Result<Vec<HealthCheck>, anyhow::Error> {
+        let limit = self.output_limit;
+        let mut checks: Vec<HealthCheck> =
+            self.send_request(Method::GET, client).await?.decode()?;
+        if let Some(limit) = limit {
+            checks
+                .iter_mut()
+                .for_each(|check| check.truncate_output(limit));
+        }
+        Ok(checks)
+    }
+
+    pub fn watch(self, client: &Client) -> Watch {
+        let client = client.clone();
+        Watch::new(move |index, wait| {
+            let client = client.clone();
+            let health = self.clone().index(index).wait(wait);
+            async move { health.send_request(Method::GET, &client).await }
+        })
+    }
+
+    /// Follows the passing instances matched by this query with blocking
+    /// queries in a background task, publishing them mapped through `map`.
+    /// The task owns the sender and stops once every receiver is dropped,
+    /// so a held receiver never sees it closed. On errors the last known
+    /// instances are kept.
+    pub(crate) fn follow<T, F>(self, client: &Client, map: F) -> watch::Receiver<Arc<Vec<T>>>
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&ServiceEntry) -> T + Send + 'static,
+    {
+        let (tx, rx) = watch::channel(Arc::new(vec![]));
+        let service = self.path.clone();
+        let mut updates = self.passing(true).watch(client);
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = tx.closed() => return,
+                    event = updates.next() => event,
+                };
+                match event.and_then(|e| Health::entries(e.into_response(), None)) {
+                    Ok(entries) => {
+                        tx.send_replace(Arc::new(entries.iter().map(&map).collect()));
+                    }
+                    Err(e) => tracing::warn!("Failed to refresh instances of {service}: {e:#}"),
+                }
+            }
+        });
+        rx
+    }
+
+    /// Waits until a receiver of [`Health::follow`] has an instance.
+    pub(crate) async fn followed<T>(instances: &watch::Receiver<Arc<Vec<T>>>) {
+        let mut instances = instances.clone();
+        let _ = instances.wait_for(|i| !i.is_empty()).await;
+    }
+
+    pub(crate) fn entries(
+        rs: Response,
+        limit: Option<usize>,
+    ) -> Result<Vec<ServiceEntry>, anyhow::Error> {
+        let mut entries: Vec<ServiceEntry> = rs.decode()?;
+        if let Some(limit) = limit {
+            entries
+                .iter_mut()
+                .flat_map(|entry| entry.checks.iter_mut())
+                .for_each(|check| check.truncate_output(limit));
+        }
+        Ok(entries)
+    }
+}
+
+impl Endpoint for Health {
+    type Query = HealthQuery;
+
+    fn path(&self) -> Cow<'_, str> {
+        self.path.as_str().into()
+    }
+
+    fn query(&self) -> &HealthQuery {
+        &self.query
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    /// Matches every state in [`Health::state`] queries.
+    Any,
+    Passing,
+    Warning,
+    Critical,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Any => "any",
+            State::Passing => "passing",
+            State::Warning => "warning",
+            State::Critical => "critical",
+        }
+    }
+}
+
+/// Status a check can be set to, unlike [`State`] without `any`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passing,
+    Warning,
+    Critical,
+}
+
+impl From<CheckStatus> for State {
+    fn from(status: CheckStatus) -> Self {
+        match status {
+            CheckStatus::Passing => State::Passing,
+            CheckStatus::Warning => State::Warning,
+            CheckStatus::Critical => State::Critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceEntry {
+    node: Node,
+    service: AgentService,
+    #[serde(default)]
+    checks: Vec<HealthCheck>,
+}
+
+impl ServiceEntry {
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    pub fn service(&self) -> &AgentService {
+        &self.service
+    }
+
+    pub fn checks(&self) -> &[HealthCheck] {
+        &self.checks
+    }
+
+    /// Service address, falling back to the node address when unset.
+    pub fn address(&self) -> &str {
+        if self.service.address.is_empty() {
+            &self.node.address
+        } else {
+            &self.service.address
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.service.port
+    }
+
+    /// `host:port` of the instance, with IPv6 addresses in brackets.
+    pub fn authority(&self) -> String {
+        authority(self.address(), self.port())
+    }
+
+    /// Effective DNS weight: the warning weight if any check is warning,
+    /// 0 if any is critical, otherwise the passing weight.
+    pub fn weight(&self) -> u32 {
+        let status = |state: State| self.checks.iter().any(|c| c.status == state.as_str());
+        if status(State::Critical) {
+            0
+        } else if status(State::Warning) {
+            self.service.weights.warning
+        } else {
+            self.service.weights.passing
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Node {
+    #[serde(rename = "ID", default)]
+    id: String,
+    node: String,
+    address: String,
+    #[serde(default)]
+    datacenter: String,
+    #[serde(default)]
+    tagged_addresses: Option<HashMap<String, String>>,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+}
+
+impl Node {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn datacenter(&self) -> &str {
+        &self.datacenter
+    }
+
+    /// Node addresses by kind, e.g. `lan` or `wan`.
+    pub fn tagged_addresses(&self) -> Option<&HashMap<String, String>> {
+        self.tagged_addresses.as_ref()
+    }
+
+    pub fn meta(&self) -> Option<&HashMap<String, String>> {
+        self.meta.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AgentService {
+    #[serde(rename = "ID")]
+    id: String,
+    service: String,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    address: String,
+    #[serde(default)]
+    port: u16,
+    #[serde(default)]
+    tagged_addresses: Option<HashMap<String, ServiceAddress>>,
+    #[serde(default)]
+    weights: Weights,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+}
+
+impl AgentService {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn tags(&self) -> &[String] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Service addresses by kind, e.g. `lan_ipv4`, `wan` or `virtual`.
+    pub fn tagged_addresses(&self) -> Option<&HashMap<String, ServiceAddress>> {
+        self.tagged_addresses.as_ref()
+    }
+
+    pub fn weights(&self) -> Weights {
+        self.weights
+    }
+
+    pub fn meta(&self) -> Option<&HashMap<String, String>> {
+        self.meta.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceAddress {
+    address: String,
+    port: u16,
+}
+
+impl ServiceAddress {
+    pub fn new<A>(address: A, port: u16) -> Self
+    where
+        A: Into<String>,
+    {
+        Self {
+            address: address.into(),
+            port,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// DNS SRV weights of an instance by health; Consul defaults both to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Weights {
+    passing: u32,
+    warning: u32,
+}
+
+impl Weights {
+    pub fn new(passing: u32, warning: u32) -> Self {
+        Self { passing, warning }
+    }
+
+    pub fn passing(&self) -> u32 {
+        self.passing
+    }
+
+    pub fn warning(&self) -> u32 {
+        self.warning
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HealthCheck {
+    node: String,
+    #[serde(rename = "CheckID")]
+    check_id: String,
+    name: String,
+    status: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    output: String,
+    #[serde(rename = "ServiceID", default)]
+    service_id: String,
+    #[serde(default)]
+    service_name: String,
+    #[serde(default)]
+    service_tags: Option<Vec<String>>,
+    #[serde(default)]
+    r#type: String,
+    #[serde(default)]
+    exposed_port: u16,
+    #[serde(default)]
+    definition: CheckDefinition,
+    #[serde(default)]
+    create_index: u64,
+    #[serde(default)]
+    modify_index: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CheckDefinition {
+    #[serde(default)]
+    interval: Option<String>,
+    #[serde(default)]
+    timeout: Option<String>,
+}
+
+impl HealthCheck {
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    pub fn check_id(&self) -> &str {
+        &self.check_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Typed [`HealthCheck::status`]; `None` for unknown values.
+    pub fn state(&self) -> Option<State> {
+        serde_json::from_value(serde_json::Value::String(self.status.clone())).ok()
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn service_tags(&self) -> &[String] {
+        self.service_tags.as_deref().unwrap_or_default()
+    }
+
+    /// Check type such as `http`, `tcp`, `ttl` or `script`; empty for
+    /// node-level serf checks.
+    pub fn check_type(&self) -> &str {
+        &self.r#type
+    }
+
+    pub fn exposed_port(&self) -> Option<u16> {
+        (self.exposed_port != 0).then_some(self.exposed_port)
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.definition
+            .interval
+            .as_deref()
+            .and_then(crate::parse_duration)
+            .filter(|d| !d.is_zero())
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.definition
+            .timeout
+            .as_deref()
+            .and_then(crate::parse_duration)
+            .filter(|d| !d.is_zero())
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+
+    fn truncate_output(&mut self, limit: usize) {
+        if self.output.len() > limit {
+            let mut end = limit;
+            while !self.output.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.output.truncate(end);
+        }
+    }
+}
+
+impl Client {
+    /// Socket addresses of the healthy instances of `name`, optionally
+    /// filtered by `tag`, heaviest first. Instances with a weight of 0 are
+    /// left out, and hostnames are resolved through the system resolver;
+    /// instances whose hostname doesn't resolve are logged and skipped.
+    pub async fn resolve_service(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+    ) -> Result<Vec<SocketAddr>, anyhow::Error> {
+        let mut entries = self.weighted_entries(name, tag).await?;
+        entries.sort_by_key(|(weight, _)| std::cmp::Reverse(*weight));
+        socket_addrs(entries).await
+    }
+
+    /// Like [`Client::resolve_service`], in a random order where heavier
+    /// instances are proportionally more likely to come first.
+    pub async fn resolve_service_shuffled(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+    ) -> Result<Vec<SocketAddr>, anyhow::Error> {
+        let entries = self.weighted_entries(name, tag).await?;
+        // Weighted random sampling (Efraimidis-Spirakis): sort by u^(1/w).
+        let mut keyed: Vec<_> = entries
+            .into_iter()
+            .map(|(weight, entry)| {
+                let u = random_unit();
+                (u.powf(1.0 / f64::from(weight)), (weight, entry))
+            })
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        socket_addrs(keyed.into_iter().map(|(_, entry)| entry).collect()).await
+    }
+
+    async fn weighted_entries(
+        &self,
+        name: &str,
+        tag: Option<&str>,
+    ) -> Result<Vec<(u32, ServiceEntry)>, anyhow::Error> {
+        let mut health = Health::service(name);
+        if let Some(tag) = tag {
+            health = health.tag(tag);
+        }
+        Ok(health
+            .get(self)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.weight(), entry))
+            .filter(|(weight, _)| *weight > 0)
+            .collect())
+    }
+}
+
+fn authority(address: &str, port: u16) -> String {
+    match address.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, port).to_string(),
+        Err(_) => format!("{address}:{port}"),
+    }
+}
+
+async fn socket_addrs(entries: Vec<(u32, ServiceEntry)>) -> Result<Vec<SocketAddr>, anyhow::Error> {
+    let mut addrs = Vec::with_capacity(entries.len());
+    for (_, entry) in entries {
+        let port = entry.port();
+        match entry.address().parse::<IpAddr>() {
+            Ok(ip) => addrs.push(SocketAddr::new(ip, port)),
+            Err(_) => {
+                let host = entry.address().to_string();
+                let resolved =
+                    tokio::task::spawn_blocking(move || (host.as_str(), port).to_socket_addrs())
+                        .await?;
+                match resolved {
+                    Ok(resolved) => addrs.extend(resolved.take(1)),
+                    Err(e) => tracing::warn!(
+                        "Skipping instance {} of {}: failed to resolve {}: {e}",
+                        entry.service().id(),
+                        entry.service().service(),
+                        entry.address()
+                    ),
+                }
+            }
+        }
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+
+    #[test]
+    fn it_weighs_entries_by_health() {
+        let entry = |status: &str| -> ServiceEntry {
+            serde_json::from_value(serde_json::json!({
+                "Node": {"Node": "n1", "Address": "10.0.0.1"},
+                "Service": {"ID": "web-1", "Service": "web", "Weights": {"Passing": 5, "Warning": 2}},
+                "Checks": [{"Node": "n1", "CheckID": "c", "Name": "c", "Status": status}],
+            }))
+            .unwrap()
+        };
+        assert_eq!(entry("passing").weight(), 5);
+        assert_eq!(entry("warning").weight(), 2);
+        assert_eq!(entry("critical").weight(), 0);
+    }
+
+    #[test]
+    fn it_brackets_ipv6_authorities() {
+        assert_eq!(authority("10.0.0.1", 50051), "10.0.0.1:50051");
+        assert_eq!(authority("fd00::1", 50051), "[fd00::1]:50051");
+        assert_eq!(authority("orders.internal", 50051), "orders.internal:50051");
+    }
+
+    #[tokio::test]
+    async fn it_resolves_services() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let addrs = client.resolve_service("consul", None).await.unwrap();
+        assert!(!addrs.is_empty());
+        assert!(addrs.iter().all(|addr| addr.port() > 0));
+        let shuffled = client
+            .resolve_service_shuffled("consul", None)
+            .await
+            .unwrap();
+        assert_eq!(shuffled.len(), addrs.len());
+        let tagged = client
+            .resolve_service("consul", Some("missing-tag"))
+            .await
+            .unwrap();
+        assert!(tagged.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_skips_unresolvable_instances() {
+        let entry = |id: &str, address: &str| -> (u32, ServiceEntry) {
+            let entry = serde_json::json!({
+                "Node": {"Node": "n1", "Address": "10.0.0.1"},
+                "Service": {"ID": id, "Service": "web", "Address": address, "Port": 8080},
+                "Checks": [],
+            });
+            (1, serde_json::from_value(entry).unwrap())
+        };
+        let entries = vec![
+            entry("web-1", "web-1.invalid"),
+            entry("web-2", "10.0.0.2"),
+            entry("web-3", "localhost"),
+        ];
+        let addrs = socket_addrs(entries).await.unwrap();
+        assert_eq!(addrs.len(), 2);
+        assert_eq!(addrs[0], "10.0.0.2:8080".parse().unwrap());
+        assert!(addrs[1].ip().is_loopback());
+    }
+
+    #[test]
+    fn it_defaults_weights() {
+        let service: AgentService =
+            serde_json::from_str(r#"{"ID": "web-1", "Service": "web"}"#).unwrap();
+        assert_eq!(service.weights(), Weights::new(1, 1));
+        assert!(service.tagged_addresses().is_none());
+        let service: AgentService = serde_json::from_str(
+            r#"{"ID": "web-1", "Service": "web", "Weights": {"Passing": 3, "Warning": 0},
+                "TaggedAddresses": {"virtual": {"Address": "240.0.0.1", "Port": 80}}}"#,
+        )
+        .unwrap();
+        assert_eq!(service.weights().passing(), 3);
+        assert_eq!(service.tagged_addresses().unwrap()["virtual"].port(), 80);
+    }
+
+    #[tokio::test]
+    async fn it_lists_consul() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let entries = Health::service("consul")
+            .passing(true)
+            .get(&client)
+            .await
+            .unwrap();
+        assert!(!entries.is_empty());
+        assert_eq!(entries[0].service().service(), "consul");
+        assert!(entries[0].port() > 0);
+        let entries = Health::service("consul")
+            .filter(Filter::selector("Service.Service").ne("consul"))
+            .get(&client)
+            .await
+            .unwrap();
+        assert!(entries.is_empty());
+        let entries = Health::service("consul")
+            .near("_agent")
+            .get(&client)
+            .await
+            .unwrap();
+        assert!(!entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_lists_checks() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let checks = Health::state(State::Any).checks(&client).await.unwrap();
+        assert!(!checks.is_empty());
+        let node = checks[0].node().to_string();
+        let checks = Health::node(&node).checks(&client).await.unwrap();
+        assert!(checks.iter().all(|check| check.node() == node));
+        let checks = Health::state(State::Critical)
+            .checks(&client)
+            .await
+            .unwrap();
+        assert!(
+            checks
+                .iter()
+                .all(|check| check.state() == Some(State::Critical))
+        );
+    }
+
+    #[test]
+    fn it_parses_checks() {
+        let mut check: HealthCheck = serde_json::from_value(serde_json::json!({
+            "Node": "node1",
+            "CheckID": "service:web",
+            "Name": "Web HTTP",
+            "Status": "passing",
+            "Notes": "primary endpoint",
+            "Output": "HTTP GET http://127.0.0.1:8080/health: 200 OK",
+            "ServiceID": "web",
+            "ServiceName": "web",
+            "ServiceTags": ["primary"],
+            "Type": "http",
+            "ExposedPort": 21500,
+            "Definition": {"Interval": "1m30s", "Timeout": "500ms"},
+            "CreateIndex": 10,
+            "ModifyIndex": 12
+        }))
+        .unwrap();
+        assert_eq!(check.state(), Some(State::Passing));
+        assert_eq!(check.notes(), "primary endpoint");
+        assert_eq!(check.service_tags(), ["primary"]);
+        assert_eq!(check.check_type(), "http");
+        assert_eq!(check.exposed_port(), Some(21500));
+        assert_eq!(check.interval(), Some(Duration::from_secs(90)));
+        assert_eq!(check.timeout(), Some(Duration::from_millis(500)));
+        check.truncate_output(8);
+        assert_eq!(check.output(), "HTTP GET");
+    }
+}
@@ -0,0 +1,149 @@
+//! Keeping a TTL check alive from the service it belongs to.
+//!
+//! A [`CheckHeartbeat`] reports the check's status every interval, which
+//! should be comfortably shorter than the check's TTL. Switching the status
+//! is reported right away:
+//!
+//! ```ignore
+//! let heartbeat = CheckHeartbeat::spawn(&client, "service:web-1", Duration::from_secs(10));
+//! heartbeat.set_warning("queue backlog above 10k");
+//! heartbeat.set_passing(None::<String>);
+//! ```
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::Client;
+use crate::agent::Agent;
+use crate::health::CheckStatus;
+
+/// Status and output reported on every beat.
+type Report = (CheckStatus, Option<String>);
+
+/// Running heartbeat; the background task stops when it is dropped, after
+/// which the check goes critical once its TTL expires.
+#[derive(Debug)]
+pub struct CheckHeartbeat {
+    report: watch::Sender<Report>,
+}
+
+impl CheckHeartbeat {
+    /// Starts reporting the check `check_id` as passing every `interval`.
+    /// Failed updates are logged and retried on the next beat.
+    pub fn spawn<S>(client: &Client, check_id: S, interval: Duration) -> Self
+    where
+        S: Into<String>,
+    {
+        let (tx, mut rx) = watch::channel((CheckStatus::Passing, None));
+        let client = client.clone();
+        let check_id = check_id.into();
+        tokio::spawn(async move {
+            loop {
+                let (status, output) = rx.borrow_and_update().clone();
+                if let Err(e) =
+                    Agent::update_check(&client, &check_id, status, output.as_deref()).await
+                {
+                    tracing::warn!("Failed to update check {check_id}: {e:#}");
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    changed = rx.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Self { report: tx }
+    }
+
+    pub fn set_passing<S>(&self, note: Option<S>) -> &Self
+    where
+        S: Into<String>,
+    {
+        self.set(CheckStatus::Passing, note.map(Into::into))
+    }
+
+    pub fn set_warning<S>(&self, note: S) -> &Self
+    where
+        S: Into<String>,
+    {
+        self.set(CheckStatus::Warning, Some(note.into()))
+    }
+
+    pub fn set_critical<S>(&self, note: S) -> &Self
+    where
+        S: Into<String>,
+    {
+        self.set(CheckStatus::Critical, Some(note.into()))
+    }
+
+    /// Status currently reported.
+    pub fn status(&self) -> CheckStatus {
+        self.report.borrow().0
+    }
+
+    fn set(&self, status: CheckStatus, note: Option<String>) -> &Self {
+        self.report.send_replace((status, note));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+
+    use super::*;
+
+    async fn check(client: &Client, check_id: &str) -> (String, String) {
+        let checks = Agent::checks(client).await.unwrap();
+        let check = &checks[check_id];
+        (check.status().to_string(), check.output().to_string())
+    }
+
+    #[tokio::test]
+    async fn it_beats() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let registration = serde_json::json!({
+            "ID": "heartbeat-ttl",
+            "Name": "heartbeat",
+            "TTL": "30s",
+        });
+        client
+            .send(
+                Method::PUT,
+                "v1/agent/check/register",
+                &(),
+                Some(registration),
+                None,
+            )
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        let heartbeat = CheckHeartbeat::spawn(&client, "heartbeat-ttl", Duration::from_millis(100));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(check(&client, "heartbeat-ttl").await.0, "passing");
+        heartbeat.set_warning("backlog");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            check(&client, "heartbeat-ttl").await,
+            ("warning".to_string(), "backlog".to_string())
+        );
+        heartbeat.set_critical("down");
+        assert_eq!(heartbeat.status(), CheckStatus::Critical);
+        drop(heartbeat);
+        client
+            .send(
+                Method::PUT,
+                "v1/agent/check/deregister/heartbeat-ttl",
+                &(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Local view of healthy service instances for latency-sensitive lookups.
+//!
+//! A [`ServiceCache`] follows each configured service with blocking health
+//! queries in the background, so reading the current instances is a
+//! non-async, allocation-free snapshot. [`ServiceCache::shard`] maps work
+//! keys onto instances with rendezvous hashing, so consumers registered as
+//! a service can split work without coordinating:
+//!
+//! ```ignore
+//! let cache = ServiceCache::new(&client)
+//!     .service("web", Health::service("web").tag("v1"))
+//!     .service("api", Health::service("api"));
+//! cache.ready().await;
+//! for instance in cache.instances("web").iter() {
+//!     println!("{}:{}", instance.address(), instance.port());
+//! }
+//! let owner = cache.shard("worker", b"tenant-42");
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::time::error::Elapsed;
+
+use crate::Client;
+use crate::health::{Health, ServiceEntry};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    id: String,
+    service: String,
+    node: String,
+    address: String,
+    port: u16,
+    tags: Vec<String>,
+    meta: HashMap<String, String>,
+}
+
+impl Instance {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Service address, or the node address when the service has none.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn meta(&self) -> &HashMap<String, String> {
+        &self.meta
+    }
+}
+
+impl From<&ServiceEntry> for Instance {
+    fn from(entry: &ServiceEntry) -> Self {
+        Self {
+            id: entry.service().id().to_string(),
+            service: entry.service().service().to_string(),
+            node: entry.node().node().to_string(),
+            address: entry.address().to_string(),
+            port: entry.port(),
+            tags: entry.service().tags().to_vec(),
+            meta: entry.service().meta().cloned().unwrap_or_default(),
+        }
+    }
+}
+
+type Instances = watch::Receiver<Arc<Vec<Instance>>>;
+
+#[derive(Debug, Clone)]
+pub struct ServiceCache {
+    client: Client,
+    services: HashMap<String, Instances>,
+}
+
+impl ServiceCache {
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            services: HashMap::new(),
+        }
+    }
+
+    /// Starts following the passing instances matched by `health` under
+    /// `name`; on errors the last known instances are kept.
+    pub fn service<N>(mut self, name: N, health: Health) -> Self
+    where
+        N: Into<String>,
+    {
+        let instances = health.follow(&self.client, |entry| Instance::from(entry));
+        self.services.insert(name.into(), instances);
+        self
+    }
+
+    /// Waits until every configured service has at least one instance,
+    /// forever if one never gets any; see [`ServiceCache::ready_timeout`].
+    pub async fn ready(&self) {
+        for instances in self.services.values() {
+            Health::followed(instances).await;
+        }
+    }
+
+    /// [`ServiceCache::ready`] giving up after `timeout`.
+    pub async fn ready_timeout(&self, timeout: Duration) -> Result<(), Elapsed> {
+        tokio::time::timeout(timeout, self.ready()).await
+    }
+
+    /// Current instances of the service configured as `name`, empty if it
+    /// is unknown or has not been resolved yet.
+    pub fn instances(&self, name: &str) -> Arc<Vec<Instance>> {
+        self.services
+            .get(name)
+            .map(|instances| instances.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Instance of the service configured as `name` that owns `key`; see
+    /// [`rendezvous`].
+    pub fn shard<K>(&self, name: &str, key: K) -> Option<Instance>
+    where
+        K: AsRef<[u8]>,
+    {
+        rendezvous(&self.instances(name), key.as_ref()).cloned()
+    }
+}
+
+/// Picks the owner of `key` by rendezvous (highest random weight) hashing
+/// over `node/id` of the instances, as service IDs are only unique per
+/// agent. Every process with the same instances picks the same
+/// owner, and when an instance leaves only the keys it owned move.
+pub fn rendezvous<'a>(instances: &'a [Instance], key: &[u8]) -> Option<&'a Instance> {
+    instances
+        .iter()
+        .max_by_key(|instance| (weight(instance, key), &instance.node, &instance.id))
+}
+
+/// FNV-1a over `node/id`, a separator and `key`, finished with the
+/// splitmix64 mixer. Stable across processes and Rust versions, unlike
+/// `DefaultHasher`.
+fn weight(instance: &Instance, key: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let bytes = instance.node.as_bytes().iter().chain(b"/");
+    for byte in bytes
+        .chain(instance.id.as_bytes())
+        .chain(&[0xff])
+        .chain(key)
+    {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(id: &str) -> Instance {
+        Instance {
+            id: id.to_string(),
+            service: "worker".to_string(),
+            node: "node".to_string(),
+            address: "10.0.0.1".to_string(),
+            port: 8080,
+            tags: vec![],
+            meta: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn it_shards_keys() {
+        let all: Vec<_> = ["a", "b", "c"].into_iter().map(instance).collect();
+        let keys: Vec<_> = (0..300).map(|i| format!("key-{i}")).collect();
+        let owners: Vec<_> = keys
+            .iter()
+            .map(|key| rendezvous(&all, key.as_bytes()).unwrap().id().to_string())
+            .collect();
+        for id in ["a", "b", "c"] {
+            let owned = owners.iter().filter(|owner| *owner == id).count();
+            assert!((50..150).contains(&owned), "{id} owns {owned} keys");
+        }
+        let rest = &all[..2];
+        for (key, owner) in keys.iter().zip(&owners) {
+            let moved = rendezvous(rest, key.as_bytes()).unwrap().id();
+            if owner != "c" {
+                assert_eq!(moved, owner);
+            }
+        }
+        assert!(rendezvous(&[], b"key").is_none());
+    }
+
+    #[test]
+    fn it_shards_same_ids_across_nodes() {
+        let all: Vec<_> = ["n1", "n2", "n3"]
+            .into_iter()
+            .map(|node| Instance {
+                node: node.to_string(),
+                ..instance("worker")
+            })
+            .collect();
+        let mut owned = HashMap::new();
+        for i in 0..300 {
+            let key = format!("key-{i}");
+            let owner = rendezvous(&all, key.as_bytes()).unwrap();
+            *owned.entry(owner.node.clone()).or_insert(0) += 1;
+        }
+        for node in ["n1", "n2", "n3"] {
+            assert!((50..150).contains(&owned[node]), "{node} owns {owned:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn it_caches_instances() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let cache = ServiceCache::new(&client).service("consul", Health::service("consul"));
+        cache.ready_timeout(Duration::from_secs(5)).await.unwrap();
+        let instances = cache.instances("consul");
+        assert!(!instances.is_empty());
+        assert!(instances.iter().all(|i| i.service() == "consul"));
+        assert!(cache.instances("unknown").is_empty());
+    }
+}
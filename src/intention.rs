@@ -0,0 +1,233 @@
+//! Service intention checks, optionally cached for high-QPS authorization.
+//!
+//! An [`IntentionCache`] remembers [`Intention::check`] results per source
+//! and destination pair. A background blocking query on the intention list
+//! invalidates every cached result as soon as any intention changes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch as channel;
+
+use crate::Client;
+use crate::watch::Watch;
+
+pub struct Intention;
+
+#[derive(Serialize)]
+struct CheckQuery<'a> {
+    source: &'a str,
+    destination: &'a str,
+}
+
+#[derive(Default, Serialize)]
+struct BlockingQuery {
+    index: Option<u64>,
+    wait: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CheckResponse {
+    allowed: bool,
+}
+
+impl Intention {
+    /// Whether a connection from `source` to `destination` is allowed,
+    /// taking the default ACL policy into account when no intention matches.
+    pub async fn check(
+        client: &Client,
+        source: &str,
+        destination: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let query = CheckQuery {
+            source,
+            destination,
+        };
+        let rs: CheckResponse = client
+            .send(
+                Method::GET,
+                "v1/connect/intentions/check",
+                &query,
+                None,
+                None,
+            )
+            .await?
+            .decode()?;
+        Ok(rs.allowed)
+    }
+
+    /// Yields an event whenever any intention is created, changed or deleted.
+    pub fn watch(client: &Client) -> Watch {
+        let client = client.clone();
+        Watch::new(move |index, wait| {
+            let client = client.clone();
+            let query = BlockingQuery {
+                index: Some(index),
+                wait: Some(format!("{}ms", wait.as_millis())),
+            };
+            async move {
+                client
+                    .send(Method::GET, "v1/connect/intentions", &query, None, None)
+                    .await
+            }
+        })
+    }
+}
+
+/// Check results stamped with the intentions index they were read at.
+type Results = Mutex<HashMap<(String, String), (u64, bool)>>;
+
+#[derive(Debug, Clone)]
+pub struct IntentionCache {
+    client: Client,
+    index: channel::Receiver<u64>,
+    results: Arc<Results>,
+}
+
+impl IntentionCache {
+    /// Starts following intention changes. While that fails, e.g. without
+    /// permission to list intentions, every check asks Consul, as a revoked
+    /// intention would go unnoticed.
+    pub fn new(client: &Client) -> Self {
+        let (tx, rx) = channel::channel(0);
+        let mut updates = Intention::watch(client);
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = tx.closed() => return,
+                    event = updates.next() => event,
+                };
+                match event {
+                    Ok(event) => {
+                        tx.send_replace(event.index());
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to watch intentions: {e:#}");
+                        tx.send_replace(0);
+                    }
+                }
+            }
+        });
+        Self {
+            client: client.clone(),
+            index: rx,
+            results: Arc::default(),
+        }
+    }
+
+    /// Cached [`Intention::check`]; only asks Consul on a miss, after
+    /// intentions changed, or while changes can't be followed.
+    pub async fn check(&self, source: &str, destination: &str) -> Result<bool, anyhow::Error> {
+        let index = *self.index.borrow();
+        let key = (source.to_string(), destination.to_string());
+        if index > 0
+            && let Some((cached, allowed)) = self.results.lock().unwrap().get(&key)
+            && *cached == index
+        {
+            return Ok(*allowed);
+        }
+        let allowed = Intention::check(&self.client, source, destination).await?;
+        let mut results = self.results.lock().unwrap();
+        results.retain(|_, (cached, _)| *cached == index);
+        if index > 0 {
+            results.insert(key, (index, allowed));
+        }
+        Ok(allowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_checks_intentions() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let pair = [("source", "intention-web"), ("destination", "intention-db")];
+        let allowed = Intention::check(&client, "intention-web", "intention-db")
+            .await
+            .unwrap();
+        let cache = IntentionCache::new(&client);
+        assert_eq!(
+            cache.check("intention-web", "intention-db").await.unwrap(),
+            allowed
+        );
+        let action = if allowed { "deny" } else { "allow" };
+        client
+            .send(
+                Method::PUT,
+                "v1/connect/intentions/exact",
+                &pair,
+                Some(serde_json::json!({"Action": action})),
+                None,
+            )
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        let flipped = flips(&cache, !allowed).await;
+        client
+            .send(
+                Method::DELETE,
+                "v1/connect/intentions/exact",
+                &pair,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        assert!(flipped, "cached result kept after the intention changed");
+        assert!(flips(&cache, allowed).await);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_bypasses_the_cache_while_the_watch_fails() {
+        use std::time::Duration;
+
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let list = "v1/connect/intentions";
+        agent.expect("GET", list, Reply::json(serde_json::json!([])).index(3));
+        let denied = Reply::new(403, "Permission denied").delay(Duration::from_millis(300));
+        agent.expect("GET", list, denied);
+        for _ in 0..3 {
+            let allowed = Reply::json(serde_json::json!({"Allowed": true}));
+            agent.expect("GET", "v1/connect/intentions/check", allowed);
+        }
+        let client = Client::new(agent.url()).unwrap();
+        let cache = IntentionCache::new(&client);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(cache.check("web", "db").await.unwrap());
+        assert!(cache.check("web", "db").await.unwrap());
+        let checks = || {
+            agent
+                .requests()
+                .iter()
+                .filter(|rq| rq.path() == "v1/connect/intentions/check")
+                .count()
+        };
+        assert_eq!(checks(), 1);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(cache.check("web", "db").await.unwrap());
+        assert!(cache.check("web", "db").await.unwrap());
+        assert_eq!(checks(), 3);
+    }
+
+    /// Whether the cached check turns `expected` once the change is seen.
+    async fn flips(cache: &IntentionCache, expected: bool) -> bool {
+        for _ in 0..50 {
+            if cache.check("intention-web", "intention-db").await.unwrap() == expected {
+                return true;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        false
+    }
+}
@@ -1,13 +1,179 @@
+pub mod acl;
+pub mod agent;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod catalog;
+pub mod config;
+pub mod connect;
+pub mod diff;
+pub mod discovery;
+pub mod endpoint;
+pub mod error;
+pub mod failover;
+pub mod filter;
+pub mod format;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod heartbeat;
+pub mod instances;
+pub mod intention;
+pub mod middleware;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod operator;
+pub mod peering;
 pub mod prelude;
+#[cfg(feature = "figment")]
+pub mod provider;
+pub mod queue;
+mod ratelimit;
+pub mod replicate;
+#[cfg(feature = "resolve")]
+pub mod resolve;
+pub mod retry;
+pub mod scoped;
+pub mod semaphore;
+pub mod session;
+pub mod simple;
+pub mod status;
+#[cfg(feature = "metrics")]
+mod telemetry;
+#[cfg(feature = "template")]
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod token;
+pub mod watch;
 use base64::prelude::*;
 
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::Stream;
 use reqwest::Method;
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Client {
-    url: url::Url,
+    endpoints: Arc<failover::Endpoints>,
     client: reqwest::Client,
+    compression: bool,
+    stats: Arc<Stats>,
+    cache: Option<Arc<cache::ResponseCache>>,
+    timeout: Option<Duration>,
+    datacenter: Option<String>,
+    tokens: Option<token::Tokens>,
+    middleware: middleware::Stack,
+    limiter: Arc<ratelimit::RateLimiter>,
+    retry: Option<retry::RetryPolicy>,
+    request_id: Option<RequestId>,
+    /// Only a successful read is kept; a failed one is retried on the next
+    /// gated call.
+    agent_version: Arc<tokio::sync::OnceCell<agent::AgentVersion>>,
+    #[cfg(feature = "audit")]
+    audit: Option<audit::Audit>,
+}
+
+#[derive(Debug)]
+pub struct ClientBuilder {
+    urls: Vec<String>,
+    strategy: failover::Strategy,
+    cooldown: Duration,
+    compression: bool,
+    cache_idle: Option<Duration>,
+    timeout: Option<Duration>,
+    datacenter: Option<String>,
+    tokens: Option<token::Tokens>,
+    middleware: middleware::Stack,
+    limiter: ratelimit::RateLimiter,
+    retry: Option<retry::RetryPolicy>,
+    request_id_header: String,
+    request_id: Option<GenerateId>,
+    #[cfg(feature = "audit")]
+    audit: Option<audit::Audit>,
+    #[cfg(feature = "audit")]
+    audit_accessor: Option<String>,
+    http: Http,
+}
+
+/// Source of correlation IDs, called once per request.
+#[derive(Clone)]
+struct GenerateId(Arc<dyn Fn() -> String + Send + Sync>);
+
+impl std::fmt::Debug for GenerateId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GenerateId")
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RequestId {
+    header: reqwest::header::HeaderName,
+    generate: GenerateId,
+}
+
+type ConfigureHttp = Box<dyn FnOnce(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send>;
+
+/// How the underlying reqwest client is obtained: built with the
+/// `configure` steps, or given. Setting both is an error.
+#[derive(Default)]
+struct Http {
+    configure: Vec<ConfigureHttp>,
+    client: Option<reqwest::Client>,
+}
+
+impl std::fmt::Debug for Http {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Http")
+            .field("configure", &format_args!("{} steps", self.configure.len()))
+            .field("client", &self.client)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    responses: AtomicU64,
+    compressed_responses: AtomicU64,
+    wire_bytes: AtomicU64,
+    body_bytes: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferStats {
+    responses: u64,
+    compressed_responses: u64,
+    wire_bytes: u64,
+    body_bytes: u64,
+}
+
+impl TransferStats {
+    pub fn responses(&self) -> u64 {
+        self.responses
+    }
+
+    pub fn compressed_responses(&self) -> u64 {
+        self.compressed_responses
+    }
+
+    /// Bytes of response bodies as received, before decompression.
+    pub fn wire_bytes(&self) -> u64 {
+        self.wire_bytes
+    }
+
+    /// Bytes of response bodies, after decompression.
+    pub fn body_bytes(&self) -> u64 {
+        self.body_bytes
+    }
 }
 
 trait Helper {
@@ -31,46 +197,205 @@ impl Helper for reqwest::RequestBuilder {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Kv {
     path: String,
     query: KvQuery,
+    timeout: Option<Duration>,
     payload: Option<serde_json::Value>,
     body: Option<Vec<u8>>,
 }
 
-#[derive(Default, Serialize)]
+/// `recurse`, `raw` and `keys` are flags: Consul checks for their presence
+/// and ignores the value, so they are only sent when set to `true`.
+#[derive(Default, Clone, Serialize)]
 pub struct KvQuery {
     dc: Option<String>,
+    #[serde(skip_serializing_if = "flag_unset", serialize_with = "flag")]
     recurse: Option<bool>,
+    #[serde(skip_serializing_if = "flag_unset", serialize_with = "flag")]
     raw: Option<bool>,
+    #[serde(skip_serializing_if = "flag_unset", serialize_with = "flag")]
     keys: Option<bool>,
     separator: Option<String>,
+    flags: Option<u64>,
+    acquire: Option<String>,
+    release: Option<String>,
+    index: Option<u64>,
+    wait: Option<String>,
+    cas: Option<u64>,
 }
 
-#[derive(Debug)]
+impl endpoint::Endpoint for Kv {
+    type Query = KvQuery;
+
+    fn path(&self) -> std::borrow::Cow<'_, str> {
+        self.path.as_str().into()
+    }
+
+    fn query(&self) -> &KvQuery {
+        &self.query
+    }
+
+    fn take_payload(&mut self) -> Option<serde_json::Value> {
+        self.payload.take()
+    }
+
+    fn take_body(&mut self) -> Option<Vec<u8>> {
+        self.body.take()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+fn flag_unset(value: &Option<bool>) -> bool {
+    *value != Some(true)
+}
+
+/// Value-less form of a flag, e.g. `?recurse=`.
+fn flag<S>(_: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str("")
+}
+
+/// Consul's answer to a request. Its `Debug` output shows the body's size
+/// only, as it may hold KV values.
+#[derive(Clone)]
 pub struct Response {
+    method: Method,
+    path: String,
     status: u16,
-    json: Option<serde_json::Value>,
-    raw: String,
+    index: Option<u64>,
+    /// Body holds user data rather than JSON.
+    raw: bool,
+    body: Bytes,
+    request_id: Option<String>,
+}
+
+impl std::fmt::Debug for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Response")
+            .field("method", &self.method)
+            .field("path", &self.path)
+            .field("status", &self.status)
+            .field("index", &self.index)
+            .field("raw", &self.raw)
+            .field("body", &format_args!("<{} bytes>", self.body.len()))
+            .field("request_id", &self.request_id)
+            .finish()
+    }
 }
 
 impl Response {
+    /// Bodies of `raw` reads are user data and never decoded as JSON. Other
+    /// bodies are only parsed when asked for, straight into the wanted type.
+    fn new(
+        method: Method,
+        url: &url::Url,
+        status: u16,
+        index: Option<u64>,
+        body: Bytes,
+        raw: bool,
+    ) -> Self {
+        Self {
+            method,
+            path: url.path().to_string(),
+            status,
+            index,
+            raw,
+            body,
+            request_id: None,
+        }
+    }
+
+    /// Correlation ID the request was sent with, see
+    /// [`ClientBuilder::request_id`].
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Body as text; invalid UTF-8 is replaced.
     pub fn raw(self) -> String {
-        self.raw
+        self.text().into_owned()
+    }
+
+    pub fn bytes(self) -> Bytes {
+        self.body
     }
 
     pub fn json(self) -> Option<serde_json::Value> {
-        self.json
+        self.parse().ok()
     }
 
     pub fn status(self) -> u16 {
         self.status
     }
 
+    pub fn index(&self) -> Option<u64> {
+        self.index
+    }
+
     pub fn is_success(&self) -> bool {
         self.status == 200
     }
+
+    /// Boolean body returned by PUTs with `cas`, `acquire` or `release`.
+    pub fn as_bool(&self) -> Option<bool> {
+        self.parse().ok()
+    }
+
+    /// Like [`Response::as_bool`], failing on error statuses and on bodies
+    /// that are neither `true` nor `false`.
+    pub fn into_bool(self) -> Result<bool, anyhow::Error> {
+        let rs = self.error_for_status()?;
+        rs.as_bool().ok_or_else(|| {
+            let body = String::from_utf8_lossy(&rs.body);
+            anyhow::anyhow!(
+                "Expected true or false from {} {}, got {body:?}",
+                rs.method,
+                rs.path
+            )
+        })
+    }
+
+    pub(crate) fn error_for_status(self) -> Result<Self, anyhow::Error> {
+        if !self.is_success() {
+            return Err(self.into_error());
+        }
+        Ok(self)
+    }
+
+    /// Typed error for this response, see [`error`].
+    pub(crate) fn into_error(self) -> anyhow::Error {
+        let error = error::status_error(&self.method, &self.path, self.status, &self.body);
+        error::with_request_id(error, self.request_id.as_deref())
+    }
+
+    pub(crate) fn text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    pub(crate) fn decode<T>(self) -> Result<T, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.error_for_status()?.parse()
+    }
+
+    /// Deserializes the body without an intermediate `serde_json::Value`.
+    fn parse<T>(&self) -> Result<T, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if self.raw || self.body.is_empty() {
+            anyhow::bail!("No JSON in response");
+        }
+        Ok(serde_json::from_slice(&self.body)?)
+    }
 }
 
 impl Kv {
@@ -116,6 +441,50 @@ impl Kv {
         self
     }
 
+    pub fn flags(mut self, flags: u64) -> Self {
+        self.query.flags = Some(flags);
+        self
+    }
+
+    /// Check-and-set: the write only succeeds if the key's `ModifyIndex`
+    /// matches, or if the key does not exist when `index` is 0.
+    pub fn cas(mut self, index: u64) -> Self {
+        self.query.cas = Some(index);
+        self
+    }
+
+    pub fn acquire<S>(mut self, session: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.acquire = Some(session.into());
+        self
+    }
+
+    pub fn release<S>(mut self, session: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.release = Some(session.into());
+        self
+    }
+
+    pub fn index(mut self, index: u64) -> Self {
+        self.query.index = Some(index);
+        self
+    }
+
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.query.wait = Some(format!("{}ms", wait.as_millis()));
+        self
+    }
+
+    /// Overrides the client's default timeout for this request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     pub fn payload(mut self, payload: serde_json::Value) -> Self {
         self.payload = Some(payload);
         self
@@ -143,98 +512,1255 @@ impl Kv {
         method: reqwest::Method,
         client: &Client,
     ) -> Result<Response, anyhow::Error> {
-        let url = client.url.join(&self.path)?;
-        let rs = client
-            .client
-            .request(method, url)
-            .query(&self.query)
-            .apply_if(self.payload, |k, v| k.json(&v))
-            .apply_if(self.body, |k, v| k.body(v))
-            .send()
-            .await?;
-        let status = rs.status();
-        let raw = rs.text().await?;
-        let json = serde_json::from_str::<serde_json::Value>(&raw).ok();
-        Ok(Response {
-            status: status.as_u16(),
-            json,
-            raw,
-        })
+        endpoint::send(self, method, client).await
     }
 
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
     pub async fn get(self, client: &Client) -> Result<Option<Record>, anyhow::Error> {
         let rs = self.send_request(Method::GET, client).await?;
         if rs.status == 404 {
             return Ok(None);
         };
-        let mut key: Vec<Record> = rs.try_into()?;
+        let mut key: Vec<Record> = rs.error_for_status()?.try_into()?;
         Ok(key.pop())
     }
 
+    /// Fetches several exact keys, relative to this builder's path, with up
+    /// to [`GET_MANY_CONCURRENCY`] requests in flight. Keys that don't exist
+    /// map to `None`; the first failed request fails the whole batch.
+    pub async fn get_many(
+        self,
+        client: &Client,
+        keys: &[&str],
+    ) -> Result<HashMap<String, Option<Record>>, anyhow::Error> {
+        use futures_util::{StreamExt, TryStreamExt};
+
+        futures_util::stream::iter(keys)
+            .map(|key| {
+                let mut kv = self.clone();
+                kv.path.push_str(key);
+                async move { Ok::<_, anyhow::Error>((key.to_string(), kv.get(client).await?)) }
+            })
+            .buffer_unordered(GET_MANY_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Raw value of the key, without base64 or JSON decoding.
+    pub async fn get_bytes(self, client: &Client) -> Result<Option<Bytes>, anyhow::Error> {
+        let rs = self.raw(true).send_request(Method::GET, client).await?;
+        if rs.status == 404 {
+            return Ok(None);
+        };
+        Ok(Some(rs.error_for_status()?.bytes()))
+    }
+
+    /// Decodes the key's JSON value, or `T::default()` if it doesn't exist.
+    pub async fn get_or_default<T>(self, client: &Client) -> Result<T, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned + Default,
+    {
+        match self.get(client).await? {
+            Some(record) => Ok(serde_json::from_value(record.value()?)?),
+            None => Ok(T::default()),
+        }
+    }
+
+    /// Decodes the key's value in the format recorded in its flags.
+    pub async fn get_as<T>(self, client: &Client) -> Result<Option<T>, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.get(client)
+            .await?
+            .map(|record| record.value_as())
+            .transpose()
+    }
+
+    /// Read-modify-write of a JSON value: `f` gets the current value (`None`
+    /// if the key doesn't exist) and its result is written back with CAS on
+    /// the observed `ModifyIndex`. On conflict the key is re-read after a
+    /// short randomized backoff and `f` called again, up to
+    /// [`CAS_ATTEMPTS`] times before failing with [`error::CasConflict`].
+    /// Returns the value that was written.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
+    pub async fn update<T, F>(self, client: &Client, mut f: F) -> Result<T, anyhow::Error>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+        F: FnMut(Option<T>) -> T,
+    {
+        for attempt in 1..=CAS_ATTEMPTS {
+            let (current, index) = match self.clone().get(client).await? {
+                Some(record) => (
+                    Some(serde_json::from_value(record.value()?)?),
+                    record.modify_index() as u64,
+                ),
+                None => (None, 0),
+            };
+            let next = f(current);
+            let written = self
+                .clone()
+                .cas(index)
+                .payload(serde_json::to_value(&next)?)
+                .put_with_result(client)
+                .await?;
+            if written {
+                return Ok(next);
+            }
+            if attempt < CAS_ATTEMPTS {
+                tracing::debug!("CAS conflict on {}, retrying", self.path);
+                let delay = CAS_BACKOFF
+                    .saturating_mul(1 << attempt.min(16))
+                    .min(CAS_MAX_BACKOFF);
+                tokio::time::sleep(delay.mul_f64(0.5 + random_unit() / 2.0)).await;
+            }
+        }
+        Err(error::CasConflict::new(&self.path, CAS_ATTEMPTS).into())
+    }
+
+    /// Applies an RFC 7396 JSON merge patch to the key's value with the same
+    /// CAS retry as [`Kv::update`]; a missing key is patched as `null`.
+    /// Returns the merged document.
+    pub async fn merge_patch(
+        self,
+        client: &Client,
+        patch: &serde_json::Value,
+    ) -> Result<serde_json::Value, anyhow::Error> {
+        self.update(client, |current: Option<serde_json::Value>| {
+            let mut doc = current.unwrap_or_default();
+            apply_merge_patch(&mut doc, patch);
+            doc
+        })
+        .await
+    }
+
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
+    pub async fn get_stream(
+        self,
+        client: &Client,
+    ) -> Result<Option<impl Stream<Item = Result<Bytes, anyhow::Error>>>, anyhow::Error> {
+        let kv = self.raw(true);
+        let url = client.request_url(&kv.path, &kv.query)?;
+        let Some(rs) = client.execute_unbuffered(url, kv.timeout).await? else {
+            return Ok(None);
+        };
+        let stream = futures_util::stream::try_unfold(rs, |mut rs| async move {
+            Ok(rs.chunk().await?.map(|chunk| (chunk, rs)))
+        });
+        Ok(Some(stream))
+    }
+
+    pub fn watch(self, client: &Client) -> watch::Watch {
+        let client = client.clone();
+        watch::Watch::new(move |index, wait| {
+            let client = client.clone();
+            let kv = self.clone().index(index).wait(wait);
+            async move { kv.send_request(Method::GET, &client).await }
+        })
+    }
+
+    /// Watches a single key, yielding typed [`watch::KeyEvent`]s.
+    pub fn watch_key(self, client: &Client) -> watch::KeyWatch {
+        watch::KeyWatch::new(self.watch(client))
+    }
+
+    /// Blocks until the key's value satisfies `predicate`, returning the
+    /// matching record. The current value is checked first, then every change
+    /// reported by blocking queries; fails once `timeout` elapses.
+    pub async fn wait_for<F>(
+        self,
+        client: &Client,
+        mut predicate: F,
+        timeout: Duration,
+    ) -> Result<Record, anyhow::Error>
+    where
+        F: FnMut(&Record) -> bool,
+    {
+        let path = self.path.clone();
+        let mut watch = self.watch_key(client).emit_deletes(false);
+        let wait = async {
+            loop {
+                match watch.next().await? {
+                    watch::KeyEvent::Initial(Some(record)) | watch::KeyEvent::Changed(record)
+                        if predicate(&record) =>
+                    {
+                        return Ok(record);
+                    }
+                    _ => continue,
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(record) => record,
+            Err(_) => anyhow::bail!("Timed out after {timeout:?} waiting for {path}"),
+        }
+    }
+
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
     pub async fn put(self, client: &Client) -> Result<Response, anyhow::Error> {
         self.send_request(Method::PUT, client).await
     }
 
+    /// PUT for writes Consul answers with `true` or `false`, e.g. with `cas`,
+    /// `acquire` or `release`; see [`Response::into_bool`].
+    pub async fn put_with_result(self, client: &Client) -> Result<bool, anyhow::Error> {
+        self.put(client).await?.into_bool()
+    }
+
+    pub async fn put_string<S>(self, client: &Client, value: S) -> Result<Response, anyhow::Error>
+    where
+        S: Into<String>,
+    {
+        self.body(value.into().into_bytes()).put(client).await
+    }
+
+    pub async fn put_bytes<B>(self, client: &Client, value: B) -> Result<Response, anyhow::Error>
+    where
+        B: Into<Vec<u8>>,
+    {
+        self.body(value.into()).put(client).await
+    }
+
+    /// Writes the key while acquiring it under `session`, so it is removed
+    /// when the session ends if the session was created with
+    /// [`session::Behavior::Delete`]. Returns `false` if another session
+    /// holds the key.
+    pub async fn put_ephemeral<S>(self, client: &Client, session: S) -> Result<bool, anyhow::Error>
+    where
+        S: Into<String>,
+    {
+        self.acquire(session).put_with_result(client).await
+    }
+
+    /// Serializes `value` in `format` and marks the format in the key's flags.
+    pub async fn put_serialized<T>(
+        self,
+        client: &Client,
+        value: &T,
+        format: format::Format,
+    ) -> Result<Response, anyhow::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.flags(format.flags())
+            .body(format.serialize(value)?)
+            .put(client)
+            .await
+    }
+
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
     pub async fn delete(self, client: &Client) -> Result<Response, anyhow::Error> {
         self.send_request(Method::DELETE, client).await
     }
 
+    /// DELETE returning whether it took effect, `false` on a `cas` conflict;
+    /// see [`Response::into_bool`].
+    pub async fn delete_with_result(self, client: &Client) -> Result<bool, anyhow::Error> {
+        self.delete(client).await?.into_bool()
+    }
+
+    /// Deletes every key under the prefix and returns the keys that existed
+    /// beforehand. An empty prefix, i.e. the whole store, is refused.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
+    pub async fn delete_tree(self, client: &Client) -> Result<Vec<String>, anyhow::Error> {
+        let keys = self.clone().delete_tree_dry_run(client).await?;
+        self.recurse(true)
+            .delete(client)
+            .await?
+            .error_for_status()?;
+        Ok(keys)
+    }
+
+    /// Keys that [`Kv::delete_tree`] would delete, without deleting them.
+    pub async fn delete_tree_dry_run(self, client: &Client) -> Result<Vec<String>, anyhow::Error> {
+        if self
+            .path
+            .trim_start_matches("v1/kv/")
+            .trim_matches('/')
+            .is_empty()
+        {
+            anyhow::bail!("Refusing to delete the whole KV store");
+        }
+        let mut kv = self;
+        kv.query.separator = None;
+        kv.keys_list(client).await
+    }
+
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
     pub async fn list(self, client: &Client) -> Result<Vec<Record>, anyhow::Error> {
         let rs = self.recurse(true).send_request(Method::GET, client).await?;
         if rs.status == 404 {
             return Ok(vec![]);
         };
 
-        rs.try_into()
+        rs.error_for_status()?.try_into()
+    }
+
+    /// Like [`Kv::list`], telling a missing prefix apart from an empty
+    /// result and carrying the index to start a watch from.
+    pub async fn list_with_index(self, client: &Client) -> Result<KvList, anyhow::Error> {
+        let rs = self.recurse(true).send_request(Method::GET, client).await?;
+        let index = rs.index().unwrap_or_default();
+        if rs.status == 404 {
+            return Ok(KvList::NotFound { index });
+        };
+        let records = rs.error_for_status()?.try_into()?;
+        Ok(KvList::Found { index, records })
+    }
+
+    /// Key names under the prefix, without values. With a `separator`,
+    /// keys below it are collapsed into their common prefix, e.g. `a/` for
+    /// `a/b` and `a/c` with separator `/`.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(path = %self.path)))]
+    pub async fn keys_list(self, client: &Client) -> Result<Vec<String>, anyhow::Error> {
+        let rs = self.keys(true).send_request(Method::GET, client).await?;
+        if rs.status == 404 {
+            return Ok(vec![]);
+        };
+        rs.decode()
+    }
+
+    /// Lists the prefix in batches of at most `batch` records: key names are
+    /// read first, then values are fetched per batch through a read-only
+    /// transaction, bounding memory to one batch of values. Batches are
+    /// capped at the 64 operations Consul allows per transaction.
+    pub fn list_paginated(
+        self,
+        client: &Client,
+        batch: usize,
+    ) -> impl Stream<Item = Result<Record, anyhow::Error>> {
+        let client = client.clone();
+        let batch = batch.clamp(1, TXN_MAX_OPS);
+        let state = (self, None::<VecDeque<String>>, VecDeque::new());
+        futures_util::stream::try_unfold(state, move |(kv, mut keys, mut buffer)| {
+            let client = client.clone();
+            async move {
+                loop {
+                    if let Some(record) = buffer.pop_front() {
+                        return Ok(Some((record, (kv, keys, buffer))));
+                    }
+                    let pending = match &mut keys {
+                        Some(pending) => pending,
+                        None => keys.insert(kv.clone().keys_list(&client).await?.into()),
+                    };
+                    if pending.is_empty() {
+                        return Ok(None);
+                    }
+                    let chunk: Vec<String> = pending.drain(..batch.min(pending.len())).collect();
+                    buffer = kv.get_batch(&client, chunk).await?.into();
+                }
+            }
+        })
+    }
+
+    /// Values of `keys` in one transaction; keys deleted in the meantime
+    /// fail the transaction and are then skipped one by one.
+    async fn get_batch(
+        &self,
+        client: &Client,
+        keys: Vec<String>,
+    ) -> Result<Vec<Record>, anyhow::Error> {
+        let ops: Vec<_> = keys
+            .iter()
+            .map(|key| serde_json::json!({"KV": {"Verb": "get", "Key": key}}))
+            .collect();
+        let query = TxnQuery {
+            dc: self.query.dc.as_deref(),
+        };
+        let rs = client
+            .send(Method::PUT, "v1/txn", &query, Some(ops.into()), None)
+            .await?;
+        if rs.status == 409 {
+            let mut records = vec![];
+            for key in keys {
+                let kv = Kv::new(key).apply_if(self.query.dc.clone(), |kv, dc| kv.dc(dc));
+                records.extend(kv.get(client).await?);
+            }
+            return Ok(records);
+        }
+        let rs: TxnResponse = rs.decode()?;
+        Ok(rs.results.into_iter().map(|r| r.kv).collect())
     }
 }
 
+/// Consul's limit of operations per transaction.
+pub(crate) const TXN_MAX_OPS: usize = 64;
+
+/// Requests in flight for [`Kv::get_many`].
+pub const GET_MANY_CONCURRENCY: usize = 16;
+
+/// Writes [`Kv::update`] and [`Kv::merge_patch`] attempt before giving up.
+pub const CAS_ATTEMPTS: u32 = 10;
+
+/// Backoff after the first CAS conflict, doubled per conflict.
+const CAS_BACKOFF: Duration = Duration::from_millis(5);
+const CAS_MAX_BACKOFF: Duration = Duration::from_millis(200);
+
+#[derive(Serialize)]
+struct TxnQuery<'a> {
+    dc: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TxnResponse {
+    #[serde(default)]
+    results: Vec<TxnResult>,
+}
+
+#[derive(Deserialize)]
+struct TxnResult {
+    #[serde(rename = "KV")]
+    kv: Record,
+}
+
 impl Client {
     pub fn new<S>(url: S) -> Result<Self, anyhow::Error>
     where
         S: Into<String>,
     {
-        let client = reqwest::Client::new();
-        let url = url.into().parse()?;
-        Ok(Self { url, client })
+        Self::builder(url).build()
     }
-}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct Record {
-    create_index: usize,
-    flags: usize,
-    key: String,
-    lock_index: usize,
-    modify_index: usize,
-    value: String,
-}
+    pub fn builder<S>(url: S) -> ClientBuilder
+    where
+        S: Into<String>,
+    {
+        ClientBuilder::new(url)
+    }
 
-impl Record {
-    pub fn create_index(&self) -> usize {
-        self.create_index
+    /// Checks that the agent is reachable and the cluster has a leader,
+    /// e.g. at startup. Returns the leader address.
+    pub async fn ping(&self) -> Result<String, anyhow::Error> {
+        status::Status::leader(self)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Consul cluster has no leader"))
     }
 
-    pub fn flags(&self) -> usize {
-        self.flags
+    /// Version of the agent, read from `v1/agent/self` on first use and
+    /// cached for the lifetime of the client and its clones. Failed reads
+    /// aren't cached, the next call tries again.
+    pub async fn agent_version(&self) -> Result<agent::AgentVersion, anyhow::Error> {
+        self.agent_version
+            .get_or_try_init(|| async {
+                agent::Agent::self_info(self)
+                    .await?
+                    .config()
+                    .version()
+                    .parse()
+            })
+            .await
+            .copied()
     }
 
-    pub fn key(&self) -> &str {
-        &self.key
+    /// Fails with [`error::UnsupportedByAgent`] when the agent is too old for
+    /// `capability`. If the version can't be read, e.g. without
+    /// `agent:read`, the request is let through.
+    pub(crate) async fn require(&self, capability: agent::Capability) -> Result<(), anyhow::Error> {
+        match self.agent_version().await {
+            Ok(version) if version < capability.since => {
+                Err(
+                    error::UnsupportedByAgent::new(capability.name, capability.since, version)
+                        .into(),
+                )
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::debug!("Failed to read agent version: {e:#}");
+                Ok(())
+            }
+        }
     }
 
-    pub fn lock_index(&self) -> usize {
-        self.lock_index
+    /// Health of each configured agent address, primary first.
+    pub fn endpoint_status(&self) -> Vec<failover::EndpointStatus> {
+        self.endpoints.status()
     }
 
-    pub fn modify_index(&self) -> usize {
-        self.modify_index
+    pub fn transfer_stats(&self) -> TransferStats {
+        TransferStats {
+            responses: self.stats.responses.load(Ordering::Relaxed),
+            compressed_responses: self.stats.compressed_responses.load(Ordering::Relaxed),
+            wire_bytes: self.stats.wire_bytes.load(Ordering::Relaxed),
+            body_bytes: self.stats.body_bytes.load(Ordering::Relaxed),
+        }
     }
 
-    pub fn value_as_slice(&self) -> Result<Vec<u8>, anyhow::Error> {
-        let value = BASE64_STANDARD.decode(&self.value)?;
-        Ok(value)
+    pub(crate) async fn send<Q>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &Q,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+    ) -> Result<Response, anyhow::Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.send_with_timeout(method, path, query, payload, body, None)
+            .await
+    }
+
+    pub(crate) async fn send_with_timeout<Q>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &Q,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+        timeout: Option<Duration>,
+    ) -> Result<Response, anyhow::Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let url = self.request_url(path, query)?;
+        if method == Method::GET
+            && payload.is_none()
+            && body.is_none()
+            && let Some(cache) = &self.cache
+            && !url.query_pairs().any(|(k, _)| k == "index" || k == "hash")
+        {
+            return cache.get(self, url, timeout).await;
+        }
+        if method == Method::GET {
+            return self.execute(method, url, payload, body, timeout).await;
+        }
+        let rs = self
+            .execute(method, url.clone(), payload, body, timeout)
+            .await;
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&url);
+        }
+        rs
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+        timeout: Option<Duration>,
+    ) -> Result<Response, anyhow::Error> {
+        let request_id = self.request_id.as_ref().map(|id| (id.generate.0)());
+        #[cfg(feature = "audit")]
+        let audited = self
+            .audit
+            .as_ref()
+            .filter(|_| audit::Audit::is_write(&method))
+            .map(|audit| (audit, method.clone(), url.clone()));
+        #[cfg(feature = "instrument")]
+        let span = request_span(&method, &url, request_id.as_deref());
+        let rs = self.dispatch(method, url, payload, body, timeout, request_id.as_deref());
+        #[cfg(feature = "instrument")]
+        let rs = traced(span, rs, |rs| rs.status);
+        let rs = rs.await;
+        #[cfg(feature = "audit")]
+        if let Some((audit, method, url)) = audited {
+            audit.record(method, &url, request_id.as_deref(), &rs);
+        }
+        rs
+    }
+
+    /// GET whose body is left unread for streaming. Compression isn't
+    /// negotiated, so the body arrives as stored. `None` on 404.
+    pub(crate) async fn execute_unbuffered(
+        &self,
+        url: url::Url,
+        timeout: Option<Duration>,
+    ) -> Result<Option<reqwest::Response>, anyhow::Error> {
+        let request_id = self.request_id.as_ref().map(|id| (id.generate.0)());
+        #[cfg(feature = "instrument")]
+        let span = request_span(&Method::GET, &url, request_id.as_deref());
+        let rs = self.send_unbuffered(Method::GET, url, timeout, request_id.as_deref(), |rq| rq);
+        #[cfg(feature = "instrument")]
+        let rs = traced(span, rs, |rs| rs.status().as_u16());
+        let rs = rs.await?;
+        if rs.status() == 404 {
+            return Ok(None);
+        }
+        if !rs.status().is_success() {
+            let status = rs.status().as_u16();
+            let path = rs.url().path().to_string();
+            let body = rs.bytes().await?;
+            let error = error::status_error(&Method::GET, &path, status, &body);
+            return Err(error::with_request_id(error, request_id.as_deref()));
+        }
+        Ok(Some(rs))
+    }
+
+    async fn dispatch(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        payload: Option<serde_json::Value>,
+        body: Option<Vec<u8>>,
+        timeout: Option<Duration>,
+        request_id: Option<&str>,
+    ) -> Result<Response, anyhow::Error> {
+        let raw = is_raw(&url);
+        let rs = self
+            .send_unbuffered(method.clone(), url, timeout, request_id, |rq| {
+                rq.apply_if(self.accept_encoding(), |k, v| k.header(ACCEPT_ENCODING, v))
+                    .apply_if(payload.as_ref(), |k, v| k.json(v))
+                    .apply_if(body.clone(), |k, v| k.body(v))
+            })
+            .await?;
+        let status = rs.status().as_u16();
+        let index = consul_index(rs.headers());
+        let url = rs.url().clone();
+        let body = self.read_body(rs).await?;
+        let mut rs = Response::new(method, &url, status, index, body, raw);
+        rs.request_id = request_id.map(str::to_owned);
+        Ok(rs)
+    }
+
+    /// Sends with the timeout, request ID and metrics applied, up to the
+    /// response headers.
+    async fn send_unbuffered<F>(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        timeout: Option<Duration>,
+        request_id: Option<&str>,
+        build: F,
+    ) -> Result<reqwest::Response, anyhow::Error>
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let header = self
+            .request_id
+            .as_ref()
+            .zip(request_id)
+            .map(|(id, value)| (&id.header, value));
+        let timeout = timeout
+            .or(self.timeout)
+            .map(|timeout| timeout + blocking_wait(&url));
+        #[cfg(feature = "metrics")]
+        let timer = telemetry::Timer::start(&method, &url, blocking_wait(&url) > Duration::ZERO);
+        let rs = self
+            .request(method, url, |rq| {
+                build(rq)
+                    .apply_if(timeout, |k, v| k.timeout(v))
+                    .apply_if(header, |k, (name, value)| k.header(name, value))
+            })
+            .await;
+        #[cfg(feature = "metrics")]
+        timer.finish(rs.as_ref().ok().map(|rs| rs.status().as_u16()));
+        rs.map_err(|e| error::with_request_id(e, request_id))
+    }
+
+    /// Request URL with the default datacenter applied unless the query
+    /// names one.
+    fn request_url<Q>(&self, path: &str, query: &Q) -> Result<url::Url, anyhow::Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let mut url = request_url(self.endpoints.primary(), path, query)?;
+        if let Some(dc) = &self.datacenter
+            && !url.query_pairs().any(|(k, _)| k == "dc")
+        {
+            url.query_pairs_mut().append_pair("dc", dc);
+        }
+        Ok(url)
+    }
+
+    /// Sends within the rate limit, retrying 429 responses per the retry
+    /// policy; the last one becomes a [`error::RateLimited`] error.
+    async fn request<F>(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        build: F,
+    ) -> Result<reqwest::Response, anyhow::Error>
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            self.limiter.acquire(url.path()).await;
+            let rs = self.authorized(method.clone(), url.clone(), &build).await?;
+            if rs.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+                || reports_state_with_429(url.path())
+            {
+                return Ok(rs);
+            }
+            let retry_after = retry::retry_after(rs.headers());
+            let backoff = self
+                .retry
+                .as_ref()
+                .and_then(|p| p.backoff(attempt, retry_after));
+            let Some(delay) = backoff else {
+                let path = rs.url().path().to_string();
+                let body = rs.bytes().await?;
+                return Err(error::rate_limited(&method, &path, retry_after, &body));
+            };
+            tracing::debug!("Rate limited, retrying in {delay:?}");
+            #[cfg(feature = "metrics")]
+            telemetry::retry("rate_limited");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends with the provider's token, retrying once with a refreshed
+    /// token when Consul answers 403.
+    async fn authorized<F>(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        build: F,
+    ) -> Result<reqwest::Response, anyhow::Error>
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let Some(tokens) = &self.tokens else {
+            return self.failover(method, url, build).await;
+        };
+        let token = tokens.0.token().await?;
+        let rs = self
+            .failover(method.clone(), url.clone(), |rq| {
+                build(rq).header(token::TOKEN_HEADER, &token)
+            })
+            .await?;
+        if rs.status() != reqwest::StatusCode::FORBIDDEN {
+            return Ok(rs);
+        }
+        tracing::debug!("Request denied, refreshing ACL token");
+        #[cfg(feature = "metrics")]
+        telemetry::retry("token");
+        let token = tokens.0.refresh().await?;
+        self.failover(method, url, |rq| {
+            build(rq).header(token::TOKEN_HEADER, &token)
+        })
+        .await
+    }
+
+    /// Sends to the configured addresses in failover order, moving on to
+    /// the next one only when the connection itself fails.
+    async fn failover<F>(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        build: F,
+    ) -> Result<reqwest::Response, anyhow::Error>
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let mut last = None;
+        for index in self.endpoints.order() {
+            let url = self.endpoints.rebase(&url, index)?;
+            #[cfg(feature = "metrics")]
+            if last.is_some() {
+                telemetry::retry("failover");
+            }
+            let mut rq = build(self.client.request(method.clone(), url)).build()?;
+            self.middleware.before(&mut rq).await?;
+            match self.client.execute(rq).await {
+                Ok(rs) => {
+                    self.endpoints.mark_up(index);
+                    self.middleware.after(&rs).await?;
+                    return Ok(rs);
+                }
+                Err(e) if e.is_connect() => {
+                    tracing::debug!("Agent unreachable, failing over: {e}");
+                    self.endpoints.mark_down(index);
+                    last = Some(e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(last.map_or_else(
+            || anyhow::anyhow!("No agent address configured"),
+            Into::into,
+        ))
+    }
+
+    fn accept_encoding(&self) -> Option<&'static str> {
+        self.compression.then_some("gzip, deflate, br")
+    }
+
+    async fn read_body(&self, rs: reqwest::Response) -> Result<Bytes, anyhow::Error> {
+        let encoding = rs
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let wire = rs.bytes().await?;
+        let wire_len = wire.len() as u64;
+        let body = match encoding.as_deref() {
+            Some(encoding) if self.compression => {
+                self.stats
+                    .compressed_responses
+                    .fetch_add(1, Ordering::Relaxed);
+                decode(encoding, wire)?
+            }
+            _ => wire,
+        };
+        self.stats.responses.fetch_add(1, Ordering::Relaxed);
+        self.stats.wire_bytes.fetch_add(wire_len, Ordering::Relaxed);
+        self.stats
+            .body_bytes
+            .fetch_add(body.len() as u64, Ordering::Relaxed);
+        Ok(body)
+    }
+}
+
+impl ClientBuilder {
+    pub fn new<S>(url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            urls: vec![url.into()],
+            strategy: failover::Strategy::default(),
+            cooldown: Duration::from_secs(30),
+            compression: false,
+            cache_idle: None,
+            timeout: None,
+            datacenter: None,
+            tokens: None,
+            middleware: middleware::Stack::default(),
+            limiter: ratelimit::RateLimiter::default(),
+            retry: None,
+            request_id_header: "X-Request-ID".to_string(),
+            request_id: None,
+            #[cfg(feature = "audit")]
+            audit: None,
+            #[cfg(feature = "audit")]
+            audit_accessor: None,
+            http: Http::default(),
+        }
+    }
+
+    /// Adds a fallback agent address, tried when the ones before it are
+    /// unreachable.
+    pub fn address<S>(mut self, url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.urls.push(url.into());
+        self
+    }
+
+    pub fn addresses<I, S>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.urls.extend(urls.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn failover(mut self, strategy: failover::Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// How long an address that failed to connect is skipped.
+    pub fn failover_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Static ACL token sent with every request.
+    pub fn token<S>(self, token: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.token_provider(token.into())
+    }
+
+    /// Fetches the ACL token per request, refreshing it once on 403.
+    pub fn token_provider<P>(mut self, provider: P) -> Self
+    where
+        P: token::TokenProvider + 'static,
+    {
+        self.tokens = Some(token::Tokens(Arc::new(provider)));
+        self
+    }
+
+    /// Reports every write to `sink`; see [`audit`].
+    #[cfg(feature = "audit")]
+    pub fn audit<S>(mut self, sink: S) -> Self
+    where
+        S: audit::AuditSink + 'static,
+    {
+        self.audit = Some(audit::Audit {
+            sink: Arc::new(sink),
+            accessor: None,
+        });
+        self
+    }
+
+    /// Accessor ID of the client's token, recorded in audit entries.
+    #[cfg(feature = "audit")]
+    pub fn audit_accessor<S>(mut self, accessor: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.audit_accessor = Some(accessor.into());
+        self
+    }
+
+    pub fn middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: middleware::Middleware + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Limits requests to `per_second` on average, allowing bursts of up
+    /// to `burst`. Requests over the limit wait instead of failing.
+    /// [`build`](Self::build) fails if either is 0.
+    pub fn rate_limit(mut self, per_second: u32, burst: u32) -> Self {
+        self.limiter.set_default(per_second, burst);
+        self
+    }
+
+    /// Separate limit for requests whose path starts with `prefix`, e.g.
+    /// `v1/kv/`, replacing the default limit for them. As with
+    /// [`rate_limit`](Self::rate_limit), both values must be at least 1.
+    pub fn rate_limit_path<S>(mut self, prefix: S, per_second: u32, burst: u32) -> Self
+    where
+        S: Into<String>,
+    {
+        self.limiter.set_override(prefix.into(), per_second, burst);
+        self
+    }
+
+    /// Sends a correlation ID from `generate` with every request, in the
+    /// `X-Request-ID` header unless changed with `request_id_header`. It is
+    /// generated once per request, so retries share it, and is recorded in
+    /// `instrument` spans and in error messages.
+    pub fn request_id<F>(mut self, generate: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.request_id = Some(GenerateId(Arc::new(generate)));
+        self
+    }
+
+    pub fn request_id_header<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request_id_header = name.into();
+        self
+    }
+
+    /// Waits and retries when Consul answers 429, honouring `Retry-After`.
+    pub fn retry_policy(mut self, policy: retry::RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Datacenter for every request whose builder doesn't set one.
+    pub fn datacenter<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.datacenter = Some(dc.into());
+        self
+    }
+
+    /// Memoizes GET responses and keeps them fresh with background blocking
+    /// queries; entries not read for `idle` are dropped.
+    pub fn response_cache(mut self, idle: Duration) -> Self {
+        self.cache_idle = Some(idle);
+        self
+    }
+
+    /// Requests gzip, deflate and br encoded responses and decodes them
+    /// transparently; see [`Client::transfer_stats`] for the savings.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, value: bool) -> Self {
+        self.compression = value;
+        self
+    }
+
+    /// Default timeout for every request. Blocking queries get their `wait`
+    /// added on top so they are not cut off mid-poll.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Adjusts the underlying reqwest client before it is built, e.g. to
+    /// set a proxy, bind a local address or tune the connection pool.
+    /// Calls compose in order.
+    pub fn configure_http<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + 'static,
+    {
+        self.http.configure.push(Box::new(configure));
+        self
+    }
+
+    /// How long idle pooled connections are kept. Shorter timeouts avoid
+    /// reusing connections an agent restart has silently dropped.
+    pub fn pool_idle_timeout(self, timeout: Duration) -> Self {
+        self.configure_http(move |http| http.pool_idle_timeout(timeout))
+    }
+
+    pub fn pool_max_idle_per_host(self, max: usize) -> Self {
+        self.configure_http(move |http| http.pool_max_idle_per_host(max))
+    }
+
+    /// Enables TCP keepalive probes on agent connections.
+    pub fn tcp_keepalive(self, interval: Duration) -> Self {
+        self.configure_http(move |http| http.tcp_keepalive(interval))
+    }
+
+    /// Speaks HTTP/2 without negotiating it first. Consul agents only serve
+    /// HTTP/2 over TLS, so this is for proxies in front of them that accept
+    /// cleartext HTTP/2.
+    #[cfg(feature = "http2")]
+    pub fn http2_prior_knowledge(self) -> Self {
+        self.configure_http(|http| http.http2_prior_knowledge())
+    }
+
+    /// Sends HTTP/2 pings at `interval` to keep connections open and notice
+    /// dead ones.
+    #[cfg(feature = "http2")]
+    pub fn http2_keep_alive_interval(self, interval: Duration) -> Self {
+        self.configure_http(move |http| http.http2_keep_alive_interval(interval))
+    }
+
+    /// How long to wait for a ping acknowledgement before closing the
+    /// connection.
+    #[cfg(feature = "http2")]
+    pub fn http2_keep_alive_timeout(self, timeout: Duration) -> Self {
+        self.configure_http(move |http| http.http2_keep_alive_timeout(timeout))
+    }
+
+    /// Keeps pinging connections with no requests in flight.
+    #[cfg(feature = "http2")]
+    pub fn http2_keep_alive_while_idle(self, enabled: bool) -> Self {
+        self.configure_http(move |http| http.http2_keep_alive_while_idle(enabled))
+    }
+
+    /// Sends requests with a pre-built reqwest client. [`ClientBuilder::build`]
+    /// fails if `configure_http` or the pool, keepalive and HTTP/2 options
+    /// are set too, as they can't apply to it. Not supported with
+    /// `unix://` addresses.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http.client = Some(client);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, anyhow::Error> {
+        let mut urls = self
+            .urls
+            .iter()
+            .map(|url| url.parse())
+            .collect::<Result<Vec<url::Url>, _>>()?;
+        let socket = unix_socket(&urls)?;
+        self.limiter.validate()?;
+        if socket.is_some() {
+            urls = vec![SOCKET_BASE.parse()?];
+        }
+        let request_id = match self.request_id {
+            Some(generate) => Some(RequestId {
+                header: self.request_id_header.parse()?,
+                generate,
+            }),
+            None => None,
+        };
+        let client = match self.http.client {
+            Some(client) => {
+                if socket.is_some() {
+                    anyhow::bail!("A custom HTTP client can't connect to a unix socket");
+                }
+                if !self.http.configure.is_empty() {
+                    anyhow::bail!(
+                        "configure_http and the pool, keepalive and HTTP/2 options don't apply to a custom HTTP client"
+                    );
+                }
+                client
+            }
+            None => {
+                let mut client = reqwest::Client::builder();
+                // Bodies are decoded here, after counting their wire size, so
+                // reqwest's own decoders must leave them alone should another
+                // crate enable them.
+                if self.compression {
+                    client = client.no_gzip().no_brotli().no_deflate();
+                }
+                #[cfg(unix)]
+                if let Some(path) = socket {
+                    client = client.unix_socket(path);
+                }
+                for configure in self.http.configure {
+                    client = configure(client);
+                }
+                client.build()?
+            }
+        };
+        let endpoints = failover::Endpoints::new(urls, self.strategy, self.cooldown);
+        Ok(Client {
+            endpoints: Arc::new(endpoints),
+            client,
+            compression: self.compression,
+            stats: Arc::default(),
+            cache: self
+                .cache_idle
+                .map(|idle| Arc::new(cache::ResponseCache::new(idle))),
+            timeout: self.timeout,
+            datacenter: self.datacenter,
+            tokens: self.tokens,
+            middleware: self.middleware,
+            limiter: Arc::new(self.limiter),
+            retry: self.retry,
+            request_id,
+            agent_version: Arc::default(),
+            #[cfg(feature = "audit")]
+            audit: self.audit.map(|audit| audit::Audit {
+                accessor: self.audit_accessor,
+                ..audit
+            }),
+        })
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decode(encoding: &str, body: Bytes) -> Result<Bytes, anyhow::Error> {
+    use std::io::Read;
+
+    let mut out = Vec::new();
+    match encoding {
+        "gzip" => {
+            flate2::read::GzDecoder::new(&body[..]).read_to_end(&mut out)?;
+        }
+        "deflate" => {
+            flate2::read::ZlibDecoder::new(&body[..]).read_to_end(&mut out)?;
+        }
+        "br" => {
+            brotli::Decompressor::new(&body[..], 4096).read_to_end(&mut out)?;
+        }
+        _ => return Ok(body),
+    }
+    Ok(out.into())
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode(_encoding: &str, body: Bytes) -> Result<Bytes, anyhow::Error> {
+    Ok(body)
+}
+
+/// KV entry. Its `Debug` output redacts the value, which often holds
+/// secrets; see [`Record::debug_with_values`].
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Record {
+    create_index: usize,
+    flags: u64,
+    key: String,
+    lock_index: usize,
+    modify_index: usize,
+    /// `null` for keys written without a value.
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    session: Option<String>,
+}
+
+impl std::fmt::Debug for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        RecordDebug {
+            record: self,
+            values: false,
+        }
+        .fmt(f)
+    }
+}
+
+/// `Debug` view of a record, see [`Record::debug_with_values`].
+struct RecordDebug<'a> {
+    record: &'a Record,
+    values: bool,
+}
+
+impl std::fmt::Debug for RecordDebug<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let record = self.record;
+        let value = match &record.value {
+            Some(value) if self.values => value.as_str(),
+            Some(_) => "<redacted>",
+            None => "<none>",
+        };
+        f.debug_struct("Record")
+            .field("create_index", &record.create_index)
+            .field("flags", &record.flags)
+            .field("key", &record.key)
+            .field("lock_index", &record.lock_index)
+            .field("modify_index", &record.modify_index)
+            .field("value", &format_args!("{value}"))
+            .field("session", &record.session)
+            .finish()
+    }
+}
+
+impl Record {
+    /// `Debug` output including the value, e.g. while debugging locally:
+    /// `println!("{:?}", record.debug_with_values())`.
+    pub fn debug_with_values(&self) -> impl std::fmt::Debug + '_ {
+        RecordDebug {
+            record: self,
+            values: true,
+        }
+    }
+
+    pub fn create_index(&self) -> usize {
+        self.create_index
+    }
+
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn lock_index(&self) -> usize {
+        self.lock_index
+    }
+
+    pub fn modify_index(&self) -> usize {
+        self.modify_index
+    }
+
+    /// Session holding the lock on this key, if any.
+    pub fn session(&self) -> Option<&str> {
+        self.session.as_deref()
+    }
+
+    pub fn value_as_slice(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let Some(value) = &self.value else {
+            return Ok(vec![]);
+        };
+        let value = BASE64_STANDARD.decode(value)?;
+        Ok(value)
+    }
+
+    /// Base64 value exactly as returned by Consul, for passing it on
+    /// without decoding.
+    pub fn expose_value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Value wrapped so it is zeroized on drop and never printed.
+    #[cfg(feature = "secrecy")]
+    pub fn value_as_secret(&self) -> Result<secrecy::SecretBox<[u8]>, anyhow::Error> {
+        let value = self.value_as_slice()?.into_boxed_slice();
+        Ok(secrecy::SecretBox::new(value))
+    }
+
+    /// Value as UTF-8 text, for keys holding plain strings.
+    pub fn value_as_string(&self) -> Result<String, anyhow::Error> {
+        Ok(String::from_utf8(self.value_as_slice()?)?)
     }
 
     pub fn value(&self) -> Result<serde_json::Value, anyhow::Error> {
@@ -242,22 +1768,340 @@ impl Record {
         let value: serde_json::Value = serde_json::from_slice(&value.to_vec())?;
         Ok(value)
     }
+
+    /// Format of the value, as recorded in the flags.
+    pub fn format(&self) -> format::Format {
+        format::Format::from_flags(self.flags)
+    }
+
+    /// Decodes the value in the format recorded in the flags.
+    pub fn value_as<T>(&self) -> Result<T, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.format().deserialize(&self.value_as_slice()?)
+    }
+
+    /// Replaces the value locally; see [`Record::save`].
+    pub fn set_value<B>(&mut self, value: B)
+    where
+        B: AsRef<[u8]>,
+    {
+        self.value = Some(BASE64_STANDARD.encode(value));
+    }
+
+    pub fn set_json<T>(&mut self, value: &T) -> Result<(), anyhow::Error>
+    where
+        T: Serialize,
+    {
+        self.set_value(serde_json::to_vec(value)?);
+        Ok(())
+    }
+
+    pub fn set_flags(&mut self, flags: u64) {
+        self.flags = flags;
+    }
+
+    /// Writes the value and flags back with CAS on the record's
+    /// `ModifyIndex`. Returns `false` if the key changed since it was read;
+    /// on success the record is re-read so it can be saved again.
+    pub async fn save(&mut self, client: &Client) -> Result<bool, anyhow::Error> {
+        let saved = Kv::new(&self.key)
+            .cas(self.modify_index as u64)
+            .flags(self.flags)
+            .body(self.value_as_slice()?)
+            .put_with_result(client)
+            .await?;
+        if !saved {
+            return Ok(false);
+        }
+        if let Some(record) = Kv::new(&self.key).get(client).await? {
+            *self = record;
+        }
+        Ok(true)
+    }
+}
+
+/// Result of [`Kv::list_with_index`].
+#[derive(Debug, Clone)]
+pub enum KvList {
+    Found {
+        index: u64,
+        records: Vec<Record>,
+    },
+    /// No key exists under the prefix.
+    NotFound {
+        index: u64,
+    },
+}
+
+impl KvList {
+    /// `X-Consul-Index` of the read, to pass to [`Kv::index`].
+    pub fn index(&self) -> u64 {
+        match self {
+            KvList::Found { index, .. } | KvList::NotFound { index } => *index,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, KvList::NotFound { .. })
+    }
+
+    pub fn records(&self) -> &[Record] {
+        match self {
+            KvList::Found { records, .. } => records,
+            KvList::NotFound { .. } => &[],
+        }
+    }
+
+    pub fn into_records(self) -> Vec<Record> {
+        match self {
+            KvList::Found { records, .. } => records,
+            KvList::NotFound { .. } => vec![],
+        }
+    }
 }
 
 impl TryFrom<Response> for Vec<Record> {
     type Error = anyhow::Error;
     fn try_from(value: Response) -> Result<Self, Self::Error> {
-        let Some(json) = value.json else {
-            anyhow::bail!("No JSON in response");
+        value.parse()
+    }
+}
+
+/// Placeholder base for requests sent over a unix socket.
+const SOCKET_BASE: &str = "http://localhost/";
+
+/// Endpoints answering 429 with their regular body to report state, not
+/// rate limiting: agent service health for instances in warning state and
+/// autopilot health for an unhealthy cluster.
+const STATE_429_PATHS: &[&str] = &["/v1/agent/health/service/", "/v1/operator/autopilot/health"];
+
+fn reports_state_with_429(path: &str) -> bool {
+    STATE_429_PATHS.iter().any(|p| path.contains(p))
+}
+
+/// Socket path of a `unix://` agent address, which can't be combined with
+/// other addresses.
+fn unix_socket(urls: &[url::Url]) -> Result<Option<String>, anyhow::Error> {
+    if !urls.iter().any(|url| url.scheme() == "unix") {
+        return Ok(None);
+    }
+    if !cfg!(unix) {
+        anyhow::bail!("Unix sockets are not supported on this platform");
+    }
+    let [url] = urls else {
+        anyhow::bail!("A unix socket address can't be combined with other addresses");
+    };
+    Ok(Some(url.path().to_string()))
+}
+
+fn apply_merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(Default::default());
+    }
+    let Some(target) = target.as_object_mut() else {
+        return;
+    };
+    for (name, value) in patch {
+        if value.is_null() {
+            target.remove(name);
+        } else {
+            let entry = target
+                .entry(name.clone())
+                .or_insert(serde_json::Value::Null);
+            apply_merge_patch(entry, value);
+        }
+    }
+}
+
+fn request_url<Q>(base: &url::Url, path: &str, query: &Q) -> Result<url::Url, anyhow::Error>
+where
+    Q: Serialize + ?Sized,
+{
+    let mut url = base.join(path)?;
+    let query = serde_urlencoded::to_string(query)?;
+    if !query.is_empty() {
+        url.set_query(Some(&query));
+    }
+    Ok(url)
+}
+
+fn consul_index(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .get("X-Consul-Index")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+#[cfg(feature = "instrument")]
+fn request_span(method: &Method, url: &url::Url, request_id: Option<&str>) -> tracing::Span {
+    let dc = url
+        .query_pairs()
+        .find(|(k, _)| k == "dc")
+        .map(|(_, v)| v.into_owned());
+    tracing::debug_span!(
+        "consul.request",
+        method = %method,
+        path = url.path(),
+        dc = dc.as_deref(),
+        request_id,
+        status = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+/// Runs `request` in `span`, recording its status and duration.
+#[cfg(feature = "instrument")]
+async fn traced<T, F>(
+    span: tracing::Span,
+    request: F,
+    status: fn(&T) -> u16,
+) -> Result<T, anyhow::Error>
+where
+    F: std::future::Future<Output = Result<T, anyhow::Error>>,
+{
+    use tracing::Instrument;
+
+    let started = std::time::Instant::now();
+    let rs = request.instrument(span.clone()).await;
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+    match &rs {
+        Ok(rs) => span.record("status", status(rs)),
+        Err(e) => span.record("status", tracing::field::display(e)),
+    };
+    rs
+}
+
+fn is_raw(url: &url::Url) -> bool {
+    url.query_pairs().any(|(k, _)| k == "raw")
+}
+
+/// Time a blocking query may legitimately hold the connection: its `wait`
+/// (5 minutes by default) plus the up to `wait / 16` jitter Consul adds.
+fn blocking_wait(url: &url::Url) -> Duration {
+    let mut blocking = false;
+    let mut wait = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "index" | "hash" => blocking = true,
+            "wait" => wait = parse_duration(&value),
+            _ => {}
+        }
+    }
+    if !blocking {
+        return Duration::ZERO;
+    }
+    let wait = wait.unwrap_or(Duration::from_secs(300));
+    wait + wait / 16
+}
+
+/// Random number in `[0, 1)` from the std hasher's per-instance keys.
+pub(crate) fn random_unit() -> f64 {
+    let bits = std::hash::RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Parses Go-style durations as rendered by Consul, e.g. `10s` or `1m30s`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0f64;
+    let mut rest = value.trim();
+    if rest == "0" {
+        return Some(Duration::ZERO);
+    }
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let scale = match unit {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
         };
-        Ok(serde_json::from_value(json)?)
+        total += number * scale;
+        rest = tail;
     }
+    Duration::try_from_secs_f64(total).ok()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "compression")]
+    #[test]
+    fn it_decodes_encodings() {
+        use std::io::Write;
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        gzip.write_all(b"[1,2,3]").unwrap();
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        zlib.write_all(b"[1,2,3]").unwrap();
+        let mut br = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+        br.write_all(b"[1,2,3]").unwrap();
+        for (encoding, body) in [
+            ("gzip", gzip.finish().unwrap()),
+            ("deflate", zlib.finish().unwrap()),
+            ("br", br.into_inner()),
+            ("identity", b"[1,2,3]".to_vec()),
+        ] {
+            assert_eq!(&decode(encoding, body.into()).unwrap()[..], b"[1,2,3]");
+        }
+    }
+
+    #[cfg(all(feature = "compression", feature = "mock"))]
+    #[tokio::test]
+    async fn it_decodes_compressed_bodies() {
+        use std::io::Write;
+
+        use crate::mock::{MockAgent, Reply};
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        gzip.write_all(br#""10.0.0.1:8300""#).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let agent = MockAgent::start().await.unwrap();
+        let compressed = Reply::new(200, gzip.clone()).header("Content-Encoding", "gzip");
+        agent.expect("GET", "v1/status/leader", compressed);
+        agent.expect("GET", "v1/status/leader", Reply::new(200, gzip.clone()));
+        let client = Client::builder(agent.url())
+            .compression(true)
+            .build()
+            .unwrap();
+        let leader = status::Status::leader(&client).await.unwrap();
+        assert_eq!(leader.as_deref(), Some("10.0.0.1:8300"));
+        let stats = client.transfer_stats();
+        assert_eq!(stats.compressed_responses(), 1);
+        assert_eq!(stats.wire_bytes(), gzip.len() as u64);
+        assert_eq!(stats.body_bytes(), 15);
+        let client = Client::new(agent.url()).unwrap();
+        let rs = client
+            .send(Method::GET, "v1/status/leader", &(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(&rs.body[..], &gzip[..]);
+        let requests = agent.requests();
+        let accepted = requests[0].header("Accept-Encoding").unwrap();
+        assert!(
+            accepted.contains("gzip") && accepted.contains("br"),
+            "{accepted}"
+        );
+        assert_eq!(requests[1].header("Accept-Encoding"), None);
+    }
+
     #[tokio::test]
     async fn it_works() {
         let client = Client::new("http://localhost:8500").unwrap();
@@ -278,4 +2122,744 @@ mod tests {
         let list = Kv::new("path/").list(&client).await.unwrap();
         assert_eq!(list.len(), 1);
     }
+
+    #[test]
+    fn it_sends_flags_without_values() {
+        let base: url::Url = "http://localhost:8500".parse().unwrap();
+        let kv = Kv::new("key").recurse(true).raw(false).keys(true);
+        let url = request_url(&base, &kv.path, &kv.query).unwrap();
+        assert_eq!(url.query(), Some("recurse=&keys="));
+        assert!(!is_raw(&url));
+    }
+
+    #[tokio::test]
+    async fn it_writes_flags() {
+        let base: url::Url = "http://localhost:8500".parse().unwrap();
+        let kv = Kv::new("key").flags(u64::MAX);
+        let url = request_url(&base, &kv.path, &kv.query).unwrap();
+        assert_eq!(url.query(), Some("flags=18446744073709551615"));
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("tagged-flags/key")
+            .flags(u64::MAX)
+            .put_string(&client, "v")
+            .await
+            .unwrap();
+        let record = Kv::new("tagged-flags/key").get(&client).await.unwrap();
+        assert_eq!(record.unwrap().flags(), u64::MAX);
+        Kv::new("tagged-flags/key").delete(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_honors_false_flags() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for key in ["flags/a", "flags/ab"] {
+            Kv::new(key).put_string(&client, key).await.unwrap();
+        }
+        let record = Kv::new("flags/a")
+            .recurse(false)
+            .raw(false)
+            .get(&client)
+            .await
+            .unwrap();
+        assert_eq!(record.unwrap().key(), "flags/a");
+        let records = Kv::new("flags/a")
+            .recurse(true)
+            .send_request(Method::GET, &client);
+        let records: Vec<Record> = records.await.unwrap().try_into().unwrap();
+        assert_eq!(records.len(), 2);
+        Kv::new("flags/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_streams() {
+        use futures_util::TryStreamExt;
+
+        let client = Client::new("http://localhost:8500").unwrap();
+        let body = vec![7u8; 1 << 20];
+        Kv::new("stream/key0")
+            .body(body.clone())
+            .put(&client)
+            .await
+            .unwrap();
+        let stream = Kv::new("stream/key0").get_stream(&client).await.unwrap();
+        let chunks: Vec<Bytes> = stream.unwrap().try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), body);
+        assert!(
+            Kv::new("stream/missing")
+                .get_stream(&client)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        Kv::new("stream/key0").delete(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_stores_plain_values() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("plain/text")
+            .put_string(&client, "{not json")
+            .await
+            .unwrap();
+        let record = Kv::new("plain/text").get(&client).await.unwrap().unwrap();
+        assert_eq!(record.value_as_string().unwrap(), "{not json");
+        let binary = vec![0u8, 159, 146, 150];
+        Kv::new("plain/binary")
+            .put_bytes(&client, binary.clone())
+            .await
+            .unwrap();
+        let value = Kv::new("plain/binary").get_bytes(&client).await.unwrap();
+        assert_eq!(value.unwrap(), binary);
+        let rs = Kv::new("plain/text")
+            .raw(true)
+            .send_request(Method::GET, &client)
+            .await
+            .unwrap();
+        assert!(rs.clone().json().is_none());
+        assert_eq!(rs.bytes(), "{not json");
+        assert!(
+            Kv::new("plain/missing")
+                .get_bytes(&client)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        Kv::new("plain/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_lists_in_batches() {
+        use futures_util::TryStreamExt;
+
+        let client = Client::new("http://localhost:8500").unwrap();
+        for i in 0..10 {
+            Kv::new(format!("paginated/key{i}"))
+                .put_string(&client, i.to_string())
+                .await
+                .unwrap();
+        }
+        let records: Vec<Record> = Kv::new("paginated/")
+            .list_paginated(&client, 3)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 10);
+        assert_eq!(records[0].key(), "paginated/key0");
+        assert_eq!(records[9].value_as_string().unwrap(), "9");
+        Kv::new("paginated/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_lists_keys() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for key in ["keys/a/one", "keys/a/two", "keys/b"] {
+            Kv::new(key).put_string(&client, "x").await.unwrap();
+        }
+        let keys = Kv::new("keys/").keys_list(&client).await.unwrap();
+        assert_eq!(keys, ["keys/a/one", "keys/a/two", "keys/b"]);
+        let keys = Kv::new("keys/")
+            .separator("/")
+            .keys_list(&client)
+            .await
+            .unwrap();
+        assert_eq!(keys, ["keys/a/", "keys/b"]);
+        let keys = Kv::new("keys/missing/").keys_list(&client).await.unwrap();
+        assert!(keys.is_empty());
+        Kv::new("keys/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_puts_ephemeral_keys() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let session = session::Session::new()
+            .behavior(session::Behavior::Delete)
+            .create(&client)
+            .await
+            .unwrap();
+        let acquired = Kv::new("ephemeral/marker")
+            .body(b"up".to_vec())
+            .put_ephemeral(&client, &session)
+            .await
+            .unwrap();
+        assert!(acquired);
+        let record = Kv::new("ephemeral/marker").get(&client).await.unwrap();
+        assert_eq!(record.unwrap().session(), Some(session.as_str()));
+        session::Session::destroy(&client, &session).await.unwrap();
+        let record = Kv::new("ephemeral/marker").get(&client).await.unwrap();
+        assert!(record.is_none());
+    }
+
+    #[tokio::test]
+    async fn it_deletes_trees() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for key in ["tree/a", "tree/b/c"] {
+            Kv::new(key).put_string(&client, "x").await.unwrap();
+        }
+        let keys = Kv::new("tree/").delete_tree_dry_run(&client).await.unwrap();
+        assert_eq!(keys, ["tree/a", "tree/b/c"]);
+        assert!(Kv::new("tree/a").get(&client).await.unwrap().is_some());
+        let keys = Kv::new("tree/").delete_tree(&client).await.unwrap();
+        assert_eq!(keys, ["tree/a", "tree/b/c"]);
+        assert!(Kv::new("tree/").list(&client).await.unwrap().is_empty());
+        assert!(Kv::new("/").delete_tree(&client).await.is_err());
+    }
+
+    #[test]
+    fn it_parses_empty_values() {
+        let json = serde_json::json!([{
+            "CreateIndex": 5,
+            "Flags": 0,
+            "Key": "empty",
+            "LockIndex": 0,
+            "ModifyIndex": 5,
+            "Value": null,
+        }]);
+        let records: Vec<Record> = serde_json::from_value(json).unwrap();
+        assert!(records[0].value_as_slice().unwrap().is_empty());
+        assert_eq!(records[0].value_as_string().unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn it_stores_empty_values() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("empty/key").put(&client).await.unwrap();
+        let records = Kv::new("empty/").list(&client).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].value_as_slice().unwrap().is_empty());
+        Kv::new("empty/key").delete(&client).await.unwrap();
+    }
+
+    #[test]
+    fn it_parses_bool_results() {
+        let response = |status: u16, body: &str| Response {
+            method: Method::PUT,
+            path: "/v1/kv/key".to_string(),
+            status,
+            index: None,
+            raw: false,
+            body: Bytes::copy_from_slice(body.as_bytes()),
+            request_id: None,
+        };
+        assert!(response(200, "true").into_bool().unwrap());
+        assert!(!response(200, "false").into_bool().unwrap());
+        let err = response(200, "{}").into_bool().unwrap_err();
+        assert!(err.to_string().contains("PUT /v1/kv/key"), "{err}");
+        assert!(response(500, "oops").into_bool().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_locks() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let rs = client
+            .send(Method::PUT, "v1/session/create", &(), None, None)
+            .await
+            .unwrap();
+        let session = rs.json().unwrap()["ID"].as_str().unwrap().to_string();
+        let rs = Kv::new("lock/key0").acquire(&session).put(&client).await;
+        assert_eq!(rs.unwrap().as_bool(), Some(true));
+        let released = Kv::new("lock/key0")
+            .release(&session)
+            .put_with_result(&client)
+            .await;
+        assert!(released.unwrap());
+        let deleted = Kv::new("lock/key0")
+            .cas(1)
+            .delete_with_result(&client)
+            .await;
+        assert!(!deleted.unwrap());
+        let path = format!("v1/session/destroy/{session}");
+        client
+            .send(Method::PUT, &path, &(), None, None)
+            .await
+            .unwrap();
+        Kv::new("lock/key0").delete(&client).await.unwrap();
+    }
+
+    #[test]
+    fn it_redacts_values() {
+        let record: Record = serde_json::from_value(serde_json::json!({
+            "CreateIndex": 1,
+            "Flags": 0,
+            "Key": "secrets/db",
+            "LockIndex": 0,
+            "ModifyIndex": 1,
+            "Value": "aHVudGVyMg==",
+        }))
+        .unwrap();
+        let debug = format!("{record:?}");
+        assert!(debug.contains("value: <redacted>"));
+        assert!(!debug.contains("aHVudGVyMg=="));
+        assert_eq!(record.expose_value(), Some("aHVudGVyMg=="));
+        let debug = format!("{:?}", record.debug_with_values());
+        assert!(debug.contains("value: aHVudGVyMg=="));
+        let url: url::Url = "http://localhost:8500/v1/kv/secrets/db".parse().unwrap();
+        let body = Bytes::from_static(br#"[{"Key":"secrets/db","Value":"aHVudGVyMg=="}]"#);
+        let rs = Response::new(Method::GET, &url, 200, Some(1), body, false);
+        let debug = format!("{rs:?}");
+        assert!(!debug.contains("aHVudGVyMg=="));
+        assert!(debug.contains("body: <45 bytes>"), "{debug}");
+    }
+
+    #[test]
+    fn it_applies_merge_patches() {
+        use serde_json::json;
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (json!(["a", "b"]), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+        ];
+        for (mut target, patch, expected) in cases {
+            apply_merge_patch(&mut target, &patch);
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_gets_many() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for key in ["many/a", "many/b"] {
+            Kv::new(key).put_string(&client, key).await.unwrap();
+        }
+        let records = Kv::new("many/")
+            .get_many(&client, &["a", "b", "missing"])
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records["a"].as_ref().unwrap().key(), "many/a");
+        assert_eq!(records["b"].as_ref().unwrap().key(), "many/b");
+        assert!(records["missing"].is_none());
+        Kv::new("many/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_merges_patches() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("patch/doc")
+            .payload(serde_json::json!({"replicas": 1, "image": "web:1"}))
+            .put(&client)
+            .await
+            .unwrap();
+        let doc = Kv::new("patch/doc")
+            .merge_patch(&client, &serde_json::json!({"replicas": 3, "image": null}))
+            .await
+            .unwrap();
+        assert_eq!(doc, serde_json::json!({"replicas": 3}));
+        let record = Kv::new("patch/doc").get(&client).await.unwrap().unwrap();
+        assert_eq!(record.value().unwrap(), doc);
+        Kv::new("patch/doc").delete(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_updates_atomically() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("update/counter").delete(&client).await.unwrap();
+        let initial: u64 = Kv::new("update/counter")
+            .get_or_default(&client)
+            .await
+            .unwrap();
+        assert_eq!(initial, 0);
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    Kv::new("update/counter")
+                        .update(&client, |n: Option<u64>| n.unwrap_or_default() + 1)
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let counter: u64 = Kv::new("update/counter")
+            .get_or_default(&client)
+            .await
+            .unwrap();
+        assert_eq!(counter, 8);
+        Kv::new("update/counter").delete(&client).await.unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_gives_up_on_cas_conflicts() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        for _ in 0..CAS_ATTEMPTS {
+            agent.expect("GET", "v1/kv/counter", Reply::kv("counter", b"1"));
+            agent.expect("PUT", "v1/kv/counter", Reply::json(false.into()));
+        }
+        let client = Client::new(agent.url()).unwrap();
+        let err = Kv::new("counter")
+            .update(&client, |n: Option<u64>| n.unwrap_or(0) + 1)
+            .await
+            .unwrap_err();
+        let conflict = err.downcast_ref::<error::CasConflict>().unwrap();
+        assert_eq!(conflict.attempts(), CAS_ATTEMPTS);
+        agent.verify().unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_saves_records() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("save/key0")
+            .payload(serde_json::json!({"replicas": 1}))
+            .put(&client)
+            .await
+            .unwrap();
+        let mut record = Kv::new("save/key0").get(&client).await.unwrap().unwrap();
+        let mut stale = record.clone();
+        record
+            .set_json(&serde_json::json!({"replicas": 2}))
+            .unwrap();
+        assert!(record.save(&client).await.unwrap());
+        record.set_flags(3);
+        assert!(record.save(&client).await.unwrap());
+        stale.set_value("conflict");
+        assert!(!stale.save(&client).await.unwrap());
+        let record = Kv::new("save/key0").get(&client).await.unwrap().unwrap();
+        assert_eq!(record.value().unwrap()["replicas"], 2);
+        assert_eq!(record.flags(), 3);
+        Kv::new("save/key0").delete(&client).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn it_connects_over_unix_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path = std::env::temp_dir().join(format!("consulite-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(buf[..n].starts_with(b"GET /v1/status/leader "));
+            let body = "\"10.0.0.1:8300\"";
+            let rs = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(rs.as_bytes()).await.unwrap();
+        });
+        let client = Client::new(format!("unix://{}", path.display())).unwrap();
+        let leader = status::Status::leader(&client).await.unwrap();
+        assert_eq!(leader.as_deref(), Some("10.0.0.1:8300"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            Client::builder(format!("unix://{}", path.display()))
+                .address("http://localhost:8500")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn it_pings() {
+        let client = Client::builder("http://localhost:8500")
+            .pool_idle_timeout(Duration::from_secs(30))
+            .pool_max_idle_per_host(4)
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert!(!client.ping().await.unwrap().is_empty());
+        let unreachable = Client::new("http://127.0.0.1:1").unwrap();
+        assert!(unreachable.ping().await.is_err());
+    }
+
+    #[cfg(all(feature = "http2", feature = "mock"))]
+    #[tokio::test]
+    async fn it_configures_http2() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect(
+            "GET",
+            "v1/status/leader",
+            Reply::json("10.0.0.1:8300".into()),
+        );
+        // Keepalive settings leave HTTP/1.1 agents reachable.
+        let client = Client::builder(agent.url())
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .http2_keep_alive_timeout(Duration::from_secs(5))
+            .http2_keep_alive_while_idle(true)
+            .build()
+            .unwrap();
+        client.ping().await.unwrap();
+        agent.verify().unwrap();
+        // The mock agent only speaks HTTP/1.1.
+        let client = Client::builder(agent.url())
+            .http2_prior_knowledge()
+            .build()
+            .unwrap();
+        assert!(client.ping().await.is_err());
+    }
+
+    #[test]
+    fn it_rejects_conflicting_http_options() {
+        let http = reqwest::Client::new();
+        assert!(
+            Client::builder("http://localhost:8500")
+                .http_client(http.clone())
+                .tcp_keepalive(Duration::from_secs(60))
+                .build()
+                .is_err()
+        );
+        assert!(
+            Client::builder("http://localhost:8500")
+                .configure_http(|http| http.user_agent("custom/1"))
+                .http_client(http.clone())
+                .build()
+                .is_err()
+        );
+        #[cfg(feature = "http2")]
+        assert!(
+            Client::builder("http://localhost:8500")
+                .http2_keep_alive_interval(Duration::from_secs(30))
+                .http_client(http.clone())
+                .build()
+                .is_err()
+        );
+        assert!(
+            Client::builder("http://localhost:8500")
+                .http_client(http)
+                .build()
+                .is_ok()
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_configures_http_client() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        for _ in 0..2 {
+            agent.expect(
+                "GET",
+                "v1/status/leader",
+                Reply::json("10.0.0.1:8300".into()),
+            );
+        }
+        let client = Client::builder(agent.url())
+            .configure_http(|http| http.user_agent("custom/1"))
+            .build()
+            .unwrap();
+        status::Status::leader(&client).await.unwrap();
+        let http = reqwest::Client::builder()
+            .user_agent("custom/1")
+            .build()
+            .unwrap();
+        let client = Client::builder(agent.url())
+            .http_client(http.clone())
+            .build()
+            .unwrap();
+        status::Status::leader(&client).await.unwrap();
+        agent.verify().unwrap();
+        for rq in agent.requests() {
+            assert_eq!(rq.header("User-Agent"), Some("custom/1"));
+        }
+        assert!(
+            Client::builder("unix:///tmp/consul.sock")
+                .http_client(http)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_values() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("wait_for/flag")
+            .put_string(&client, "off")
+            .await
+            .unwrap();
+        let is_on = |record: &Record| record.value_as_string().is_ok_and(|v| v == "on");
+        assert!(
+            Kv::new("wait_for/flag")
+                .wait_for(&client, is_on, Duration::from_millis(200))
+                .await
+                .is_err()
+        );
+        let flip = {
+            let client = client.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Kv::new("wait_for/flag")
+                    .put_string(&client, "on")
+                    .await
+                    .unwrap();
+            })
+        };
+        let record = Kv::new("wait_for/flag")
+            .wait_for(&client, is_on, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(record.value_as_string().unwrap(), "on");
+        flip.await.unwrap();
+        Kv::new("wait_for/flag").delete(&client).await.unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_tells_missing_prefixes_apart() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/app/", Reply::not_found().index(7));
+        agent.expect("GET", "v1/kv/app/", Reply::kv("app/a", b"1").index(9));
+        agent.expect("GET", "v1/kv/app/", Reply::new(500, "rpc error"));
+        agent.expect("GET", "v1/kv/app/a", Reply::new(500, "rpc error"));
+        let client = Client::new(agent.url()).unwrap();
+        let list = Kv::new("app/").list_with_index(&client).await.unwrap();
+        assert!(list.is_not_found());
+        assert_eq!(list.index(), 7);
+        let list = Kv::new("app/").list_with_index(&client).await.unwrap();
+        assert!(!list.is_not_found());
+        assert_eq!(list.index(), 9);
+        assert_eq!(list.records()[0].key(), "app/a");
+        let err = Kv::new("app/").list(&client).await.unwrap_err();
+        let api = err.downcast_ref::<error::ApiError>().unwrap();
+        assert_eq!(api.status(), 500);
+        assert!(Kv::new("app/a").get(&client).await.is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_sends_request_ids() {
+        use crate::mock::{MockAgent, Reply};
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect(
+            "GET",
+            "v1/status/leader",
+            Reply::json("10.0.0.1:8300".into()),
+        );
+        agent.expect("GET", "v1/status/leader", Reply::new(500, "rpc error"));
+        let counter = AtomicU64::new(0);
+        let client = Client::builder(agent.url())
+            .request_id(move || format!("req-{}", counter.fetch_add(1, Ordering::Relaxed) + 1))
+            .request_id_header("X-Correlation-ID")
+            .build()
+            .unwrap();
+        status::Status::leader(&client).await.unwrap();
+        let err = status::Status::leader(&client).await.unwrap_err();
+        assert!(format!("{err:#}").contains("req-2"), "{err:#}");
+        let ids: Vec<_> = agent
+            .requests()
+            .iter()
+            .map(|rq| {
+                rq.header("X-Correlation-ID")
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(ids, ["req-1", "req-2"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_streams_through_the_request_pipeline() {
+        use crate::mock::{MockAgent, Reply};
+        use futures_util::TryStreamExt;
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/blob", Reply::new(200, "chunk"));
+        agent.expect("GET", "v1/kv/blob", Reply::new(500, "rpc error"));
+        let slow = Reply::new(200, "chunk").delay(Duration::from_secs(5));
+        agent.expect("GET", "v1/kv/blob", slow);
+        let client = Client::builder(agent.url())
+            .request_id(|| "req-1".to_string())
+            .build()
+            .unwrap();
+        let stream = Kv::new("blob").get_stream(&client).await.unwrap().unwrap();
+        let chunks: Vec<Bytes> = stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), b"chunk");
+        let err = match Kv::new("blob").get_stream(&client).await {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        };
+        assert!(format!("{err:#}").contains("req-1"), "{err:#}");
+        let streamed = Kv::new("blob")
+            .timeout(Duration::from_millis(100))
+            .get_stream(&client);
+        assert!(streamed.await.is_err());
+        let requests = agent.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(
+            requests
+                .iter()
+                .all(|rq| rq.header("X-Request-ID") == Some("req-1"))
+        );
+    }
+
+    #[test]
+    fn it_parses_durations() {
+        assert_eq!(parse_duration("1m30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("99999999999999999999999h"), None);
+        assert_eq!(parse_duration("10x"), None);
+    }
+
+    #[test]
+    fn it_extends_blocking_timeouts() {
+        let url: url::Url = "http://localhost:8500/v1/kv/a?index=5&wait=16s"
+            .parse()
+            .unwrap();
+        assert_eq!(blocking_wait(&url), Duration::from_secs(17));
+        let url: url::Url = "http://localhost:8500/v1/kv/a?index=5".parse().unwrap();
+        assert_eq!(blocking_wait(&url), Duration::from_millis(318_750));
+        let url: url::Url = "http://localhost:8500/v1/kv/a?wait=16s".parse().unwrap();
+        assert_eq!(blocking_wait(&url), Duration::ZERO);
+    }
 }
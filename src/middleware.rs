@@ -0,0 +1,121 @@
+//! Hooks around every HTTP request the client sends.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), anyhow::Error>> + Send + 'a>>;
+
+/// Request interceptor, registered with `ClientBuilder::middleware`.
+///
+/// `before` may modify the request, e.g. to add headers or sign it, and
+/// `after` sees the response before its body is read. Hooks run in
+/// registration order for every attempt, including failover and token
+/// refresh retries; an error from a hook fails the request.
+pub trait Middleware: Send + Sync {
+    fn before<'a>(&'a self, _request: &'a mut reqwest::Request) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn after<'a>(&'a self, _response: &'a reqwest::Response) -> HookFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<T> Middleware for Arc<T>
+where
+    T: Middleware + ?Sized,
+{
+    fn before<'a>(&'a self, request: &'a mut reqwest::Request) -> HookFuture<'a> {
+        (**self).before(request)
+    }
+
+    fn after<'a>(&'a self, response: &'a reqwest::Response) -> HookFuture<'a> {
+        (**self).after(response)
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Stack(Vec<Arc<dyn Middleware>>);
+
+impl Stack {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.0.push(middleware);
+    }
+
+    pub(crate) async fn before(&self, request: &mut reqwest::Request) -> Result<(), anyhow::Error> {
+        for middleware in &self.0 {
+            middleware.before(request).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn after(&self, response: &reqwest::Response) -> Result<(), anyhow::Error> {
+        for middleware in &self.0 {
+            middleware.after(response).await?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stack({} middleware)", self.0.len())
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::Client;
+    use crate::status::Status;
+
+    #[derive(Default)]
+    struct Audit {
+        seen: Mutex<Vec<(String, u16)>>,
+    }
+
+    impl Middleware for Audit {
+        fn before<'a>(&'a self, request: &'a mut reqwest::Request) -> HookFuture<'a> {
+            Box::pin(async move {
+                let value = reqwest::header::HeaderValue::from_static("audit");
+                request.headers_mut().insert("X-Request-Source", value);
+                Ok(())
+            })
+        }
+
+        fn after<'a>(&'a self, response: &'a reqwest::Response) -> HookFuture<'a> {
+            Box::pin(async move {
+                let path = response.url().path().to_string();
+                let status = response.status().as_u16();
+                self.seen.lock().unwrap().push((path, status));
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn it_runs_hooks() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect(
+            "GET",
+            "v1/status/leader",
+            Reply::json("10.0.0.1:8300".into()),
+        );
+        let audit = Arc::new(Audit::default());
+        let client = Client::builder(agent.url())
+            .middleware(audit.clone())
+            .build()
+            .unwrap();
+        Status::leader(&client).await.unwrap();
+        let seen = audit.seen.lock().unwrap();
+        assert_eq!(*seen, [("/v1/status/leader".to_string(), 200)]);
+        let requests = agent.requests();
+        assert_eq!(requests[0].header("X-Request-Source"), Some("audit"));
+    }
+}
@@ -0,0 +1,329 @@
+//! In-process agent stand-in for unit tests of code built on the client.
+//!
+//! A [`MockAgent`] listens on a local port and answers requests from
+//! programmed expectations, so the [`Client`] it hands out behaves exactly
+//! as against a real agent, including error handling:
+//!
+//! ```no_run
+//! use consulite::mock::{MockAgent, Reply};
+//! use consulite::prelude::*;
+//!
+//! # async fn run() -> Result<(), anyhow::Error> {
+//! let agent = MockAgent::start().await?;
+//! agent.expect("GET", "v1/kv/config", Reply::kv("config", b"{}"));
+//! let record = Kv::new("config").get(&agent.client()?).await?;
+//! agent.verify()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::prelude::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+use crate::Client;
+
+/// Canned response for an expected request.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    index: Option<u64>,
+    headers: Vec<(String, String)>,
+    delay: Option<Duration>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn new<B>(status: u16, body: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        Self {
+            status,
+            index: None,
+            headers: vec![],
+            delay: None,
+            body: body.into(),
+        }
+    }
+
+    pub fn json(value: serde_json::Value) -> Self {
+        Self::new(200, value.to_string())
+    }
+
+    /// Body of a KV read returning a single key with `value`.
+    pub fn kv(key: &str, value: &[u8]) -> Self {
+        Self::json(serde_json::json!([{
+            "CreateIndex": 1,
+            "Flags": 0,
+            "Key": key,
+            "LockIndex": 0,
+            "ModifyIndex": 1,
+            "Value": BASE64_STANDARD.encode(value),
+        }]))
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404, "")
+    }
+
+    /// Sets the `X-Consul-Index` header.
+    pub fn index(mut self, index: u64) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Adds a response header, e.g. `Retry-After`.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Answers only after `delay`, e.g. to stand in for a hung agent.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+/// Request received by a [`MockAgent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Path without the leading slash, e.g. `v1/kv/key`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Value of the header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    expected: VecDeque<(String, String, Reply)>,
+    received: Vec<Request>,
+    unexpected: Vec<Request>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockAgent {
+    url: String,
+    state: Arc<Mutex<State>>,
+    _shutdown: Arc<watch::Sender<()>>,
+}
+
+impl MockAgent {
+    /// Listens on a free local port until the last clone is dropped.
+    pub async fn start() -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State::default()));
+        let (shutdown, mut closed) = watch::channel(());
+        let shared = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    _ = closed.changed() => return,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(_) => return,
+                    },
+                };
+                let state = shared.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &state).await {
+                        tracing::debug!("Mock agent connection failed: {e:#}");
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            url,
+            state,
+            _shutdown: Arc::new(shutdown),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn client(&self) -> Result<Client, anyhow::Error> {
+        Client::new(&self.url)
+    }
+
+    /// Answers the next `method` request to `path` (query ignored) with
+    /// `reply`. Expectations for the same request are used in order.
+    pub fn expect(&self, method: &str, path: &str, reply: Reply) {
+        let path = path.trim_start_matches('/').to_string();
+        let mut state = self.state.lock().unwrap();
+        state
+            .expected
+            .push_back((method.to_uppercase(), path, reply));
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Fails if an expectation was not met or a request was not expected.
+    pub fn verify(&self) -> Result<(), anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        if let Some(rq) = state.unexpected.first() {
+            anyhow::bail!("Unexpected request {} {}", rq.method, rq.path);
+        }
+        if let Some((method, path, _)) = state.expected.front() {
+            anyhow::bail!("Expected request {method} {path} was not received");
+        }
+        Ok(())
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: &Mutex<State>) -> Result<(), anyhow::Error> {
+    let rq = read_request(&mut stream).await?;
+    let reply = {
+        let mut state = state.lock().unwrap();
+        state.received.push(rq.clone());
+        let position = state
+            .expected
+            .iter()
+            .position(|(method, path, _)| *method == rq.method && *path == rq.path);
+        match position.and_then(|i| state.expected.remove(i)) {
+            Some((_, _, reply)) => reply,
+            None => {
+                state.unexpected.push(rq.clone());
+                Reply::new(501, format!("Unexpected request {} {}", rq.method, rq.path))
+            }
+        }
+    };
+    if let Some(delay) = reply.delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nConnection: close\r\nContent-Length: {}\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    if let Some(index) = reply.index {
+        head.push_str(&format!("X-Consul-Index: {index}\r\n"));
+    }
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&reply.body).await?;
+    Ok(())
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, anyhow::Error> {
+    let mut buf = Vec::new();
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            anyhow::bail!("Connection closed before request head");
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+        .collect();
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buf.split_off(end + 4);
+    while body.len() < length {
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            anyhow::bail!("Connection closed before request body");
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    Ok(Request {
+        method,
+        path: path.trim_start_matches('/').to_string(),
+        query: query.to_string(),
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kv;
+    use crate::error::ApiError;
+    use crate::status::Status;
+
+    #[tokio::test]
+    async fn it_answers_expectations() {
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/config", Reply::kv("config", b"42"));
+        agent.expect("PUT", "/v1/kv/config", Reply::json(true.into()));
+        let client = agent.client().unwrap();
+        let record = Kv::new("config").get(&client).await.unwrap().unwrap();
+        assert_eq!(record.value_as_string().unwrap(), "42");
+        let rs = Kv::new("config").put_string(&client, "43").await.unwrap();
+        assert_eq!(rs.as_bool(), Some(true));
+        agent.verify().unwrap();
+        let requests = agent.requests();
+        assert_eq!(requests[1].method(), "PUT");
+        assert_eq!(requests[1].body(), b"43");
+    }
+
+    #[tokio::test]
+    async fn it_rejects_unexpected_requests() {
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/other", Reply::not_found());
+        let client = agent.client().unwrap();
+        let err = Status::leader(&client).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ApiError>().unwrap().status(), 501);
+        let err = agent.verify().unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected request GET v1/status/leader");
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+
+pub struct Operator;
+
+#[derive(Default, Serialize)]
+struct PeerQuery<'a> {
+    id: Option<&'a str>,
+    address: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TransferResponse {
+    success: bool,
+}
+
+#[derive(Default, Serialize)]
+struct CasQuery {
+    cas: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct KeyringRequest<'a> {
+    key: &'a str,
+}
+
+impl Operator {
+    pub async fn raft_configuration(client: &Client) -> Result<RaftConfiguration, anyhow::Error> {
+        client
+            .send(
+                Method::GET,
+                "v1/operator/raft/configuration",
+                &(),
+                None,
+                None,
+            )
+            .await?
+            .decode()
+    }
+
+    pub async fn remove_peer_by_id(client: &Client, id: &str) -> Result<(), anyhow::Error> {
+        let query = PeerQuery {
+            id: Some(id),
+            ..Default::default()
+        };
+        Self::remove_peer(client, &query).await
+    }
+
+    pub async fn remove_peer_by_address(
+        client: &Client,
+        address: &str,
+    ) -> Result<(), anyhow::Error> {
+        let query = PeerQuery {
+            address: Some(address),
+            ..Default::default()
+        };
+        Self::remove_peer(client, &query).await
+    }
+
+    async fn remove_peer(client: &Client, query: &PeerQuery<'_>) -> Result<(), anyhow::Error> {
+        client
+            .send(Method::DELETE, "v1/operator/raft/peer", query, None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Makes the current leader step down in favour of the server with
+    /// raft `id`, or of any other voter when `None`. Requires
+    /// `operator:write`.
+    pub async fn transfer_leader(client: &Client, id: Option<&str>) -> Result<(), anyhow::Error> {
+        let query = PeerQuery {
+            id,
+            ..Default::default()
+        };
+        let rs: TransferResponse = client
+            .send(
+                Method::POST,
+                "v1/operator/raft/transfer-leader",
+                &query,
+                None,
+                None,
+            )
+            .await?
+            .decode()?;
+        if !rs.success {
+            anyhow::bail!("Leadership transfer failed");
+        }
+        Ok(())
+    }
+
+    pub async fn autopilot_configuration(
+        client: &Client,
+    ) -> Result<AutopilotConfiguration, anyhow::Error> {
+        client
+            .send(
+                Method::GET,
+                "v1/operator/autopilot/configuration",
+                &(),
+                None,
+                None,
+            )
+            .await?
+            .decode()
+    }
+
+    /// Updates the autopilot configuration. With `cas`, the write only
+    /// succeeds if `ModifyIndex` still matches; the result reports whether
+    /// it was applied.
+    pub async fn set_autopilot_configuration(
+        client: &Client,
+        config: &AutopilotConfiguration,
+        cas: Option<u64>,
+    ) -> Result<bool, anyhow::Error> {
+        let rs = client
+            .send(
+                Method::PUT,
+                "v1/operator/autopilot/configuration",
+                &CasQuery { cas },
+                Some(serde_json::to_value(config)?),
+                None,
+            )
+            .await?
+            .error_for_status()?;
+        Ok(rs.as_bool().unwrap_or(true))
+    }
+
+    pub async fn autopilot_health(client: &Client) -> Result<AutopilotHealth, anyhow::Error> {
+        let rs = client
+            .send(Method::GET, "v1/operator/autopilot/health", &(), None, None)
+            .await?;
+        // Consul answers 429 with the full body when the cluster is unhealthy.
+        if rs.status == 429 {
+            return Ok(serde_json::from_slice(&rs.body)?);
+        }
+        rs.decode()
+    }
+
+    /// Gossip keys known to each pool (LAN per datacenter/segment and WAN).
+    pub async fn keyring_list(client: &Client) -> Result<Vec<KeyringResponse>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/operator/keyring", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Distributes a new base64 gossip key to every member.
+    pub async fn keyring_install(client: &Client, key: &str) -> Result<(), anyhow::Error> {
+        Self::keyring(client, Method::POST, key).await
+    }
+
+    /// Makes an installed key the primary key used for encryption.
+    pub async fn keyring_use(client: &Client, key: &str) -> Result<(), anyhow::Error> {
+        Self::keyring(client, Method::PUT, key).await
+    }
+
+    /// Removes a key; Consul refuses to remove the primary key.
+    pub async fn keyring_remove(client: &Client, key: &str) -> Result<(), anyhow::Error> {
+        Self::keyring(client, Method::DELETE, key).await
+    }
+
+    async fn keyring(client: &Client, method: Method, key: &str) -> Result<(), anyhow::Error> {
+        let payload = serde_json::to_value(KeyringRequest { key })?;
+        client
+            .send(method, "v1/operator/keyring", &(), Some(payload), None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RaftConfiguration {
+    servers: Vec<RaftServer>,
+    index: u64,
+}
+
+impl RaftConfiguration {
+    pub fn servers(&self) -> &[RaftServer] {
+        &self.servers
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn leader(&self) -> Option<&RaftServer> {
+        self.servers.iter().find(|server| server.leader)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RaftServer {
+    #[serde(rename = "ID")]
+    id: String,
+    node: String,
+    address: String,
+    leader: bool,
+    #[serde(default)]
+    protocol_version: String,
+    voter: bool,
+}
+
+impl RaftServer {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn leader(&self) -> bool {
+        self.leader
+    }
+
+    pub fn protocol_version(&self) -> &str {
+        &self.protocol_version
+    }
+
+    pub fn voter(&self) -> bool {
+        self.voter
+    }
+}
+
+/// Autopilot settings; read them, adjust them with the setters and write
+/// them back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AutopilotConfiguration {
+    cleanup_dead_servers: bool,
+    last_contact_threshold: String,
+    max_trailing_logs: u64,
+    #[serde(default)]
+    min_quorum: u64,
+    server_stabilization_time: String,
+    #[serde(default)]
+    redundancy_zone_tag: String,
+    #[serde(default)]
+    disable_upgrade_migration: bool,
+    #[serde(default)]
+    upgrade_version_tag: String,
+    #[serde(default, skip_serializing)]
+    create_index: u64,
+    #[serde(default, skip_serializing)]
+    modify_index: u64,
+}
+
+impl AutopilotConfiguration {
+    pub fn cleanup_dead_servers(&self) -> bool {
+        self.cleanup_dead_servers
+    }
+
+    pub fn set_cleanup_dead_servers(&mut self, value: bool) {
+        self.cleanup_dead_servers = value;
+    }
+
+    /// Duration as reported by Consul, e.g. `200ms`.
+    pub fn last_contact_threshold(&self) -> &str {
+        &self.last_contact_threshold
+    }
+
+    pub fn set_last_contact_threshold(&mut self, threshold: Duration) {
+        self.last_contact_threshold = format!("{}ms", threshold.as_millis());
+    }
+
+    pub fn max_trailing_logs(&self) -> u64 {
+        self.max_trailing_logs
+    }
+
+    pub fn set_max_trailing_logs(&mut self, logs: u64) {
+        self.max_trailing_logs = logs;
+    }
+
+    pub fn min_quorum(&self) -> u64 {
+        self.min_quorum
+    }
+
+    pub fn set_min_quorum(&mut self, quorum: u64) {
+        self.min_quorum = quorum;
+    }
+
+    /// Duration as reported by Consul, e.g. `10s`.
+    pub fn server_stabilization_time(&self) -> &str {
+        &self.server_stabilization_time
+    }
+
+    pub fn set_server_stabilization_time(&mut self, time: Duration) {
+        self.server_stabilization_time = format!("{}ms", time.as_millis());
+    }
+
+    pub fn redundancy_zone_tag(&self) -> &str {
+        &self.redundancy_zone_tag
+    }
+
+    pub fn set_redundancy_zone_tag<S>(&mut self, tag: S)
+    where
+        S: Into<String>,
+    {
+        self.redundancy_zone_tag = tag.into();
+    }
+
+    pub fn disable_upgrade_migration(&self) -> bool {
+        self.disable_upgrade_migration
+    }
+
+    pub fn set_disable_upgrade_migration(&mut self, value: bool) {
+        self.disable_upgrade_migration = value;
+    }
+
+    pub fn upgrade_version_tag(&self) -> &str {
+        &self.upgrade_version_tag
+    }
+
+    pub fn set_upgrade_version_tag<S>(&mut self, tag: S)
+    where
+        S: Into<String>,
+    {
+        self.upgrade_version_tag = tag.into();
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AutopilotHealth {
+    healthy: bool,
+    failure_tolerance: u32,
+    servers: Vec<ServerHealth>,
+}
+
+impl AutopilotHealth {
+    pub fn healthy(&self) -> bool {
+        self.healthy
+    }
+
+    pub fn failure_tolerance(&self) -> u32 {
+        self.failure_tolerance
+    }
+
+    pub fn servers(&self) -> &[ServerHealth] {
+        &self.servers
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServerHealth {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+    address: String,
+    serf_status: String,
+    version: String,
+    leader: bool,
+    #[serde(default)]
+    last_contact: String,
+    last_term: u64,
+    last_index: u64,
+    healthy: bool,
+    voter: bool,
+    #[serde(default)]
+    stable_since: String,
+}
+
+impl ServerHealth {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn serf_status(&self) -> &str {
+        &self.serf_status
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn leader(&self) -> bool {
+        self.leader
+    }
+
+    pub fn last_contact(&self) -> &str {
+        &self.last_contact
+    }
+
+    pub fn last_term(&self) -> u64 {
+        self.last_term
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.last_index
+    }
+
+    pub fn healthy(&self) -> bool {
+        self.healthy
+    }
+
+    pub fn voter(&self) -> bool {
+        self.voter
+    }
+
+    pub fn stable_since(&self) -> &str {
+        &self.stable_since
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyringResponse {
+    #[serde(rename = "WAN")]
+    wan: bool,
+    #[serde(default)]
+    datacenter: String,
+    #[serde(default)]
+    segment: String,
+    #[serde(default)]
+    partition: String,
+    #[serde(default)]
+    keys: HashMap<String, usize>,
+    #[serde(default)]
+    primary_keys: HashMap<String, usize>,
+    num_nodes: usize,
+}
+
+impl KeyringResponse {
+    pub fn wan(&self) -> bool {
+        self.wan
+    }
+
+    pub fn datacenter(&self) -> &str {
+        &self.datacenter
+    }
+
+    pub fn segment(&self) -> &str {
+        &self.segment
+    }
+
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
+    /// Installed keys with the number of members holding each.
+    pub fn keys(&self) -> &HashMap<String, usize> {
+        &self.keys
+    }
+
+    /// Primary keys with the number of members using each.
+    pub fn primary_keys(&self) -> &HashMap<String, usize> {
+        &self.primary_keys
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    /// Whether every member of the pool has `key` installed.
+    pub fn is_installed(&self, key: &str) -> bool {
+        self.keys.get(key) == Some(&self.num_nodes)
+    }
+
+    /// Whether every member of the pool uses `key` as its primary key.
+    pub fn is_primary(&self, key: &str) -> bool {
+        self.primary_keys.get(key) == Some(&self.num_nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_reports_unhealthy_autopilot() {
+        use crate::mock::{MockAgent, Reply};
+        use crate::retry::RetryPolicy;
+
+        let health = serde_json::json!({
+            "Healthy": false,
+            "FailureTolerance": 0,
+            "Servers": [{
+                "ID": "e349749b", "Name": "server-1", "Address": "10.0.0.1:8300",
+                "SerfStatus": "alive", "Version": "1.20.0", "Leader": true,
+                "LastTerm": 2, "LastIndex": 10, "Healthy": false, "Voter": true,
+            }],
+        });
+        let agent = MockAgent::start().await.unwrap();
+        for _ in 0..2 {
+            agent.expect(
+                "GET",
+                "v1/operator/autopilot/health",
+                Reply::new(429, health.to_string()),
+            );
+        }
+        let client = Client::new(agent.url()).unwrap();
+        let report = Operator::autopilot_health(&client).await.unwrap();
+        assert!(!report.healthy());
+        assert!(!report.servers()[0].healthy());
+        let client = Client::builder(agent.url())
+            .retry_policy(RetryPolicy::new())
+            .build()
+            .unwrap();
+        let report = Operator::autopilot_health(&client).await.unwrap();
+        assert!(!report.healthy());
+        agent.verify().unwrap();
+        assert_eq!(agent.requests().len(), 2);
+    }
+
+    #[test]
+    fn it_writes_autopilot_durations() {
+        let mut config = AutopilotConfiguration::default();
+        config.set_last_contact_threshold(Duration::from_millis(200));
+        config.set_server_stabilization_time(Duration::from_secs(10));
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["LastContactThreshold"], "200ms");
+        assert_eq!(value["ServerStabilizationTime"], "10000ms");
+        assert!(value.get("ModifyIndex").is_none());
+    }
+
+    #[tokio::test]
+    async fn it_reads_raft_and_autopilot() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let raft = Operator::raft_configuration(&client).await.unwrap();
+        assert!(raft.leader().is_some());
+        let mut config = Operator::autopilot_configuration(&client).await.unwrap();
+        let trailing = config.max_trailing_logs();
+        config.set_max_trailing_logs(trailing + 1);
+        let applied = Operator::set_autopilot_configuration(&client, &config, Some(0))
+            .await
+            .unwrap();
+        assert!(!applied);
+        let applied =
+            Operator::set_autopilot_configuration(&client, &config, Some(config.modify_index()))
+                .await
+                .unwrap();
+        assert!(applied);
+        let mut config = Operator::autopilot_configuration(&client).await.unwrap();
+        assert_eq!(config.max_trailing_logs(), trailing + 1);
+        config.set_max_trailing_logs(trailing);
+        Operator::set_autopilot_configuration(&client, &config, None)
+            .await
+            .unwrap();
+        let health = Operator::autopilot_health(&client).await.unwrap();
+        assert_eq!(health.servers().len(), raft.servers().len());
+    }
+
+    /// Needs gossip encryption, which the compose files and CI enable.
+    #[tokio::test]
+    async fn it_rotates_keys() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let key = "T9L5ypTb8x3qPiB5rQuz+5jnRCf/sC0CeJyuUaF9ihs=";
+        Operator::keyring_install(&client, key).await.unwrap();
+        let pools = Operator::keyring_list(&client).await.unwrap();
+        assert!(pools.iter().all(|pool| pool.is_installed(key)));
+        assert!(pools.iter().all(|pool| !pool.is_primary(key)));
+        Operator::keyring_remove(&client, key).await.unwrap();
+        let pools = Operator::keyring_list(&client).await.unwrap();
+        assert!(pools.iter().all(|pool| !pool.keys().contains_key(key)));
+    }
+}
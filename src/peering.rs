@@ -0,0 +1,278 @@
+//! Cluster peering between independent Consul clusters (1.13+).
+//!
+//! One side generates a token with [`Peering::generate_token`], the other
+//! side hands it to [`Peering::establish`].
+
+use std::collections::HashMap;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+use crate::agent::Capability;
+
+pub struct Peering;
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct TokenRequest<'a> {
+    peer_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<&'a HashMap<String, String>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct EstablishRequest<'a> {
+    peer_name: &'a str,
+    peering_token: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TokenResponse {
+    peering_token: String,
+}
+
+impl Peering {
+    /// Token for the cluster named `peer_name` to establish a peering with
+    /// this one.
+    pub async fn generate_token(
+        client: &Client,
+        peer_name: &str,
+        meta: Option<&HashMap<String, String>>,
+    ) -> Result<String, anyhow::Error> {
+        client.require(Capability::PEERING).await?;
+        let request = TokenRequest { peer_name, meta };
+        let rs: TokenResponse = client
+            .send(
+                Method::POST,
+                "v1/peering/token",
+                &(),
+                Some(serde_json::to_value(request)?),
+                None,
+            )
+            .await?
+            .decode()?;
+        Ok(rs.peering_token)
+    }
+
+    /// Peers with the cluster that generated `peering_token`, naming it
+    /// `peer_name` locally.
+    pub async fn establish(
+        client: &Client,
+        peer_name: &str,
+        peering_token: &str,
+    ) -> Result<(), anyhow::Error> {
+        client.require(Capability::PEERING).await?;
+        let request = EstablishRequest {
+            peer_name,
+            peering_token,
+        };
+        client
+            .send(
+                Method::POST,
+                "v1/peering/establish",
+                &(),
+                Some(serde_json::to_value(request)?),
+                None,
+            )
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn read(client: &Client, name: &str) -> Result<Option<PeeringInfo>, anyhow::Error> {
+        client.require(Capability::PEERING).await?;
+        let path = format!("v1/peering/{name}");
+        let rs = client.send(Method::GET, &path, &(), None, None).await?;
+        if rs.status == 404 {
+            return Ok(None);
+        }
+        rs.decode()
+    }
+
+    pub async fn list(client: &Client) -> Result<Vec<PeeringInfo>, anyhow::Error> {
+        client.require(Capability::PEERING).await?;
+        client
+            .send(Method::GET, "v1/peerings", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    /// Starts deleting the peering; it stays listed as
+    /// [`PeeringState::Deleting`] until the cleanup finishes.
+    pub async fn delete(client: &Client, name: &str) -> Result<(), anyhow::Error> {
+        client.require(Capability::PEERING).await?;
+        let path = format!("v1/peering/{name}");
+        client
+            .send(Method::DELETE, &path, &(), None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PeeringState {
+    /// Token generated, waiting for the peer to establish.
+    Pending,
+    Establishing,
+    Active,
+    Failing,
+    Deleting,
+    Terminated,
+    #[serde(other)]
+    Undefined,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PeeringInfo {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+    state: PeeringState,
+    #[serde(rename = "PeerID", default)]
+    peer_id: String,
+    #[serde(default)]
+    peer_server_name: String,
+    #[serde(default)]
+    peer_server_addresses: Option<Vec<String>>,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+    #[serde(default)]
+    create_index: u64,
+    #[serde(default)]
+    modify_index: u64,
+}
+
+impl PeeringInfo {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> PeeringState {
+        self.state
+    }
+
+    /// ID of this peering in the remote cluster.
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    pub fn peer_server_name(&self) -> &str {
+        &self.peer_server_name
+    }
+
+    pub fn peer_server_addresses(&self) -> &[String] {
+        self.peer_server_addresses.as_deref().unwrap_or_default()
+    }
+
+    pub fn meta(&self) -> Option<&HashMap<String, String>> {
+        self.meta.as_ref()
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_manages_peerings() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let meta = HashMap::from([("env".to_string(), "test".to_string())]);
+        let token = Peering::generate_token(&client, "it-peer", Some(&meta))
+            .await
+            .unwrap();
+        assert!(!token.is_empty());
+        let peering = Peering::read(&client, "it-peer").await.unwrap().unwrap();
+        assert_eq!(peering.state(), PeeringState::Pending);
+        assert_eq!(peering.meta().unwrap()["env"], "test");
+        let peerings = Peering::list(&client).await.unwrap();
+        assert!(peerings.iter().any(|p| p.name() == "it-peer"));
+        Peering::delete(&client, "it-peer").await.unwrap();
+        assert!(Peering::read(&client, "missing").await.unwrap().is_none());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_rejects_old_agents() {
+        use crate::error::UnsupportedByAgent;
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let info = serde_json::json!({
+            "Config": {"Datacenter": "dc1", "NodeName": "n1", "Server": true, "Version": "1.12.9"},
+            "Member": {"Name": "n1", "Addr": "127.0.0.1", "Port": 8301, "Tags": {}, "Status": 1},
+        });
+        agent.expect("GET", "v1/agent/self", Reply::json(info));
+        let client = agent.client().unwrap();
+        let err = Peering::list(&client).await.unwrap_err();
+        let unsupported = err.downcast_ref::<UnsupportedByAgent>().unwrap();
+        assert_eq!(unsupported.agent().to_string(), "1.12.9");
+        assert_eq!(
+            err.to_string(),
+            "Cluster peering requires Consul 1.13.0 or newer, the agent runs 1.12.9"
+        );
+        // The version is cached, so no second lookup is expected.
+        assert!(Peering::read(&client, "peer").await.is_err());
+        agent.verify().unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_retries_failed_agent_version_reads() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let info = serde_json::json!({
+            "Config": {"Datacenter": "dc1", "NodeName": "n1", "Server": true, "Version": "1.21.0"},
+            "Member": {"Name": "n1", "Addr": "127.0.0.1", "Port": 8301, "Tags": {}, "Status": 1},
+        });
+        agent.expect("GET", "v1/agent/self", Reply::new(403, "Permission denied"));
+        agent.expect("GET", "v1/peerings", Reply::json(serde_json::json!([])));
+        agent.expect("GET", "v1/agent/self", Reply::json(info));
+        agent.expect("GET", "v1/peerings", Reply::json(serde_json::json!([])));
+        agent.expect("GET", "v1/peerings", Reply::json(serde_json::json!([])));
+        let client = agent.client().unwrap();
+        assert!(Peering::list(&client).await.unwrap().is_empty());
+        assert!(Peering::list(&client).await.unwrap().is_empty());
+        // Only the successful read is cached.
+        assert!(Peering::list(&client).await.unwrap().is_empty());
+        assert_eq!(client.agent_version().await.unwrap().to_string(), "1.21.0");
+        agent.verify().unwrap();
+    }
+
+    #[test]
+    fn it_parses_states() {
+        let peering: PeeringInfo = serde_json::from_value(serde_json::json!({
+            "ID": "462c45e8-018e-f19d-85eb-1fc1bcc2ef12",
+            "Name": "cluster-02",
+            "State": "ACTIVE",
+            "PeerID": "e83a315c-027e-bcb1-7c0c-a46650904a05",
+            "PeerServerName": "server.dc1.peering.consul",
+            "PeerServerAddresses": ["10.0.0.1:8502"],
+            "CreateIndex": 89,
+            "ModifyIndex": 89
+        }))
+        .unwrap();
+        assert_eq!(peering.state(), PeeringState::Active);
+        assert_eq!(peering.peer_server_addresses(), ["10.0.0.1:8502"]);
+        let state: PeeringState = serde_json::from_str("\"NEW_STATE\"").unwrap();
+        assert_eq!(state, PeeringState::Undefined);
+    }
+}
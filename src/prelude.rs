@@ -1 +1,9 @@
-pub use crate::{Client, Kv, Record, Response};
+pub use crate::agent::Agent;
+pub use crate::cache::CachedGetter;
+pub use crate::filter::Filter;
+pub use crate::format::Format;
+pub use crate::health::Health;
+pub use crate::semaphore::Semaphore;
+pub use crate::session::Session;
+pub use crate::watch::{KeyEvent, KeyWatch, Watch, WatchEvent, WatchUpdate};
+pub use crate::{Client, ClientBuilder, Kv, Record, Response};
@@ -0,0 +1,159 @@
+//! KV prefix as a figment configuration layer.
+//!
+//! Keys below the prefix become nested settings, `app/db/port` under the
+//! prefix `app/` being read as `db.port`. Values reading as booleans or
+//! numbers, like `true` or `8080`, are typed; anything else is a string.
+//!
+//! ```no_run
+//! use figment::Figment;
+//! use figment::providers::{Format, Toml};
+//!
+//! # async fn run(client: &consulite::Client) -> Result<(), anyhow::Error> {
+//! let kv = consulite::provider::KvProvider::load(client, "app/").await?;
+//! let figment = Figment::new().merge(Toml::file("App.toml")).merge(kv);
+//! # Ok(())
+//! # }
+//! ```
+
+use figment::value::{Dict, Map, Tag, Value};
+use figment::{Metadata, Profile, Provider};
+
+use crate::{Client, Kv};
+
+/// Snapshot of a KV prefix, read once by [`KvProvider::load`].
+#[derive(Debug, Clone)]
+pub struct KvProvider {
+    prefix: String,
+    profile: Profile,
+    dict: Dict,
+}
+
+impl KvProvider {
+    pub async fn load<S>(client: &Client, prefix: S) -> Result<Self, anyhow::Error>
+    where
+        S: Into<String>,
+    {
+        let prefix = prefix.into();
+        let mut dict = Dict::new();
+        for record in Kv::new(&prefix).list(client).await? {
+            let Some(key) = record.key().strip_prefix(&prefix) else {
+                continue;
+            };
+            let path: Vec<&str> = key.split('/').filter(|s| !s.is_empty()).collect();
+            if path.is_empty() {
+                continue;
+            }
+            insert(&mut dict, &path, parse(&record.value_as_string()?));
+        }
+        Ok(Self {
+            prefix,
+            profile: Profile::Default,
+            dict,
+        })
+    }
+
+    /// Profile the settings are provided for, `default` unless set.
+    pub fn profile<P>(mut self, profile: P) -> Self
+    where
+        P: Into<Profile>,
+    {
+        self.profile = profile.into();
+        self
+    }
+}
+
+fn parse(value: &str) -> Value {
+    let trimmed = value.trim();
+    if let Ok(value) = trimmed.parse::<bool>() {
+        value.into()
+    } else if let Ok(value) = trimmed.parse::<i64>() {
+        value.into()
+    } else if let Ok(value) = trimmed.parse::<u64>() {
+        value.into()
+    } else if let Ok(value) = trimmed.parse::<f64>()
+        && value.is_finite()
+    {
+        value.into()
+    } else {
+        value.into()
+    }
+}
+
+fn insert(dict: &mut Dict, path: &[&str], value: Value) {
+    let (last, parents) = path.split_last().expect("non-empty path");
+    let mut dict = dict;
+    for parent in parents {
+        let entry = dict
+            .entry(parent.to_string())
+            .or_insert_with(|| Value::Dict(Tag::Default, Dict::new()));
+        if !matches!(entry, Value::Dict(..)) {
+            *entry = Value::Dict(Tag::Default, Dict::new());
+        }
+        let Value::Dict(_, nested) = entry else {
+            unreachable!();
+        };
+        dict = nested;
+    }
+    dict.insert(last.to_string(), value);
+}
+
+impl Provider for KvProvider {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(format!("Consul KV `{}`", self.prefix))
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Ok(self.profile.collect(self.dict.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::Figment;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct AppConfig {
+        name: String,
+        db: DbConfig,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct DbConfig {
+        port: u16,
+        tls: bool,
+    }
+
+    #[test]
+    fn it_types_values() {
+        assert_eq!(parse("true").to_bool(), Some(true));
+        assert_eq!(parse(" 8080 ").to_i128(), Some(8080));
+        assert_eq!(parse("0.5").to_f64(), Some(0.5));
+        assert_eq!(parse("inf").as_str(), Some("inf"));
+        assert_eq!(parse("web").as_str(), Some("web"));
+    }
+
+    #[tokio::test]
+    async fn it_provides_settings() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        for (key, value) in [
+            ("figment/name", "web"),
+            ("figment/db/port", "5432"),
+            ("figment/db/tls", "true"),
+        ] {
+            Kv::new(key).put_string(&client, value).await.unwrap();
+        }
+        let kv = KvProvider::load(&client, "figment/").await.unwrap();
+        let config: AppConfig = Figment::from(kv).extract().unwrap();
+        assert_eq!(config.name, "web");
+        assert_eq!(config.db.port, 5432);
+        assert!(config.db.tls);
+        Kv::new("figment/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use reqwest::Method;
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{Client, Kv};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Evict the oldest queued write to make room.
+    #[default]
+    DropOldest,
+    /// Discard the incoming write.
+    DropNewest,
+    /// Fail the incoming write with an error.
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Written,
+    Queued,
+    Dropped,
+}
+
+enum Failure {
+    Retry(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+#[derive(Default)]
+struct Pending {
+    writes: VecDeque<(Method, Kv)>,
+    /// Whether the front write is being replayed, so the drop policy leaves
+    /// it alone.
+    in_flight: bool,
+}
+
+struct Inner {
+    client: Client,
+    capacity: usize,
+    policy: DropPolicy,
+    /// Only locked briefly, never across a request.
+    pending: Mutex<Pending>,
+    /// Held by whoever is sending, so direct writes go out one at a time and
+    /// never overtake queued ones.
+    sending: AsyncMutex<()>,
+    dropped: AtomicU64,
+}
+
+/// Write-behind queue for non-critical KV writes.
+///
+/// Writes go straight to Consul while it is reachable. On connection errors
+/// or 5xx responses they are buffered and replayed in order by a background
+/// task that stops once every handle is dropped.
+#[derive(Clone)]
+pub struct WriteQueue {
+    inner: Arc<Inner>,
+}
+
+pub struct WriteQueueBuilder {
+    client: Client,
+    capacity: usize,
+    policy: DropPolicy,
+    retry: Duration,
+}
+
+impl WriteQueue {
+    pub fn builder(client: &Client) -> WriteQueueBuilder {
+        WriteQueueBuilder {
+            client: client.clone(),
+            capacity: 1024,
+            policy: DropPolicy::default(),
+            retry: Duration::from_secs(5),
+        }
+    }
+
+    pub async fn put(&self, kv: Kv) -> Result<Delivery, anyhow::Error> {
+        self.submit(Method::PUT, kv).await
+    }
+
+    pub async fn delete(&self, kv: Kv) -> Result<Delivery, anyhow::Error> {
+        self.submit(Method::DELETE, kv).await
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending().writes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending().writes.is_empty()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Replays queued writes until the queue is empty or Consul fails again.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all))]
+    pub async fn flush(&self) -> Result<(), anyhow::Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let _sending = self.inner.sending.lock().await;
+        loop {
+            // The write stays queued while it is sent, and is only removed
+            // afterwards.
+            let (method, kv) = {
+                let mut pending = self.pending();
+                let Some((method, kv)) = pending.writes.front().cloned() else {
+                    return Ok(());
+                };
+                pending.in_flight = true;
+                (method, kv)
+            };
+            let result = send(&self.inner.client, method, kv.clone()).await;
+            let mut pending = self.pending();
+            pending.in_flight = false;
+            match result {
+                Ok(()) => {}
+                Err(Failure::Retry(e)) => return Err(e),
+                Err(Failure::Fatal(e)) => {
+                    tracing::warn!("Dropping queued write to {}: {e:#}", kv.path);
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            pending.writes.pop_front();
+        }
+    }
+
+    async fn submit(&self, method: Method, kv: Kv) -> Result<Delivery, anyhow::Error> {
+        // Written directly only while nothing is queued or being replayed;
+        // otherwise the write joins the queue without waiting. Direct writes
+        // take turns, so one that fails is queued before a later one is sent.
+        if self.is_empty() {
+            let _sending = self.inner.sending.lock().await;
+            if self.is_empty() {
+                match send(&self.inner.client, method.clone(), kv.clone()).await {
+                    Ok(()) => return Ok(Delivery::Written),
+                    Err(Failure::Fatal(e)) => return Err(e),
+                    Err(Failure::Retry(e)) => {
+                        tracing::debug!("Queueing write to {}: {e:#}", kv.path);
+                    }
+                }
+                // Queued before the next direct write gets its turn.
+                return self.enqueue(method, kv);
+            }
+        }
+        self.enqueue(method, kv)
+    }
+
+    fn enqueue(&self, method: Method, kv: Kv) -> Result<Delivery, anyhow::Error> {
+        let mut pending = self.pending();
+        if pending.writes.len() >= self.inner.capacity {
+            match self.inner.policy {
+                DropPolicy::DropOldest => {
+                    // A write being replayed has already gone out, so the
+                    // next one is evicted instead.
+                    let oldest = usize::from(pending.in_flight);
+                    if pending.writes.remove(oldest).is_some() {
+                        self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                DropPolicy::DropNewest => {
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Delivery::Dropped);
+                }
+                DropPolicy::Reject => anyhow::bail!("Write queue is full"),
+            }
+        }
+        pending.writes.push_back((method, kv));
+        Ok(Delivery::Queued)
+    }
+
+    fn pending(&self) -> MutexGuard<'_, Pending> {
+        self.inner.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl WriteQueueBuilder {
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Interval between replay attempts while writes are queued.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Builds the queue and spawns its replay task on the current runtime.
+    pub fn spawn(self) -> WriteQueue {
+        let inner = Arc::new(Inner {
+            client: self.client,
+            capacity: self.capacity,
+            policy: self.policy,
+            pending: Mutex::new(Pending::default()),
+            sending: AsyncMutex::new(()),
+            dropped: AtomicU64::new(0),
+        });
+        tokio::spawn(replay(Arc::downgrade(&inner), self.retry));
+        WriteQueue { inner }
+    }
+}
+
+async fn replay(inner: Weak<Inner>, retry: Duration) {
+    loop {
+        tokio::time::sleep(retry).await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let queue = WriteQueue { inner };
+        if let Err(e) = queue.flush().await {
+            tracing::debug!("Write queue replay deferred: {e:#}");
+        }
+    }
+}
+
+async fn send(client: &Client, method: Method, kv: Kv) -> Result<(), Failure> {
+    let rs = match kv.send_request(method, client).await {
+        Ok(rs) => rs,
+        Err(e) => return Err(Failure::Retry(e)),
+    };
+    match rs.status {
+        200..=299 => Ok(()),
+        500..=599 => Err(Failure::Retry(rs.into_error())),
+        _ => Err(Failure::Fatal(rs.into_error())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_buffers_while_unreachable() {
+        let client = Client::new("http://127.0.0.1:1").unwrap();
+        let queue = WriteQueue::builder(&client)
+            .capacity(2)
+            .drop_policy(DropPolicy::DropOldest)
+            .spawn();
+        for key in ["queue/key0", "queue/key1", "queue/key2"] {
+            let delivery = queue.put(Kv::new(key).body(b"1".to_vec())).await.unwrap();
+            assert_eq!(delivery, Delivery::Queued);
+        }
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped(), 1);
+        assert!(queue.flush().await.is_err());
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_replays_in_order() {
+        use crate::mock::{MockAgent, Reply};
+
+        // Unexpected requests are answered with 501, queueing the writes.
+        let agent = MockAgent::start().await.unwrap();
+        let client = Client::new(agent.url()).unwrap();
+        let queue = WriteQueue::builder(&client)
+            .retry(Duration::from_secs(3600))
+            .spawn();
+        for key in ["a", "b", "c"] {
+            let delivery = queue.put(Kv::new(key).body(b"1".to_vec())).await.unwrap();
+            assert_eq!(delivery, Delivery::Queued);
+        }
+        for key in ["a", "b", "c"] {
+            agent.expect("PUT", &format!("v1/kv/{key}"), Reply::json(true.into()));
+        }
+        queue.flush().await.unwrap();
+        assert!(queue.is_empty());
+        let paths: Vec<_> = agent
+            .requests()
+            .iter()
+            .map(|r| r.path().to_string())
+            .collect();
+        assert_eq!(paths, ["v1/kv/a", "v1/kv/a", "v1/kv/b", "v1/kv/c"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_queues_without_waiting_for_replays() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let client = Client::new(agent.url()).unwrap();
+        let queue = WriteQueue::builder(&client)
+            .retry(Duration::from_secs(3600))
+            .spawn();
+        agent.expect("PUT", "v1/kv/a", Reply::new(503, "No cluster leader"));
+        let delivery = queue.put(Kv::new("a").body(b"1".to_vec())).await.unwrap();
+        assert_eq!(delivery, Delivery::Queued);
+        let hung = Reply::json(true.into()).delay(Duration::from_secs(1));
+        agent.expect("PUT", "v1/kv/a", hung);
+        agent.expect("PUT", "v1/kv/b", Reply::json(true.into()));
+        let replay = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.flush().await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let quick = Duration::from_millis(300);
+        let put = queue.put(Kv::new("b").body(b"1".to_vec()));
+        let delivery = tokio::time::timeout(quick, put).await.unwrap().unwrap();
+        assert_eq!(delivery, Delivery::Queued);
+        assert_eq!(queue.len(), 2);
+        replay.await.unwrap().unwrap();
+        assert!(queue.is_empty());
+        agent.verify().unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_queues_behind_failing_direct_writes() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let client = Client::new(agent.url()).unwrap();
+        let queue = WriteQueue::builder(&client)
+            .retry(Duration::from_secs(3600))
+            .spawn();
+        let slow = Reply::new(503, "No cluster leader").delay(Duration::from_millis(300));
+        agent.expect("PUT", "v1/kv/a", slow);
+        let first = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.put(Kv::new("a").body(b"1".to_vec())).await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let delivery = queue.put(Kv::new("a").body(b"2".to_vec())).await.unwrap();
+        assert_eq!(delivery, Delivery::Queued);
+        assert_eq!(first.await.unwrap().unwrap(), Delivery::Queued);
+        assert_eq!(queue.len(), 2);
+        agent.expect("PUT", "v1/kv/a", Reply::json(true.into()));
+        agent.expect("PUT", "v1/kv/a", Reply::json(true.into()));
+        queue.flush().await.unwrap();
+        let bodies: Vec<_> = agent.requests().iter().map(|r| r.body().to_vec()).collect();
+        assert_eq!(bodies, [b"1", b"1", b"2"]);
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_keeps_the_replayed_write() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let client = Client::new(agent.url()).unwrap();
+        let queue = WriteQueue::builder(&client)
+            .capacity(1)
+            .retry(Duration::from_secs(3600))
+            .spawn();
+        agent.expect("PUT", "v1/kv/a", Reply::new(503, "No cluster leader"));
+        let delivery = queue.put(Kv::new("a").body(b"1".to_vec())).await.unwrap();
+        assert_eq!(delivery, Delivery::Queued);
+        let hung = Reply::json(true.into()).delay(Duration::from_millis(300));
+        agent.expect("PUT", "v1/kv/a", hung);
+        agent.expect("PUT", "v1/kv/b", Reply::json(true.into()));
+        let replay = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.flush().await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let delivery = queue.put(Kv::new("b").body(b"1".to_vec())).await.unwrap();
+        assert_eq!(delivery, Delivery::Queued);
+        assert_eq!(queue.dropped(), 0);
+        replay.await.unwrap().unwrap();
+        assert!(queue.is_empty());
+        agent.verify().unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+//! Client-side rate limiting of requests to the agent.
+//!
+//! Each limit is a token bucket refilled at `per_second` and holding at
+//! most `burst` tokens. Requests whose path starts with an override prefix
+//! draw from that bucket instead of the default one; the longest matching
+//! prefix wins. Requests over the limit wait for their turn.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+#[derive(Debug)]
+struct Bucket {
+    per_second: f64,
+    burst: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl Bucket {
+    fn new(per_second: u32, burst: u32) -> Self {
+        let burst = f64::from(burst);
+        Self {
+            per_second: f64::from(per_second),
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Takes a token, returning how long to wait until it is available.
+    /// Tokens are reserved up front so waiting requests are served in order.
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (tokens, updated) = &mut *state;
+        let now = Instant::now();
+        let refill = now.duration_since(*updated).as_secs_f64() * self.per_second;
+        *tokens = (*tokens + refill).min(self.burst) - 1.0;
+        *updated = now;
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens / self.per_second)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    default: Option<Bucket>,
+    overrides: Vec<(String, Bucket)>,
+}
+
+impl RateLimiter {
+    pub(crate) fn set_default(&mut self, per_second: u32, burst: u32) {
+        self.default = Some(Bucket::new(per_second, burst));
+    }
+
+    pub(crate) fn set_override(&mut self, prefix: String, per_second: u32, burst: u32) {
+        let prefix = prefix.trim_start_matches('/').to_string();
+        self.overrides.retain(|(p, _)| *p != prefix);
+        self.overrides
+            .push((prefix, Bucket::new(per_second, burst)));
+        self.overrides
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    }
+
+    /// Rejects limits that would never let a request through.
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        let buckets = self
+            .default
+            .iter()
+            .map(|bucket| ("default", bucket))
+            .chain(self.overrides.iter().map(|(p, b)| (p.as_str(), b)));
+        for (name, bucket) in buckets {
+            anyhow::ensure!(
+                bucket.per_second > 0.0 && bucket.burst > 0.0,
+                "Rate limit for {name} must allow at least 1 request per second and a burst of 1"
+            );
+        }
+        Ok(())
+    }
+
+    /// Waits until a request to `path` is allowed.
+    pub(crate) async fn acquire(&self, path: &str) {
+        let path = path.trim_start_matches('/');
+        let bucket = self
+            .overrides
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, bucket)| bucket)
+            .or(self.default.as_ref());
+        let Some(bucket) = bucket else {
+            return;
+        };
+        let wait = bucket.reserve();
+        if !wait.is_zero() {
+            tracing::debug!("Rate limited request to {path} for {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn it_limits_requests() {
+        let mut limiter = RateLimiter::default();
+        limiter.set_default(10, 2);
+        limiter.set_override("v1/kv/".to_string(), 1, 1);
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire("/v1/status/leader").await;
+        }
+        assert_eq!(started.elapsed(), Duration::from_millis(200));
+        let started = Instant::now();
+        for _ in 0..2 {
+            limiter.acquire("/v1/kv/key").await;
+        }
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn it_rejects_zero_limits() {
+        let mut limiter = RateLimiter::default();
+        limiter.set_default(10, 2);
+        assert!(limiter.validate().is_ok());
+        limiter.set_override("v1/kv/".to_string(), 0, 1);
+        assert!(limiter.validate().is_err());
+        limiter.set_override("v1/kv/".to_string(), 1, 1);
+        limiter.set_default(1, 0);
+        assert!(limiter.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_passes_unlimited_requests() {
+        let limiter = RateLimiter::default();
+        let started = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire("/v1/kv/key").await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,353 @@
+//! Mirrors a KV prefix to another prefix, datacenter or cluster, in the
+//! spirit of consul-replicate.
+//!
+//! A [`Replicator`] watches the source prefix with blocking queries and on
+//! every change makes the destination match it, including deletes:
+//!
+//! ```ignore
+//! let handle = Replicator::new(Kv::new("app/").dc("dc1"), Kv::new("app/").dc("dc2"))
+//!     .conflict(Conflict::Preserve)
+//!     .spawn(&client, &client);
+//! println!("{:?}", handle.status());
+//! ```
+
+use std::collections::HashMap;
+
+use tokio::sync::watch;
+
+use crate::{Client, Kv, Record, diff};
+
+/// What to do with destination keys changed by someone else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Conflict {
+    /// Make the destination identical to the source.
+    #[default]
+    Overwrite,
+    /// Leave keys that differ from what was last replicated to them, and
+    /// report them in [`ReplicationStatus::conflicts`]. What was replicated
+    /// is only kept in memory: a new replicator, e.g. after a restart,
+    /// preserves every destination key that differs from the source.
+    Preserve,
+}
+
+pub struct Replicator {
+    source: Kv,
+    destination: Kv,
+    conflict: Conflict,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationStatus {
+    index: u64,
+    rounds: u64,
+    writes: u64,
+    deletes: u64,
+    conflicts: Vec<String>,
+    last_error: Option<String>,
+}
+
+impl ReplicationStatus {
+    /// Source index the destination was last synced to.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Completed sync rounds, one per source change.
+    pub fn rounds(&self) -> u64 {
+        self.rounds
+    }
+
+    pub fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    pub fn deletes(&self) -> u64 {
+        self.deletes
+    }
+
+    /// Relative keys skipped in the last round under [`Conflict::Preserve`].
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    /// Error of the last failed round; cleared by the next successful one.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+/// Running replication; stops when the last clone is dropped, so the status
+/// is updated for as long as a handle is held.
+#[derive(Debug, Clone)]
+pub struct ReplicationHandle {
+    status: watch::Receiver<ReplicationStatus>,
+}
+
+impl ReplicationHandle {
+    pub fn status(&self) -> ReplicationStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Waits until the status satisfies `f`, e.g. a number of rounds.
+    pub async fn wait_for<F>(&self, f: F)
+    where
+        F: FnMut(&ReplicationStatus) -> bool,
+    {
+        let mut status = self.status.clone();
+        let _ = status.wait_for(f).await;
+    }
+}
+
+impl Replicator {
+    /// Replicates the keys under `source` to the same relative keys under
+    /// `destination`; set `dc` on either to cross datacenters.
+    pub fn new(source: Kv, destination: Kv) -> Self {
+        Self {
+            source,
+            destination,
+            conflict: Conflict::default(),
+        }
+    }
+
+    pub fn conflict(mut self, conflict: Conflict) -> Self {
+        self.conflict = conflict;
+        self
+    }
+
+    /// Starts replicating from `source` through `client` to the
+    /// destination through `destination_client`.
+    pub fn spawn(self, client: &Client, destination_client: &Client) -> ReplicationHandle {
+        let (tx, rx) = watch::channel(ReplicationStatus::default());
+        let mut updates = self.source.clone().recurse(true).watch(client);
+        let client = client.clone();
+        let destination_client = destination_client.clone();
+        tokio::spawn(async move {
+            let mut replicated = HashMap::new();
+            loop {
+                let event = tokio::select! {
+                    _ = tx.closed() => return,
+                    event = updates.next() => event,
+                };
+                let round = match event {
+                    Ok(event) => self
+                        .sync(&client, &destination_client, &mut replicated)
+                        .await
+                        .map(|round| (event.index(), round)),
+                    Err(e) => Err(e),
+                };
+                tx.send_modify(|status| match round {
+                    Ok((index, round)) => {
+                        status.index = index;
+                        status.rounds += 1;
+                        status.writes += round.writes;
+                        status.deletes += round.deletes;
+                        status.conflicts = round.conflicts;
+                        status.last_error = None;
+                    }
+                    Err(e) => {
+                        tracing::warn!("KV replication failed: {e:#}");
+                        status.last_error = Some(format!("{e:#}"));
+                    }
+                });
+            }
+        });
+        ReplicationHandle { status: rx }
+    }
+
+    /// Makes the destination match the source. `replicated` holds the
+    /// value and flags each relative key had at the destination after the
+    /// last round.
+    async fn sync(
+        &self,
+        client: &Client,
+        destination_client: &Client,
+        replicated: &mut HashMap<String, State>,
+    ) -> Result<Round, anyhow::Error> {
+        let mut source = diff::entries(self.source.clone(), client).await?;
+        let destination = diff::entries(self.destination.clone(), destination_client).await?;
+        let mut keys: Vec<String> = source.keys().chain(destination.keys()).cloned().collect();
+        keys.sort();
+        keys.dedup();
+        let mut round = Round::default();
+        for key in keys {
+            let wanted = source.remove(&key);
+            let current = destination.get(&key).map(state);
+            if wanted.as_ref().map(state) == current {
+                match current {
+                    Some(current) => replicated.insert(key, current),
+                    None => replicated.remove(&key),
+                };
+                continue;
+            }
+            if self.conflict == Conflict::Preserve && replicated.get(&key) != current.as_ref() {
+                round.conflicts.push(key);
+                continue;
+            }
+            match wanted {
+                Some(record) => {
+                    self.write(&key, &record, destination_client).await?;
+                    replicated.insert(key, state(&record));
+                    round.writes += 1;
+                }
+                None => {
+                    self.key(&key)
+                        .delete(destination_client)
+                        .await?
+                        .error_for_status()?;
+                    replicated.remove(&key);
+                    round.deletes += 1;
+                }
+            }
+        }
+        Ok(round)
+    }
+
+    fn key(&self, key: &str) -> Kv {
+        let mut kv = Kv::new("").apply_if(self.destination.query.dc.clone(), Kv::dc);
+        kv.path = format!("{}{key}", self.destination.path);
+        kv
+    }
+
+    async fn write(
+        &self,
+        key: &str,
+        record: &Record,
+        client: &Client,
+    ) -> Result<(), anyhow::Error> {
+        let written = self
+            .key(key)
+            .flags(record.flags)
+            .body(record.value_as_slice()?)
+            .put_with_result(client)
+            .await?;
+        if !written {
+            anyhow::bail!("Failed to replicate {key}");
+        }
+        Ok(())
+    }
+}
+
+/// Value and flags of a key.
+type State = (Option<String>, u64);
+
+fn state(record: &Record) -> State {
+    (record.value.clone(), record.flags)
+}
+
+#[derive(Default)]
+struct Round {
+    writes: u64,
+    deletes: u64,
+    conflicts: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_replicates_prefixes() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("replicate/src/a")
+            .put_string(&client, "1")
+            .await
+            .unwrap();
+        Kv::new("replicate/dst/stale")
+            .put_string(&client, "0")
+            .await
+            .unwrap();
+        let handle = Replicator::new(Kv::new("replicate/src/"), Kv::new("replicate/dst/"))
+            .spawn(&client, &client);
+        handle.wait_for(|status| status.rounds() >= 1).await;
+        let record = Kv::new("replicate/dst/a").get(&client).await.unwrap();
+        assert_eq!(record.unwrap().value_as_string().unwrap(), "1");
+        assert!(
+            Kv::new("replicate/dst/stale")
+                .get(&client)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        Kv::new("replicate/src/a").delete(&client).await.unwrap();
+        handle.wait_for(|status| status.deletes() >= 2).await;
+        assert!(
+            Kv::new("replicate/dst/a")
+                .get(&client)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        Kv::new("replicate/")
+            .recurse(true)
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_preserves_changed_keys() {
+        use base64::prelude::*;
+
+        use crate::mock::{MockAgent, Reply};
+
+        let list = |entries: &[(&str, &str)]| {
+            let records: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| {
+                    serde_json::json!({
+                        "CreateIndex": 1,
+                        "Flags": 0,
+                        "Key": key,
+                        "LockIndex": 0,
+                        "ModifyIndex": 1,
+                        "Value": BASE64_STANDARD.encode(value),
+                    })
+                })
+                .collect();
+            Reply::json(records.into())
+        };
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect("GET", "v1/kv/src/", list(&[("src/a", "1"), ("src/d", "1")]));
+        agent.expect("GET", "v1/kv/dst/", Reply::not_found());
+        agent.expect("PUT", "v1/kv/dst/a", Reply::json(true.into()));
+        agent.expect("PUT", "v1/kv/dst/d", Reply::json(true.into()));
+        // Someone else changed `a` and added `b` since.
+        agent.expect("GET", "v1/kv/src/", list(&[("src/a", "2"), ("src/d", "2")]));
+        let changed = list(&[("dst/a", "x"), ("dst/b", "y"), ("dst/d", "1")]);
+        agent.expect("GET", "v1/kv/dst/", changed);
+        agent.expect("PUT", "v1/kv/dst/d", Reply::json(true.into()));
+        // A new replicator doesn't know `d` was its own write.
+        agent.expect("GET", "v1/kv/src/", list(&[("src/d", "3")]));
+        agent.expect("GET", "v1/kv/dst/", list(&[("dst/d", "2")]));
+        let client = Client::new(agent.url()).unwrap();
+        let replicator =
+            Replicator::new(Kv::new("src/"), Kv::new("dst/")).conflict(Conflict::Preserve);
+        let mut replicated = HashMap::new();
+        let round = replicator
+            .sync(&client, &client, &mut replicated)
+            .await
+            .unwrap();
+        assert_eq!((round.writes, round.conflicts.len()), (2, 0));
+        let round = replicator
+            .sync(&client, &client, &mut replicated)
+            .await
+            .unwrap();
+        assert_eq!(round.writes, 1);
+        assert_eq!(round.conflicts, ["a", "b"]);
+        let round = replicator
+            .sync(&client, &client, &mut HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(round.writes, 0);
+        assert_eq!(round.conflicts, ["d"]);
+        agent.verify().unwrap();
+        let written: Vec<_> = agent
+            .requests()
+            .into_iter()
+            .filter(|rq| rq.method() == "PUT")
+            .map(|rq| (rq.path().to_string(), rq.body().to_vec()))
+            .collect();
+        assert_eq!(written[2], ("v1/kv/dst/d".to_string(), b"2".to_vec()));
+    }
+}
@@ -0,0 +1,194 @@
+//! Client-side load balancing over the healthy instances of a service.
+//!
+//! A [`Resolver`] keeps the passing instances of a service up to date with
+//! blocking health queries in a background task. As a [`tower::Layer`] it
+//! wraps any HTTP service, e.g. a hyper client, and points each request at
+//! the next instance in round-robin order:
+//!
+//! ```ignore
+//! let resolver = Resolver::new(&client, Health::service("web").tag("v1"));
+//! resolver.ready().await;
+//! let svc = tower::ServiceBuilder::new().layer(resolver).service(hyper_client);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use tokio::sync::watch;
+
+use crate::Client;
+use crate::health::{Health, ServiceEntry};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    instances: watch::Receiver<Arc<Vec<String>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl Resolver {
+    /// Starts resolving the passing instances matched by `health`.
+    pub fn new(client: &Client, health: Health) -> Self {
+        Self {
+            instances: health.follow(client, ServiceEntry::authority),
+            next: Arc::default(),
+        }
+    }
+
+    /// Waits until at least one healthy instance is known, forever if none
+    /// ever is.
+    pub async fn ready(&self) {
+        Health::followed(&self.instances).await;
+    }
+
+    /// Current instances as `host:port` authorities.
+    pub fn instances(&self) -> Arc<Vec<String>> {
+        self.instances.borrow().clone()
+    }
+
+    /// Next instance in round-robin order.
+    pub fn pick(&self) -> Option<String> {
+        let instances = self.instances.borrow();
+        if instances.is_empty() {
+            return None;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        Some(instances[next % instances.len()].clone())
+    }
+}
+
+impl<S> tower::Layer<S> for Resolver {
+    type Service = Balance<S>;
+
+    fn layer(&self, inner: S) -> Balance<S> {
+        Balance {
+            inner,
+            resolver: self.clone(),
+        }
+    }
+}
+
+/// Service rewriting the authority of each request to a resolved instance.
+#[derive(Debug, Clone)]
+pub struct Balance<S> {
+    inner: S,
+    resolver: Resolver,
+}
+
+impl<S, B> tower::Service<http::Request<B>> for Balance<S>
+where
+    S: tower::Service<http::Request<B>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Err(e) = self.route(&mut req) {
+            return Box::pin(async move { Err(e) });
+        }
+        let fut = self.inner.call(req);
+        Box::pin(async move { fut.await.map_err(Into::into) })
+    }
+}
+
+impl<S> Balance<S> {
+    fn route<B>(&self, req: &mut http::Request<B>) -> Result<(), BoxError> {
+        let Some(authority) = self.resolver.pick() else {
+            return Err("No healthy instances".into());
+        };
+        let mut parts = req.uri().clone().into_parts();
+        parts.authority = Some(authority.parse()?);
+        if parts.scheme.is_none() {
+            parts.scheme = Some(http::uri::Scheme::HTTP);
+        }
+        if parts.path_and_query.is_none() {
+            parts.path_and_query = Some(http::uri::PathAndQuery::from_static("/"));
+        }
+        *req.uri_mut() = http::Uri::from_parts(parts)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{Layer, Service};
+
+    struct Echo;
+
+    impl Service<http::Request<()>> for Echo {
+        type Response = http::Uri;
+        type Error = BoxError;
+        type Future = std::future::Ready<Result<http::Uri, BoxError>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<()>) -> Self::Future {
+            std::future::ready(Ok(req.uri().clone()))
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_routes_to_ipv6_instances() {
+        use crate::mock::{MockAgent, Reply};
+
+        let entries = serde_json::json!([{
+            "Node": {"Node": "n1", "Address": "10.0.0.1"},
+            "Service": {"ID": "web-1", "Service": "web", "Address": "fd00::1", "Port": 8080},
+            "Checks": [],
+        }]);
+        let agent = MockAgent::start().await.unwrap();
+        agent.expect(
+            "GET",
+            "v1/health/service/web",
+            Reply::json(entries.clone()).index(1),
+        );
+        let hung = Reply::json(entries)
+            .index(1)
+            .delay(std::time::Duration::from_secs(60));
+        agent.expect("GET", "v1/health/service/web", hung);
+        let client = Client::new(agent.url()).unwrap();
+        let resolver = Resolver::new(&client, Health::service("web"));
+        resolver.ready().await;
+        assert_eq!(*resolver.instances(), ["[fd00::1]:8080"]);
+        let mut svc = resolver.layer(Echo);
+        let req = http::Request::get("/health").body(()).unwrap();
+        let uri = svc.call(req).await.unwrap();
+        assert_eq!(uri.to_string(), "http://[fd00::1]:8080/health");
+    }
+
+    #[tokio::test]
+    async fn it_balances_requests() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let resolver = Resolver::new(&client, Health::service("consul"));
+        let ready = resolver.ready();
+        tokio::time::timeout(std::time::Duration::from_secs(5), ready)
+            .await
+            .unwrap();
+        let instances = resolver.instances();
+        let mut svc = resolver.layer(Echo);
+        for i in 0..instances.len() * 2 {
+            let req = http::Request::get("/v1/status/leader").body(()).unwrap();
+            let uri = svc.call(req).await.unwrap();
+            assert_eq!(
+                uri.authority().unwrap().as_str(),
+                instances[i % instances.len()]
+            );
+            assert_eq!(uri.path(), "/v1/status/leader");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Retrying requests Consul rejected with 429 Too Many Requests.
+//!
+//! Without a [`RetryPolicy`] such requests fail right away with a
+//! [`RateLimited`](crate::error::RateLimited) error carrying the advised
+//! delay.
+
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Set with `ClientBuilder::retry_policy`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Delay when the response has no `Retry-After`, doubled per attempt.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Longest delay to wait; a longer one fails the request instead.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay before retry number `attempt + 1`, or `None` to give up.
+    pub(crate) fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let delay = retry_after.unwrap_or_else(|| self.delay.saturating_mul(1 << attempt.min(16)));
+        (delay <= self.max_delay).then_some(delay)
+    }
+}
+
+/// `Retry-After` in seconds; Consul doesn't send HTTP dates.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_backs_off() {
+        let policy = RetryPolicy::new().max_retries(2);
+        assert_eq!(policy.backoff(0, None), Some(Duration::from_secs(1)));
+        assert_eq!(policy.backoff(1, None), Some(Duration::from_secs(2)));
+        assert_eq!(policy.backoff(2, None), None);
+        let advised = Some(Duration::from_secs(60));
+        assert_eq!(policy.backoff(0, advised), None);
+    }
+
+    /// Answers 429 `limited` times, then with the leader.
+    #[cfg(feature = "mock")]
+    async fn agent(limited: usize) -> crate::mock::MockAgent {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        for _ in 0..limited {
+            let reply = Reply::new(429, "rate limit exceeded").header("Retry-After", "0");
+            agent.expect("GET", "v1/status/leader", reply);
+        }
+        agent.expect(
+            "GET",
+            "v1/status/leader",
+            Reply::json("10.0.0.1:8300".into()),
+        );
+        agent
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_retries_rate_limited_requests() {
+        use crate::Client;
+        use crate::error::RateLimited;
+        use crate::status::Status;
+
+        let retried = agent(2).await;
+        let client = Client::builder(retried.url())
+            .retry_policy(RetryPolicy::new())
+            .build()
+            .unwrap();
+        let leader = Status::leader(&client).await.unwrap();
+        assert_eq!(leader.as_deref(), Some("10.0.0.1:8300"));
+        retried.verify().unwrap();
+        let limited = agent(1).await;
+        let client = Client::new(limited.url()).unwrap();
+        let err = Status::leader(&client).await.unwrap_err();
+        let limited = err.downcast_ref::<RateLimited>().unwrap();
+        assert_eq!(limited.retry_after(), Some(Duration::ZERO));
+        assert_eq!(limited.message(), "rate limit exceeded");
+    }
+}
@@ -0,0 +1,195 @@
+//! KV access confined to one prefix.
+//!
+//! A [`ScopedKv`] prepends its prefix to every key and strips it from the
+//! keys it returns, so a library handed `client.scoped("apps/myapp/")` sees
+//! `config` rather than `apps/myapp/config` and can't address keys outside
+//! its prefix:
+//!
+//! ```ignore
+//! let kv = client.scoped("apps/myapp/");
+//! kv.put_string("config", "{}").await?;
+//! for record in kv.list("").await? {
+//!     println!("{}", record.key()); // "config"
+//! }
+//! ```
+
+use bytes::Bytes;
+
+use crate::{Client, Kv, Record, Response, watch};
+
+#[derive(Debug, Clone)]
+pub struct ScopedKv {
+    client: Client,
+    prefix: String,
+}
+
+impl Client {
+    /// KV handle confined to the folder `prefix`; a missing trailing `/` is
+    /// added so `apps/myapp` doesn't also cover `apps/myapp2`.
+    pub fn scoped<S>(&self, prefix: S) -> ScopedKv
+    where
+        S: Into<String>,
+    {
+        ScopedKv {
+            client: self.clone(),
+            prefix: folder(prefix.into()),
+        }
+    }
+}
+
+fn folder(mut prefix: String) -> String {
+    if !prefix.is_empty() && !prefix.ends_with('/') {
+        prefix.push('/');
+    }
+    prefix
+}
+
+impl ScopedKv {
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Narrower scope below this one, failing for prefixes that could leave
+    /// it like [`ScopedKv::kv`] does.
+    pub fn scoped(&self, prefix: &str) -> Result<ScopedKv, anyhow::Error> {
+        self.check(prefix)?;
+        Ok(ScopedKv {
+            client: self.client.clone(),
+            prefix: folder(format!("{}{prefix}", self.prefix)),
+        })
+    }
+
+    /// Builder for `key` within the scope, for options not covered by the
+    /// handle, e.g. `cas` or `acquire`. Run it with [`ScopedKv::client`].
+    ///
+    /// Fails for keys that could leave the scope: absolute keys and `..`
+    /// segments.
+    pub fn kv(&self, key: &str) -> Result<Kv, anyhow::Error> {
+        self.check(key)?;
+        Ok(Kv::new(format!("{}{key}", self.prefix)))
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Record>, anyhow::Error> {
+        let record = self.kv(key)?.get(&self.client).await?;
+        Ok(record.map(|record| self.strip(record)))
+    }
+
+    pub async fn get_bytes(&self, key: &str) -> Result<Option<Bytes>, anyhow::Error> {
+        self.kv(key)?.get_bytes(&self.client).await
+    }
+
+    /// Records under `prefix` within the scope.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<Record>, anyhow::Error> {
+        let records = self.kv(prefix)?.list(&self.client).await?;
+        Ok(records.into_iter().map(|r| self.strip(r)).collect())
+    }
+
+    /// Key names under `prefix` within the scope.
+    pub async fn keys(&self, prefix: &str) -> Result<Vec<String>, anyhow::Error> {
+        let keys = self.kv(prefix)?.keys_list(&self.client).await?;
+        Ok(keys
+            .into_iter()
+            .map(|key| self.relative(&key).to_string())
+            .collect())
+    }
+
+    pub async fn put_string<S>(&self, key: &str, value: S) -> Result<Response, anyhow::Error>
+    where
+        S: Into<String>,
+    {
+        self.kv(key)?.put_string(&self.client, value).await
+    }
+
+    pub async fn put_bytes<B>(&self, key: &str, value: B) -> Result<Response, anyhow::Error>
+    where
+        B: Into<Vec<u8>>,
+    {
+        self.kv(key)?.put_bytes(&self.client, value).await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<Response, anyhow::Error> {
+        self.kv(key)?.delete(&self.client).await
+    }
+
+    /// Deletes every key under `prefix` within the scope and returns them,
+    /// relative to the scope. An empty prefix clears the whole scope.
+    pub async fn delete_tree(&self, prefix: &str) -> Result<Vec<String>, anyhow::Error> {
+        let keys = self.kv(prefix)?.delete_tree(&self.client).await?;
+        Ok(keys
+            .into_iter()
+            .map(|key| self.relative(&key).to_string())
+            .collect())
+    }
+
+    /// Watches a single key within the scope; records in the events keep
+    /// their full key.
+    pub fn watch_key(&self, key: &str) -> Result<watch::KeyWatch, anyhow::Error> {
+        Ok(self.kv(key)?.watch_key(&self.client))
+    }
+
+    fn check(&self, key: &str) -> Result<(), anyhow::Error> {
+        if key.starts_with('/') || key.split('/').any(|segment| segment == "..") {
+            anyhow::bail!("Key {key:?} escapes the {:?} scope", self.prefix);
+        }
+        Ok(())
+    }
+
+    fn relative<'a>(&self, key: &'a str) -> &'a str {
+        key.strip_prefix(self.prefix.as_str()).unwrap_or(key)
+    }
+
+    fn strip(&self, mut record: Record) -> Record {
+        record.key = self.relative(&record.key).to_string();
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_confines_keys() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let kv = client.scoped("apps/myapp/");
+        assert!(kv.kv("config").is_ok());
+        assert!(kv.kv("../other/config").is_err());
+        assert!(kv.kv("/config").is_err());
+        assert_eq!(kv.scoped("db").unwrap().prefix(), "apps/myapp/db/");
+        assert!(kv.scoped("../other").is_err());
+        assert!(kv.scoped("/").is_err());
+        assert_eq!(kv.relative("apps/myapp/config"), "config");
+        assert_eq!(client.scoped("apps/myapp").prefix(), "apps/myapp/");
+        assert_eq!(client.scoped("").prefix(), "");
+    }
+
+    #[tokio::test]
+    async fn it_scopes_keys() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let kv = client.scoped("scoped/app");
+        kv.put_string("config", "1").await.unwrap();
+        kv.put_string("db/url", "2").await.unwrap();
+        Kv::new("scoped/app2")
+            .put_string(&client, "3")
+            .await
+            .unwrap();
+        let record = kv.get("config").await.unwrap().unwrap();
+        assert_eq!(record.key(), "config");
+        let keys: Vec<_> = kv
+            .list("")
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.key().to_string())
+            .collect();
+        assert_eq!(keys, ["config", "db/url"]);
+        assert_eq!(kv.keys("db/").await.unwrap(), ["db/url"]);
+        assert_eq!(kv.delete_tree("").await.unwrap(), ["config", "db/url"]);
+        assert!(Kv::new("scoped/app2").get(&client).await.unwrap().is_some());
+        Kv::new("scoped/app2").delete(&client).await.unwrap();
+    }
+}
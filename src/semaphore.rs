@@ -0,0 +1,315 @@
+//! Counting semaphore following Consul's KV + session contention protocol.
+//!
+//! Each contender writes `<prefix>/<session>` acquired by its session, and the
+//! holders are coordinated through a CAS-updated `<prefix>/.lock` document
+//! compatible with the Go client's `api.Semaphore`.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::session::KeepAlive;
+use crate::{Client, Kv, Record};
+
+/// Flag value the Go client stores on semaphore keys.
+pub const SEMAPHORE_FLAG: u64 = 0xe0f69a2baa414de0;
+
+const LOCK_KEY: &str = ".lock";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LockState {
+    limit: usize,
+    holders: HashMap<String, bool>,
+}
+
+pub struct Semaphore {
+    client: Client,
+    prefix: String,
+    limit: usize,
+    session: String,
+    keep_alive: Option<KeepAlive>,
+    wait: Duration,
+}
+
+impl Semaphore {
+    /// Semaphore allowing `limit` holders under `prefix`, contending with an
+    /// existing session. A `limit` of 0 is rejected when acquiring.
+    pub fn new<P, S>(client: &Client, prefix: P, limit: usize, session: S) -> Self
+    where
+        P: Into<String>,
+        S: Into<String>,
+    {
+        let prefix = prefix.into();
+        Self {
+            client: client.clone(),
+            prefix: prefix.trim_end_matches('/').to_string(),
+            limit,
+            session: session.into(),
+            keep_alive: None,
+            wait: Duration::from_secs(60),
+        }
+    }
+
+    /// Like [`Semaphore::new`], contending with the session renewed by
+    /// `keep_alive`. Acquiring fails once the session is lost, and the
+    /// renewals stop when the semaphore is dropped.
+    pub fn with_keep_alive<P>(
+        client: &Client,
+        prefix: P,
+        limit: usize,
+        keep_alive: KeepAlive,
+    ) -> Self
+    where
+        P: Into<String>,
+    {
+        let mut semaphore = Self::new(client, prefix, limit, keep_alive.id());
+        semaphore.keep_alive = Some(keep_alive);
+        semaphore
+    }
+
+    /// Wait time of blocking queries while waiting for a slot.
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Takes a slot if one is free, without waiting.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(prefix = %self.prefix)))]
+    pub async fn try_acquire(&self) -> Result<bool, anyhow::Error> {
+        self.contend().await?;
+        let (records, _) = self.read(None).await?;
+        self.try_take(&records).await
+    }
+
+    /// Waits until a slot is available and takes it.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(prefix = %self.prefix)))]
+    pub async fn acquire(&self) -> Result<(), anyhow::Error> {
+        self.contend().await?;
+        let mut index = None;
+        loop {
+            let read = self.read(index);
+            let (records, next) = match &self.keep_alive {
+                Some(keep_alive) => tokio::select! {
+                    read = read => read?,
+                    _ = keep_alive.lost() => anyhow::bail!("Session {} was lost", self.session),
+                },
+                None => read.await?,
+            };
+            if self.try_take(&records).await? {
+                return Ok(());
+            }
+            index = Some(next);
+        }
+    }
+
+    /// Gives the slot back and withdraws the contender entry.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all, fields(prefix = %self.prefix)))]
+    pub async fn release(&self) -> Result<(), anyhow::Error> {
+        loop {
+            let (records, _) = self.read(None).await?;
+            let (mut state, modify_index) = self.state(&records)?;
+            if state.holders.remove(&self.session).is_none() {
+                break;
+            }
+            if self.write_state(&state, modify_index).await? {
+                break;
+            }
+        }
+        Kv::new(self.contender_key())
+            .delete(&self.client)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn contender_key(&self) -> String {
+        format!("{}/{}", self.prefix, self.session)
+    }
+
+    fn lock_key(&self) -> String {
+        format!("{}/{LOCK_KEY}", self.prefix)
+    }
+
+    async fn contend(&self) -> Result<(), anyhow::Error> {
+        // No slot could ever be taken, so acquire would wait forever.
+        anyhow::ensure!(self.limit > 0, "Semaphore limit must be at least 1");
+        if self.keep_alive.as_ref().is_some_and(KeepAlive::is_lost) {
+            anyhow::bail!("Session {} was lost", self.session);
+        }
+        let acquired = Kv::new(self.contender_key())
+            .acquire(&self.session)
+            .flags(SEMAPHORE_FLAG)
+            .body(Vec::new())
+            .put_with_result(&self.client)
+            .await?;
+        if !acquired {
+            anyhow::bail!("Failed to register contender for session {}", self.session);
+        }
+        Ok(())
+    }
+
+    async fn read(&self, index: Option<u64>) -> Result<(Vec<Record>, u64), anyhow::Error> {
+        let rs = Kv::new(format!("{}/", self.prefix))
+            .recurse(true)
+            .apply_if(index, |kv, index| kv.index(index).wait(self.wait))
+            .send_request(Method::GET, &self.client)
+            .await?;
+        let next = rs.index().unwrap_or_default();
+        if rs.status == 404 {
+            return Ok((vec![], next));
+        }
+        Ok((rs.error_for_status()?.try_into()?, next))
+    }
+
+    fn state(&self, records: &[Record]) -> Result<(LockState, u64), anyhow::Error> {
+        let lock_key = self.lock_key();
+        let Some(record) = records.iter().find(|r| r.key() == lock_key) else {
+            return Ok((
+                LockState {
+                    limit: self.limit,
+                    ..Default::default()
+                },
+                0,
+            ));
+        };
+        if record.flags() != SEMAPHORE_FLAG {
+            anyhow::bail!("{lock_key} exists but is not a semaphore");
+        }
+        let state: LockState = serde_json::from_slice(&record.value_as_slice()?)?;
+        if state.limit != self.limit {
+            anyhow::bail!(
+                "Semaphore limit conflict: {} in Consul, {} requested",
+                state.limit,
+                self.limit
+            );
+        }
+        Ok((state, record.modify_index() as u64))
+    }
+
+    async fn try_take(&self, records: &[Record]) -> Result<bool, anyhow::Error> {
+        let (mut state, modify_index) = self.state(records)?;
+        let prefix = format!("{}/", self.prefix);
+        let alive: HashSet<&str> = records
+            .iter()
+            .filter_map(|r| Some((r.key().strip_prefix(&prefix)?, r.session()?)))
+            .filter(|(key, session)| key == session)
+            .map(|(_, session)| session)
+            .collect();
+        state
+            .holders
+            .retain(|holder, _| alive.contains(holder.as_str()));
+        if state.holders.contains_key(&self.session) {
+            return Ok(true);
+        }
+        if state.holders.len() >= self.limit {
+            return Ok(false);
+        }
+        state.holders.insert(self.session.clone(), true);
+        self.write_state(&state, modify_index).await
+    }
+
+    async fn write_state(&self, state: &LockState, cas: u64) -> Result<bool, anyhow::Error> {
+        Kv::new(self.lock_key())
+            .cas(cas)
+            .flags(SEMAPHORE_FLAG)
+            .body(serde_json::to_vec(state)?)
+            .put_with_result(&self.client)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+
+    #[tokio::test]
+    async fn it_limits_holders() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let mut sessions = vec![];
+        for _ in 0..3 {
+            sessions.push(Session::new().create(&client).await.unwrap());
+        }
+        let semaphores: Vec<_> = sessions
+            .iter()
+            .map(|id| Semaphore::new(&client, "semaphore/jobs", 2, id))
+            .collect();
+        assert!(semaphores[0].try_acquire().await.unwrap());
+        assert!(semaphores[1].try_acquire().await.unwrap());
+        assert!(!semaphores[2].try_acquire().await.unwrap());
+        semaphores[0].release().await.unwrap();
+        assert!(semaphores[2].try_acquire().await.unwrap());
+        semaphores[1].release().await.unwrap();
+        semaphores[2].release().await.unwrap();
+        for id in &sessions {
+            Session::destroy(&client, id).await.unwrap();
+        }
+        Kv::new("semaphore/jobs/.lock")
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_rejects_zero_limits() {
+        let client = Client::new("http://127.0.0.1:1").unwrap();
+        let semaphore = Semaphore::new(&client, "semaphore/none", 0, "session");
+        let err = semaphore.acquire().await.unwrap_err();
+        assert_eq!(err.to_string(), "Semaphore limit must be at least 1");
+        assert!(semaphore.try_acquire().await.is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_stops_waiting_once_the_session_is_lost() {
+        use base64::prelude::*;
+
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let record = |key: &str, value: &[u8], session: &str| {
+            serde_json::json!({
+                "Key": key,
+                "Flags": SEMAPHORE_FLAG,
+                "Value": BASE64_STANDARD.encode(value),
+                "Session": session,
+                "LockIndex": 1,
+                "CreateIndex": 1,
+                "ModifyIndex": 1,
+            })
+        };
+        let full = serde_json::json!([
+            record(
+                "semaphore/lost/.lock",
+                br#"{"Limit":1,"Holders":{"s2":true}}"#,
+                ""
+            ),
+            record("semaphore/lost/s2", b"", "s2"),
+        ]);
+        agent.expect("PUT", "v1/kv/semaphore/lost/s1", Reply::json(true.into()));
+        agent.expect("GET", "v1/kv/semaphore/lost/", Reply::json(full).index(5));
+        let hung = Reply::json(serde_json::json!([])).delay(Duration::from_secs(60));
+        agent.expect("GET", "v1/kv/semaphore/lost/", hung);
+        agent.expect("PUT", "v1/session/renew/s1", Reply::not_found());
+        let client = Client::new(agent.url()).unwrap();
+        let keep_alive = Session::keep_alive(
+            &client,
+            "s1",
+            Duration::from_secs(10),
+            Duration::from_millis(200),
+        );
+        let semaphore = Semaphore::with_keep_alive(&client, "semaphore/lost", 1, keep_alive);
+        let acquire = tokio::time::timeout(Duration::from_secs(5), semaphore.acquire());
+        let err = acquire.await.unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "Session s1 was lost");
+        assert!(semaphore.try_acquire().await.is_err());
+        agent.verify().unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, watch};
+use tokio::time::Instant;
+
+use crate::Client;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Behavior {
+    Release,
+    Delete,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SessionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lock_delay: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    behavior: Option<Behavior>,
+    #[serde(rename = "TTL", skip_serializing_if = "Option::is_none")]
+    ttl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_checks: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_checks: Option<Vec<ServiceCheck>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceCheck {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(default)]
+    pub namespace: String,
+}
+
+#[derive(Default, Serialize)]
+struct DcQuery {
+    dc: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Session {
+    request: SessionRequest,
+    query: DcQuery,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dc<S>(mut self, dc: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.dc = Some(dc.into());
+        self
+    }
+
+    pub fn name<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.name = Some(name.into());
+        self
+    }
+
+    pub fn node<S>(mut self, node: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.request.node = Some(node.into());
+        self
+    }
+
+    pub fn lock_delay(mut self, delay: Duration) -> Self {
+        self.request.lock_delay = Some(format!("{}ms", delay.as_millis()));
+        self
+    }
+
+    pub fn behavior(mut self, behavior: Behavior) -> Self {
+        self.request.behavior = Some(behavior);
+        self
+    }
+
+    /// Session TTL; Consul accepts values between 10s and 24h.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.request.ttl = Some(format!("{}s", ttl.as_secs()));
+        self
+    }
+
+    /// Node checks tied to the session; an empty list detaches it from the
+    /// default `serfHealth` check.
+    pub fn node_checks(mut self, checks: Vec<String>) -> Self {
+        self.request.node_checks = Some(checks);
+        self
+    }
+
+    pub fn service_checks(mut self, checks: Vec<ServiceCheck>) -> Self {
+        self.request.service_checks = Some(checks);
+        self
+    }
+
+    /// Creates the session and returns its ID.
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "debug", skip_all))]
+    pub async fn create(self, client: &Client) -> Result<String, anyhow::Error> {
+        let payload = serde_json::to_value(&self.request)?;
+        let rs: SessionId = client
+            .send(
+                Method::PUT,
+                "v1/session/create",
+                &self.query,
+                Some(payload),
+                None,
+            )
+            .await?
+            .decode()?;
+        Ok(rs.id)
+    }
+
+    pub async fn destroy(client: &Client, id: &str) -> Result<(), anyhow::Error> {
+        let path = format!("v1/session/destroy/{id}");
+        client
+            .send(Method::PUT, &path, &(), None, None)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Renews a TTL session; `None` means the session no longer exists.
+    pub async fn renew(client: &Client, id: &str) -> Result<Option<SessionInfo>, anyhow::Error> {
+        let path = format!("v1/session/renew/{id}");
+        let rs = client.send(Method::PUT, &path, &(), None, None).await?;
+        if rs.status == 404 {
+            return Ok(None);
+        }
+        let mut sessions: Vec<SessionInfo> = rs.decode()?;
+        Ok(sessions.pop())
+    }
+
+    /// Renews the session `id`, created with `ttl`, every `interval`, which
+    /// should be well below the TTL, until the returned guard is dropped.
+    /// Failed renewals are retried on the next tick; the guard reports the
+    /// session as lost once Consul no longer knows it or no renewal has
+    /// succeeded for a whole TTL.
+    pub fn keep_alive(client: &Client, id: &str, ttl: Duration, interval: Duration) -> KeepAlive {
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        let (tx, rx) = watch::channel(false);
+        let client = client.clone();
+        let session = id.to_string();
+        tokio::spawn(async move {
+            let mut renewed = Instant::now();
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => return,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+                match Session::renew(&client, &session).await {
+                    Ok(Some(_)) => {
+                        renewed = Instant::now();
+                        continue;
+                    }
+                    Ok(None) => tracing::warn!("Session {session} was invalidated"),
+                    Err(e) => {
+                        tracing::warn!("Failed to renew session {session}: {e:#}");
+                        if renewed.elapsed() < ttl {
+                            continue;
+                        }
+                    }
+                }
+                tx.send_replace(true);
+                return;
+            }
+        });
+        KeepAlive {
+            id: id.to_string(),
+            lost: rx,
+            _stop: stop,
+        }
+    }
+
+    pub async fn info(client: &Client, id: &str) -> Result<Option<SessionInfo>, anyhow::Error> {
+        let path = format!("v1/session/info/{id}");
+        let sessions: Option<Vec<SessionInfo>> = client
+            .send(Method::GET, &path, &(), None, None)
+            .await?
+            .decode()?;
+        Ok(sessions.and_then(|mut s| s.pop()))
+    }
+
+    pub async fn list(client: &Client) -> Result<Vec<SessionInfo>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/session/list", &(), None, None)
+            .await?
+            .decode()
+    }
+
+    pub async fn node_sessions(
+        client: &Client,
+        node: &str,
+    ) -> Result<Vec<SessionInfo>, anyhow::Error> {
+        let path = format!("v1/session/node/{node}");
+        client
+            .send(Method::GET, &path, &(), None, None)
+            .await?
+            .decode()
+    }
+}
+
+/// Guard returned by [`Session::keep_alive`]; renewal stops when it is
+/// dropped and the session then expires after its TTL.
+#[derive(Debug)]
+pub struct KeepAlive {
+    id: String,
+    lost: watch::Receiver<bool>,
+    _stop: oneshot::Sender<()>,
+}
+
+impl KeepAlive {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the session was invalidated or has expired; locks held
+    /// through it must be considered released.
+    pub fn is_lost(&self) -> bool {
+        *self.lost.borrow()
+    }
+
+    /// Waits until the session is lost.
+    pub async fn lost(&self) {
+        let mut lost = self.lost.clone();
+        // The sender is only dropped after reporting the loss, or together
+        // with this guard.
+        let _ = lost.wait_for(|lost| *lost).await;
+    }
+
+    /// Channel flipping to `true` once the session is lost, e.g. to hand to
+    /// a task doing work under a lock.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.lost.clone()
+    }
+}
+
+#[derive(Deserialize)]
+struct SessionId {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionInfo {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(default)]
+    name: String,
+    node: String,
+    #[serde(default)]
+    lock_delay: u64,
+    behavior: Behavior,
+    #[serde(rename = "TTL", default)]
+    ttl: String,
+    #[serde(default)]
+    node_checks: Option<Vec<String>>,
+    #[serde(default)]
+    service_checks: Option<Vec<ServiceCheck>>,
+    create_index: u64,
+    modify_index: u64,
+}
+
+impl SessionInfo {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    pub fn lock_delay(&self) -> Duration {
+        Duration::from_nanos(self.lock_delay)
+    }
+
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        crate::parse_duration(&self.ttl).filter(|d| !d.is_zero())
+    }
+
+    pub fn node_checks(&self) -> &[String] {
+        self.node_checks.as_deref().unwrap_or_default()
+    }
+
+    pub fn service_checks(&self) -> &[ServiceCheck] {
+        self.service_checks.as_deref().unwrap_or_default()
+    }
+
+    pub fn create_index(&self) -> u64 {
+        self.create_index
+    }
+
+    pub fn modify_index(&self) -> u64 {
+        self.modify_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_manages_sessions() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let id = Session::new()
+            .name("consulite")
+            .ttl(Duration::from_secs(30))
+            .behavior(Behavior::Delete)
+            .create(&client)
+            .await
+            .unwrap();
+        let info = Session::info(&client, &id).await.unwrap().unwrap();
+        assert_eq!(info.name(), "consulite");
+        assert_eq!(info.behavior(), Behavior::Delete);
+        assert_eq!(info.ttl(), Some(Duration::from_secs(30)));
+        assert!(Session::renew(&client, &id).await.unwrap().is_some());
+        assert!(
+            Session::list(&client)
+                .await
+                .unwrap()
+                .iter()
+                .any(|s| s.id() == id)
+        );
+        Session::destroy(&client, &id).await.unwrap();
+        assert!(Session::info(&client, &id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn it_keeps_sessions_alive() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let id = Session::new()
+            .ttl(Duration::from_secs(10))
+            .create(&client)
+            .await
+            .unwrap();
+        let keeper = Session::keep_alive(
+            &client,
+            &id,
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+        );
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!keeper.is_lost());
+        Session::destroy(&client, &id).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), keeper.lost())
+            .await
+            .unwrap();
+        assert!(*keeper.subscribe().borrow());
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_loses_sessions_never_renewed() {
+        use crate::mock::MockAgent;
+
+        // Every renewal fails with an unexpected-request error, not a 404.
+        let agent = MockAgent::start().await.unwrap();
+        let client = Client::new(agent.url()).unwrap();
+        let keeper = Session::keep_alive(
+            &client,
+            "adf4238a-882b-9ddc-4a9d-5b6758e4159e",
+            Duration::from_millis(200),
+            Duration::from_millis(50),
+        );
+        tokio::time::timeout(Duration::from_secs(5), keeper.lost())
+            .await
+            .unwrap();
+        assert!(keeper.is_lost());
+        assert!(!agent.requests().is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+//! Happy-path helpers for scripts and examples.
+//!
+//! Each call builds a fresh [`Client`]; long-running programs should use the
+//! full client instead. The `*_blocking` variants spin up a private runtime
+//! and must not be called from within an async context.
+
+use std::future::Future;
+
+use reqwest::Method;
+
+use crate::health::{Health, ServiceEntry};
+use crate::{Client, Kv};
+
+pub async fn get<U, K>(url: U, key: K) -> Result<Option<String>, anyhow::Error>
+where
+    U: Into<String>,
+    K: Into<String>,
+{
+    let client = Client::new(url)?;
+    let rs = Kv::new(key)
+        .raw(true)
+        .send_request(Method::GET, &client)
+        .await?;
+    match rs.status {
+        404 => Ok(None),
+        200 => Ok(Some(rs.raw())),
+        _ => Err(rs.into_error()),
+    }
+}
+
+pub async fn put<U, K, V>(url: U, key: K, value: V) -> Result<(), anyhow::Error>
+where
+    U: Into<String>,
+    K: Into<String>,
+    V: Into<Vec<u8>>,
+{
+    let client = Client::new(url)?;
+    let rs = Kv::new(key).body(value.into()).put(&client).await?;
+    rs.error_for_status()?;
+    Ok(())
+}
+
+pub async fn healthy_instances<U, S>(url: U, service: S) -> Result<Vec<ServiceEntry>, anyhow::Error>
+where
+    U: Into<String>,
+    S: Into<String>,
+{
+    let client = Client::new(url)?;
+    Health::service(service).passing(true).get(&client).await
+}
+
+pub fn get_blocking<U, K>(url: U, key: K) -> Result<Option<String>, anyhow::Error>
+where
+    U: Into<String>,
+    K: Into<String>,
+{
+    block_on(get(url, key))
+}
+
+pub fn put_blocking<U, K, V>(url: U, key: K, value: V) -> Result<(), anyhow::Error>
+where
+    U: Into<String>,
+    K: Into<String>,
+    V: Into<Vec<u8>>,
+{
+    block_on(put(url, key, value))
+}
+
+pub fn healthy_instances_blocking<U, S>(
+    url: U,
+    service: S,
+) -> Result<Vec<ServiceEntry>, anyhow::Error>
+where
+    U: Into<String>,
+    S: Into<String>,
+{
+    block_on(healthy_instances(url, service))
+}
+
+fn block_on<F, T>(future: F) -> Result<T, anyhow::Error>
+where
+    F: Future<Output = Result<T, anyhow::Error>>,
+{
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works_blocking() {
+        let url = "http://localhost:8500";
+        put_blocking(url, "simple/key0", "value").unwrap();
+        assert_eq!(
+            get_blocking(url, "simple/key0").unwrap().as_deref(),
+            Some("value")
+        );
+        assert_eq!(get_blocking(url, "simple/missing").unwrap(), None);
+        assert!(
+            !healthy_instances_blocking(url, "consul")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
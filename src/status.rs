@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures_util::Stream;
+use reqwest::Method;
+
+use crate::Client;
+
+pub struct Status;
+
+impl Status {
+    /// Raft leader address, or `None` while the cluster has no leader.
+    pub async fn leader(client: &Client) -> Result<Option<String>, anyhow::Error> {
+        let leader: String = client
+            .send(Method::GET, "v1/status/leader", &(), None, None)
+            .await?
+            .decode()?;
+        Ok((!leader.is_empty()).then_some(leader))
+    }
+
+    pub async fn peers(client: &Client) -> Result<Vec<String>, anyhow::Error> {
+        client
+            .send(Method::GET, "v1/status/peers", &(), None, None)
+            .await?
+            .decode()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderChange {
+    at: SystemTime,
+    previous: Option<String>,
+    leader: Option<String>,
+    flapping: bool,
+}
+
+impl LeaderChange {
+    pub fn at(&self) -> SystemTime {
+        self.at
+    }
+
+    pub fn previous(&self) -> Option<&str> {
+        self.previous.as_deref()
+    }
+
+    pub fn leader(&self) -> Option<&str> {
+        self.leader.as_deref()
+    }
+
+    /// Whether the flapping threshold was reached when this change was seen.
+    pub fn flapping(&self) -> bool {
+        self.flapping
+    }
+}
+
+/// Polls `v1/status/leader` and records every observed leadership change.
+pub struct LeaderTracker {
+    client: Client,
+    interval: Duration,
+    capacity: usize,
+    window: Duration,
+    threshold: usize,
+    current: Option<Option<String>>,
+    history: VecDeque<LeaderChange>,
+    /// When the changes within the flapping window were seen, regardless
+    /// of the history capacity.
+    recent: VecDeque<Instant>,
+}
+
+impl LeaderTracker {
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            interval: Duration::from_secs(5),
+            capacity: 128,
+            window: Duration::from_secs(300),
+            threshold: 3,
+            current: None,
+            history: VecDeque::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Number of changes kept in [`LeaderTracker::history`]; the initial
+    /// observation is not a change.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Leadership is flapping when `threshold` changes happen within `window`.
+    pub fn flapping(mut self, window: Duration, threshold: usize) -> Self {
+        self.window = window;
+        self.threshold = threshold.max(1);
+        self
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &LeaderChange> {
+        self.history.iter()
+    }
+
+    pub fn is_flapping(&self) -> bool {
+        self.recent_changes() >= self.threshold
+    }
+
+    /// Polls until the leader differs from the last observation. The first
+    /// call returns the initial leader with no previous value.
+    pub async fn next(&mut self) -> Result<LeaderChange, anyhow::Error> {
+        loop {
+            if self.current.is_some() {
+                tokio::time::sleep(self.interval).await;
+            }
+            let leader = Status::leader(&self.client).await?;
+            if self.current.as_ref() == Some(&leader) {
+                continue;
+            }
+            let initial = self.current.is_none();
+            let previous = self.current.replace(leader.clone()).flatten();
+            let mut change = LeaderChange {
+                at: SystemTime::now(),
+                previous,
+                leader,
+                flapping: false,
+            };
+            if !initial {
+                self.recent.push_back(Instant::now());
+                while self
+                    .recent
+                    .front()
+                    .is_some_and(|at| at.elapsed() > self.window)
+                {
+                    self.recent.pop_front();
+                }
+                change.flapping = self.recent_changes() >= self.threshold;
+                if self.history.len() == self.capacity {
+                    self.history.pop_front();
+                }
+                self.history.push_back(change.clone());
+            }
+            return Ok(change);
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<LeaderChange, anyhow::Error>> {
+        futures_util::stream::unfold(self, |mut tracker| async move {
+            let change = tracker.next().await;
+            Some((change, tracker))
+        })
+    }
+
+    fn recent_changes(&self) -> usize {
+        self.recent
+            .iter()
+            .filter(|at| at.elapsed() <= self.window)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_tracks_leader() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        let leader = Status::leader(&client).await.unwrap();
+        assert!(leader.is_some());
+        assert!(!Status::peers(&client).await.unwrap().is_empty());
+        let mut tracker = LeaderTracker::new(&client);
+        let change = tracker.next().await.unwrap();
+        assert_eq!(change.leader(), leader.as_deref());
+        assert_eq!(change.previous(), None);
+        assert!(!tracker.is_flapping());
+    }
+
+    /// Mock agent answering the leader polls with `leaders` in order; empty
+    /// for no leader.
+    #[cfg(feature = "mock")]
+    async fn agent(leaders: &[&str]) -> crate::mock::MockAgent {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        for leader in leaders {
+            agent.expect("GET", "v1/status/leader", Reply::json((*leader).into()));
+        }
+        agent
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_records_leader_changes() {
+        let agent = agent(&[
+            "10.0.0.1:8300",
+            "10.0.0.1:8300",
+            "10.0.0.2:8300",
+            "",
+            "10.0.0.2:8300",
+            "10.0.0.1:8300",
+        ])
+        .await;
+        let client = Client::new(agent.url()).unwrap();
+        let mut tracker = LeaderTracker::new(&client)
+            .interval(Duration::from_millis(1))
+            .capacity(2)
+            .flapping(Duration::from_secs(60), 3);
+        let change = tracker.next().await.unwrap();
+        assert_eq!(
+            (change.previous(), change.leader()),
+            (None, Some("10.0.0.1:8300"))
+        );
+        assert_eq!(tracker.history().count(), 0);
+        let mut changes = vec![];
+        for _ in 0..4 {
+            let change = tracker.next().await.unwrap();
+            changes.push((
+                change.previous().map(str::to_string),
+                change.leader().map(str::to_string),
+                change.flapping(),
+            ));
+        }
+        let ip = |ip: &str| Some(ip.to_string());
+        assert_eq!(
+            changes,
+            [
+                (ip("10.0.0.1:8300"), ip("10.0.0.2:8300"), false),
+                (ip("10.0.0.2:8300"), None, false),
+                (None, ip("10.0.0.2:8300"), true),
+                (ip("10.0.0.2:8300"), ip("10.0.0.1:8300"), true),
+            ]
+        );
+        assert!(tracker.is_flapping());
+        let history: Vec<_> = tracker
+            .history()
+            .map(|c| (c.previous(), c.leader()))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (None, Some("10.0.0.2:8300")),
+                (Some("10.0.0.2:8300"), Some("10.0.0.1:8300"))
+            ]
+        );
+        agent.verify().unwrap();
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_forgets_changes_outside_the_window() {
+        let agent = agent(&["10.0.0.1:8300", "10.0.0.2:8300", "10.0.0.1:8300"]).await;
+        let client = Client::new(agent.url()).unwrap();
+        let mut tracker = LeaderTracker::new(&client)
+            .interval(Duration::from_millis(1))
+            .flapping(Duration::from_millis(100), 2);
+        tracker.next().await.unwrap();
+        assert!(!tracker.next().await.unwrap().flapping());
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!tracker.next().await.unwrap().flapping());
+        assert!(!tracker.is_flapping());
+        assert_eq!(tracker.history().count(), 2);
+    }
+}
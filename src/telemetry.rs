@@ -0,0 +1,95 @@
+//! Client metrics reported through the `metrics` facade; install any
+//! recorder, e.g. `metrics-exporter-prometheus`, to collect them:
+//!
+//! - `consul_requests_total` counter by `method`, `endpoint` and `status`
+//!   (`error` when no response was received)
+//! - `consul_request_duration_seconds` histogram by `method` and `endpoint`
+//! - `consul_blocking_query_duration_seconds` histogram by `endpoint`
+//! - `consul_retries_total` counter by `reason` (`failover`, `token`,
+//!   `rate_limited`)
+//!
+//! `endpoint` is the API group, e.g. `kv`, `discovery-chain` or
+//! `health/service`, so keys, service and peer names don't end up as labels.
+
+use std::time::Instant;
+
+pub(crate) struct Timer {
+    method: String,
+    endpoint: String,
+    blocking: bool,
+    started: Instant,
+}
+
+impl Timer {
+    pub(crate) fn start(method: &reqwest::Method, url: &url::Url, blocking: bool) -> Self {
+        Self {
+            method: method.to_string(),
+            endpoint: endpoint(url.path()),
+            blocking,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self, status: Option<u16>) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let status = status.map_or_else(|| "error".to_string(), |s| s.to_string());
+        metrics::counter!(
+            "consul_requests_total",
+            "method" => self.method.clone(),
+            "endpoint" => self.endpoint.clone(),
+            "status" => status,
+        )
+        .increment(1);
+        if self.blocking {
+            metrics::histogram!(
+                "consul_blocking_query_duration_seconds",
+                "endpoint" => self.endpoint,
+            )
+            .record(elapsed);
+        } else {
+            metrics::histogram!(
+                "consul_request_duration_seconds",
+                "method" => self.method,
+                "endpoint" => self.endpoint,
+            )
+            .record(elapsed);
+        }
+    }
+}
+
+pub(crate) fn retry(reason: &'static str) {
+    metrics::counter!("consul_retries_total", "reason" => reason).increment(1);
+}
+
+/// API groups whose second path segment is a user-chosen name, e.g. a key,
+/// a service or a peer.
+const NAMED_GROUPS: &[&str] = &["kv", "discovery-chain", "peering"];
+
+/// First two path segments after `v1/`, or just the group for
+/// [`NAMED_GROUPS`].
+fn endpoint(path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    let path = path.strip_prefix("v1/").unwrap_or(path);
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    match (segments.next(), segments.next()) {
+        (Some(group), _) if NAMED_GROUPS.contains(&group) => group.to_string(),
+        (Some(group), Some(name)) => format!("{group}/{name}"),
+        (Some(group), None) => group.to_string(),
+        (None, _) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_groups_endpoints() {
+        assert_eq!(endpoint("/v1/kv/app/config"), "kv");
+        assert_eq!(endpoint("/v1/health/service/web"), "health/service");
+        assert_eq!(endpoint("/v1/status/leader"), "status/leader");
+        assert_eq!(endpoint("/v1/txn"), "txn");
+        assert_eq!(endpoint("/v1/discovery-chain/web"), "discovery-chain");
+        assert_eq!(endpoint("/v1/peering/cluster-02"), "peering");
+    }
+}
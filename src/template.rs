@@ -0,0 +1,215 @@
+//! Renders minijinja templates from KV prefixes and service health, in the
+//! spirit of consul-template.
+//!
+//! Templates see two maps: `kv.<name>` holds the keys under a prefix
+//! (relative to it) with their values as text, and `services.<name>` the
+//! instances of a service with `ID`, `Service`, `Node`, `Address`, `Port`
+//! and `Tags`:
+//!
+//! ```text
+//! {% for s in services.web %}server {{ s.Address }}:{{ s.Port }};
+//! {% endfor %}timeout {{ kv.nginx.timeout }};
+//! ```
+
+use std::collections::BTreeMap;
+use std::pin::Pin;
+
+use futures_util::{Stream, StreamExt};
+use reqwest::Method;
+
+use crate::health::Health;
+use crate::watch::WatchUpdate;
+use crate::{Client, Kv, Record, Response};
+
+#[derive(Clone)]
+enum Source {
+    Kv(String),
+    Service(Health),
+}
+
+pub struct Template {
+    source: String,
+    sources: Vec<(String, Source)>,
+}
+
+type Events = Pin<Box<dyn Stream<Item = (usize, Result<WatchUpdate, anyhow::Error>)> + Send>>;
+
+impl Template {
+    pub fn new<S>(source: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            source: source.into(),
+            sources: vec![],
+        }
+    }
+
+    /// Exposes the keys under `prefix` as `kv.<name>`.
+    pub fn kv<N, P>(mut self, name: N, prefix: P) -> Self
+    where
+        N: Into<String>,
+        P: Into<String>,
+    {
+        self.sources.push((name.into(), Source::Kv(prefix.into())));
+        self
+    }
+
+    /// Exposes the instances returned by `health` as `services.<name>`;
+    /// use `.passing(true)` to only render healthy ones.
+    pub fn service<N>(mut self, name: N, health: Health) -> Self
+    where
+        N: Into<String>,
+    {
+        self.sources.push((name.into(), Source::Service(health)));
+        self
+    }
+
+    pub async fn render(&self, client: &Client) -> Result<String, anyhow::Error> {
+        let mut responses = Vec::with_capacity(self.sources.len());
+        for (_, source) in &self.sources {
+            let rs = match source.clone() {
+                Source::Kv(prefix) => {
+                    Kv::new(prefix)
+                        .recurse(true)
+                        .send_request(Method::GET, client)
+                        .await?
+                }
+                Source::Service(health) => health.send_request(Method::GET, client).await?,
+            };
+            responses.push(rs);
+        }
+        self.render_responses(&responses)
+    }
+
+    /// Re-renders whenever one of the sources changes, skipping renders
+    /// that produce the same output as the previous one.
+    pub fn watch(self, client: &Client) -> impl Stream<Item = Result<String, anyhow::Error>> {
+        let streams: Vec<Events> = self
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(i, (_, source))| {
+                let watch = match source.clone() {
+                    Source::Kv(prefix) => Kv::new(prefix).recurse(true).watch(client),
+                    Source::Service(health) => health.watch(client),
+                };
+                Box::pin(watch.into_stream().map(move |event| (i, event))) as Events
+            })
+            .collect();
+        let state = (
+            self,
+            futures_util::stream::select_all(streams),
+            Vec::<Option<Response>>::new(),
+            None::<String>,
+        );
+        futures_util::stream::unfold(
+            state,
+            |(template, mut events, mut latest, mut last)| async move {
+                latest.resize(template.sources.len(), None);
+                loop {
+                    let (i, event) = events.next().await?;
+                    match event {
+                        Ok(event) => latest[i] = Some(event.into_response()),
+                        Err(e) => return Some((Err(e), (template, events, latest, last))),
+                    }
+                    let Some(responses) = latest.iter().cloned().collect::<Option<Vec<_>>>() else {
+                        continue;
+                    };
+                    let output = match template.render_responses(&responses) {
+                        Ok(output) => output,
+                        Err(e) => return Some((Err(e), (template, events, latest, last))),
+                    };
+                    if last.as_ref() == Some(&output) {
+                        continue;
+                    }
+                    last = Some(output.clone());
+                    return Some((Ok(output), (template, events, latest, last)));
+                }
+            },
+        )
+    }
+
+    fn render_responses(&self, responses: &[Response]) -> Result<String, anyhow::Error> {
+        let mut kv = serde_json::Map::new();
+        let mut services = serde_json::Map::new();
+        for ((name, source), rs) in self.sources.iter().zip(responses) {
+            match source {
+                Source::Kv(prefix) => {
+                    kv.insert(name.clone(), kv_values(prefix, rs.clone())?);
+                }
+                Source::Service(health) => {
+                    let limit = health.output_limit;
+                    services.insert(name.clone(), instances(rs.clone(), limit)?);
+                }
+            }
+        }
+        let context = serde_json::json!({"kv": kv, "services": services});
+        let env = minijinja::Environment::new();
+        Ok(env.render_str(&self.source, context)?)
+    }
+}
+
+fn kv_values(prefix: &str, rs: Response) -> Result<serde_json::Value, anyhow::Error> {
+    if rs.status == 404 {
+        return Ok(serde_json::Value::Object(Default::default()));
+    }
+    let records: Vec<Record> = rs.error_for_status()?.try_into()?;
+    let mut values = BTreeMap::new();
+    for record in records {
+        let key = record.key().strip_prefix(prefix).unwrap_or(record.key());
+        let value = String::from_utf8_lossy(&record.value_as_slice()?).into_owned();
+        values.insert(key.trim_start_matches('/').to_string(), value);
+    }
+    Ok(serde_json::to_value(values)?)
+}
+
+fn instances(rs: Response, limit: Option<usize>) -> Result<serde_json::Value, anyhow::Error> {
+    let instances = Health::entries(rs, limit)?
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "ID": entry.service().id(),
+                "Service": entry.service().service(),
+                "Node": entry.node().node(),
+                "Address": entry.address(),
+                "Port": entry.port(),
+                "Tags": entry.service().tags(),
+            })
+        })
+        .collect();
+    Ok(serde_json::Value::Array(instances))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_renders() {
+        let client = Client::new("http://localhost:8500").unwrap();
+        Kv::new("template/nginx/timeout")
+            .put_string(&client, "30s")
+            .await
+            .unwrap();
+        let template = Template::new(
+            "{% for s in services.consul %}{{ s.Service }} {% endfor %}{{ kv.nginx.timeout }}",
+        )
+        .kv("nginx", "template/nginx/")
+        .service("consul", Health::service("consul").passing(true));
+        let output = template.render(&client).await.unwrap();
+        assert!(output.starts_with("consul "));
+        assert!(output.ends_with("30s"));
+        let mut renders = Box::pin(template.watch(&client));
+        assert!(renders.next().await.unwrap().unwrap().ends_with("30s"));
+        Kv::new("template/nginx/timeout")
+            .put_string(&client, "60s")
+            .await
+            .unwrap();
+        assert!(renders.next().await.unwrap().unwrap().ends_with("60s"));
+        Kv::new("template/nginx/timeout")
+            .delete(&client)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! Throwaway Consul agents for end-to-end tests, run with testcontainers.
+//!
+//! ```no_run
+//! use consulite::testing::Container;
+//!
+//! # async fn run() -> Result<(), anyhow::Error> {
+//! let consul = Container::new().start().await?;
+//! let client = consul.client()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The container is removed when the returned [`Consul`] is dropped.
+
+use std::time::Duration;
+
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+use crate::Client;
+use crate::status::Status;
+
+const IMAGE: &str = "hashicorp/consul";
+const PORT: u16 = 8500;
+
+/// Dev-mode agent container to start.
+#[derive(Debug, Clone)]
+pub struct Container {
+    tag: String,
+    management_token: Option<String>,
+}
+
+impl Default for Container {
+    fn default() -> Self {
+        Self {
+            tag: "1.22".to_string(),
+            management_token: None,
+        }
+    }
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tag of the `hashicorp/consul` image.
+    pub fn tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tag = tag.into();
+        self
+    }
+
+    /// Enables ACLs (default allow) with `token` as the initial
+    /// management token.
+    pub fn management_token<S>(mut self, token: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.management_token = Some(token.into());
+        self
+    }
+
+    /// Starts the container and waits until it has elected itself leader.
+    pub async fn start(self) -> Result<Consul, anyhow::Error> {
+        let mut request = GenericImage::new(IMAGE, &self.tag)
+            .with_exposed_port(PORT.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Consul agent running!"))
+            .with_cmd(["agent", "-dev", "-client=0.0.0.0"]);
+        if let Some(token) = &self.management_token {
+            let config = serde_json::json!({
+                "acl": {
+                    "enabled": true,
+                    "default_policy": "allow",
+                    "tokens": {"initial_management": token},
+                }
+            });
+            request = request.with_env_var("CONSUL_LOCAL_CONFIG", config.to_string());
+        }
+        let container = request.start().await?;
+        let host = container.get_host().await?;
+        let port = container.get_host_port_ipv4(PORT.tcp()).await?;
+        let consul = Consul {
+            url: format!("http://{host}:{port}"),
+            management_token: self.management_token,
+            container,
+        };
+        consul.wait_for_leader().await?;
+        Ok(consul)
+    }
+}
+
+/// Running agent container.
+pub struct Consul {
+    url: String,
+    management_token: Option<String>,
+    container: ContainerAsync<GenericImage>,
+}
+
+impl Consul {
+    /// HTTP address of the agent, e.g. `http://127.0.0.1:32768`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn management_token(&self) -> Option<&str> {
+        self.management_token.as_deref()
+    }
+
+    /// Client for the agent, using the management token if ACLs are on.
+    pub fn client(&self) -> Result<Client, anyhow::Error> {
+        let builder = Client::builder(&self.url);
+        match &self.management_token {
+            Some(token) => builder.token(token).build(),
+            None => builder.build(),
+        }
+    }
+
+    pub fn container(&self) -> &ContainerAsync<GenericImage> {
+        &self.container
+    }
+
+    async fn wait_for_leader(&self) -> Result<(), anyhow::Error> {
+        let client = self.client()?;
+        for _ in 0..60 {
+            if let Ok(Some(_)) = Status::leader(&client).await {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        anyhow::bail!("Consul at {} elected no leader", self.url)
+    }
+}
@@ -0,0 +1,180 @@
+//! ACL tokens: static or supplied per request by a [`TokenProvider`].
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+pub(crate) const TOKEN_HEADER: &str = "X-Consul-Token";
+
+pub type TokenFuture<'a> = Pin<Box<dyn Future<Output = Result<String, anyhow::Error>> + Send + 'a>>;
+
+/// Source of the ACL token sent with every request.
+///
+/// `token` is called once per request, so implementations should cache the
+/// token. When Consul answers 403, `refresh` is called and the request is
+/// retried once with the token it returns.
+pub trait TokenProvider: Send + Sync {
+    fn token(&self) -> TokenFuture<'_>;
+
+    fn refresh(&self) -> TokenFuture<'_> {
+        self.token()
+    }
+}
+
+impl TokenProvider for String {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+}
+
+impl<T> TokenProvider for Arc<T>
+where
+    T: TokenProvider + ?Sized,
+{
+    fn token(&self) -> TokenFuture<'_> {
+        (**self).token()
+    }
+
+    fn refresh(&self) -> TokenFuture<'_> {
+        (**self).refresh()
+    }
+}
+
+/// Token secret that doesn't show up in `Debug` output.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Tokens(pub(crate) Arc<dyn TokenProvider>);
+
+impl fmt::Debug for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenProvider")
+    }
+}
+
+/// Error for 403 responses, reachable via `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone)]
+pub struct PermissionDenied {
+    message: String,
+}
+
+impl PermissionDenied {
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Permission denied: {}", self.message)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "mock")]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    #[cfg(feature = "mock")]
+    use crate::Client;
+    #[cfg(feature = "mock")]
+    use crate::status::Status;
+
+    #[cfg(feature = "mock")]
+    struct Rotating {
+        refreshes: AtomicUsize,
+    }
+
+    #[cfg(feature = "mock")]
+    impl TokenProvider for Rotating {
+        fn token(&self) -> TokenFuture<'_> {
+            Box::pin(async move { Ok(format!("token-{}", self.refreshes.load(Ordering::SeqCst))) })
+        }
+
+        fn refresh(&self) -> TokenFuture<'_> {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+            self.token()
+        }
+    }
+
+    #[test]
+    fn it_redacts_secrets() {
+        let secret = Secret::from("b78d37c7-0ca7-5f4d-99ee-6d9975ce4586");
+        assert_eq!(format!("{secret:?}"), "Secret(<redacted>)");
+        assert_eq!(secret.expose(), "b78d37c7-0ca7-5f4d-99ee-6d9975ce4586");
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn it_refreshes_denied_tokens() {
+        use crate::mock::{MockAgent, Reply};
+
+        let agent = MockAgent::start().await.unwrap();
+        let denied = || Reply::new(403, "ACL not found");
+        agent.expect("GET", "v1/status/leader", denied());
+        agent.expect(
+            "GET",
+            "v1/status/leader",
+            Reply::json("10.0.0.1:8300".into()),
+        );
+        agent.expect("GET", "v1/status/leader", denied());
+        agent.expect("GET", "v1/status/leader", denied());
+        let provider = Arc::new(Rotating {
+            refreshes: AtomicUsize::new(0),
+        });
+        let client = Client::builder(agent.url())
+            .token_provider(provider.clone())
+            .build()
+            .unwrap();
+        let leader = Status::leader(&client).await.unwrap();
+        assert_eq!(leader.as_deref(), Some("10.0.0.1:8300"));
+        assert_eq!(provider.refreshes.load(Ordering::SeqCst), 1);
+        let client = Client::builder(agent.url()).token("stale").build().unwrap();
+        let err = Status::leader(&client).await.unwrap_err();
+        let denied = err.downcast_ref::<PermissionDenied>().unwrap();
+        assert_eq!(denied.message(), "ACL not found");
+        agent.verify().unwrap();
+        let tokens: Vec<_> = agent
+            .requests()
+            .iter()
+            .map(|rq| rq.header("X-Consul-Token").unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(tokens, ["token-0", "token-1", "stale", "stale"]);
+    }
+}
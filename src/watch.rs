@@ -0,0 +1,660 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use futures_util::{Stream, StreamExt};
+
+use crate::{Record, Response, random_unit};
+
+type Fetch = Box<
+    dyn FnMut(
+            u64,
+            Duration,
+        ) -> Pin<Box<dyn Future<Output = Result<Response, anyhow::Error>> + Send>>
+        + Send,
+>;
+
+type Shutdown = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Error returned once a watch's shutdown signal fired; streams end instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Watch cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Context attached to every error returned by [`Watch::next`]; get it with
+/// `err.downcast_ref::<WatchError>()` to alert on persistent failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchError {
+    failures: u32,
+    retry_in: Duration,
+    index: u64,
+}
+
+impl WatchError {
+    /// Failed polls since the last successful one, including this one.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Delay before the next poll.
+    pub fn retry_in(&self) -> Duration {
+        self.retry_in
+    }
+
+    /// Last index seen before the failure.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Watch failed {} time(s) in a row, retrying in {:?}",
+            self.failures, self.retry_in
+        )
+    }
+}
+
+/// Exponential backoff with jitter between polls after a failure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    reset_after: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay after the first failure.
+    pub fn initial(mut self, initial: Duration) -> Self {
+        self.initial = initial;
+        self
+    }
+
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Growth of the delay per consecutive failure.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Starts over from `initial` when the previous failure is at least
+    /// this long ago, even without a successful poll in between.
+    pub fn reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    /// Delay after `failures` consecutive failures: the exponential delay,
+    /// capped at `max`, with up to half of it replaced by random jitter.
+    fn delay(&self, failures: u32) -> Duration {
+        let exp = self
+            .multiplier
+            .max(1.0)
+            .powi(failures.saturating_sub(1) as i32);
+        let delay = self.initial.mul_f64(exp.min(u32::MAX as f64)).min(self.max);
+        delay / 2 + (delay / 2).mul_f64(random_unit())
+    }
+}
+
+/// Changes that may have been coalesced while the watch was failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    from: u64,
+    to: u64,
+}
+
+impl Gap {
+    pub fn from(&self) -> u64 {
+        self.from
+    }
+
+    pub fn to(&self) -> u64 {
+        self.to
+    }
+
+    /// The index went backwards, e.g. after a snapshot restore.
+    pub fn is_reset(&self) -> bool {
+        self.to < self.from
+    }
+}
+
+/// Item of [`Watch::events`].
+#[derive(Debug)]
+pub enum WatchEvent {
+    Update(WatchUpdate),
+    /// A poll failed; the watch polls again after [`WatchError::retry_in`].
+    Error {
+        context: WatchError,
+        error: anyhow::Error,
+    },
+}
+
+#[derive(Debug)]
+pub struct WatchUpdate {
+    seq: u64,
+    index: u64,
+    gap: Option<Gap>,
+    response: Response,
+}
+
+impl WatchUpdate {
+    /// Per-watch sequence number, starting at 1 and increasing by one per event.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn gap(&self) -> Option<Gap> {
+        self.gap
+    }
+
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+}
+
+/// Long-polls an endpoint with blocking queries, yielding an event each time
+/// `X-Consul-Index` changes.
+///
+/// Dropping a pending [`Watch::next`] aborts the in-flight request. For
+/// daemons, [`Watch::cancel_on`] ends the watch when a shutdown signal fires.
+/// Failed polls are returned as errors carrying a [`WatchError`]; the next
+/// call waits out the [`Backoff`] delay before polling again. Use
+/// [`Watch::events`] to get them as [`WatchEvent::Error`] items instead.
+pub struct Watch {
+    fetch: Fetch,
+    wait: Duration,
+    index: u64,
+    seq: u64,
+    failed: bool,
+    backoff: Backoff,
+    failures: u32,
+    last_failure: Option<Instant>,
+    delay: Duration,
+    shutdown: Option<Shutdown>,
+    cancelled: bool,
+}
+
+impl Watch {
+    /// `fetch` is called with the last seen index and the wait time.
+    pub fn new<F, Fut>(mut fetch: F) -> Self
+    where
+        F: FnMut(u64, Duration) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Response, anyhow::Error>> + Send + 'static,
+    {
+        Self {
+            fetch: Box::new(move |index, wait| Box::pin(fetch(index, wait))),
+            wait: Duration::from_secs(300),
+            index: 0,
+            seq: 0,
+            failed: false,
+            backoff: Backoff::default(),
+            failures: 0,
+            last_failure: None,
+            delay: Duration::ZERO,
+            shutdown: None,
+            cancelled: false,
+        }
+    }
+
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Fixed delay before polling again after a failed request; see
+    /// [`Watch::backoff`].
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.backoff = self.backoff.initial(retry).max(retry);
+        self
+    }
+
+    /// Backoff between polls after failed requests.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Stops the watch once `signal` completes, e.g.
+    /// `token.cancelled_owned()` or a `tokio::signal::ctrl_c()` wrapper. A
+    /// pending request is aborted, `next` then fails with [`Cancelled`] and
+    /// streams end.
+    pub fn cancel_on<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown = Some(Box::pin(signal));
+        self
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    pub async fn next(&mut self) -> Result<WatchUpdate, anyhow::Error> {
+        if self.cancelled {
+            return Err(Cancelled.into());
+        }
+        let Some(mut shutdown) = self.shutdown.take() else {
+            return self.poll().await;
+        };
+        let event = tokio::select! {
+            biased;
+            _ = &mut shutdown => None,
+            event = self.poll() => Some(event),
+        };
+        let Some(event) = event else {
+            self.cancelled = true;
+            return Err(Cancelled.into());
+        };
+        self.shutdown = Some(shutdown);
+        event
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(level = "debug", skip_all, fields(index = self.index, seq = self.seq))
+    )]
+    async fn poll(&mut self) -> Result<WatchUpdate, anyhow::Error> {
+        loop {
+            if self.failed {
+                tokio::time::sleep(self.delay).await;
+            }
+            let rs = match (self.fetch)(self.index, self.wait).await {
+                Ok(rs) => rs,
+                Err(e) => return Err(self.fail(e)),
+            };
+            let Some(index) = rs.index() else {
+                let error = if rs.is_success() {
+                    anyhow::anyhow!("No X-Consul-Index in response")
+                } else {
+                    rs.into_error()
+                };
+                return Err(self.fail(error));
+            };
+            self.failures = 0;
+            if self.seq > 0 && index == self.index {
+                self.failed = false;
+                continue;
+            }
+            let gap = (self.seq > 0 && (self.failed || index < self.index)).then_some(Gap {
+                from: self.index,
+                to: index,
+            });
+            self.failed = false;
+            // Never block on an index that went backwards, nor on zero.
+            self.index = index.max(1);
+            self.seq += 1;
+            return Ok(WatchUpdate {
+                seq: self.seq,
+                index,
+                gap,
+                response: rs,
+            });
+        }
+    }
+
+    fn fail(&mut self, error: anyhow::Error) -> anyhow::Error {
+        let now = Instant::now();
+        if self
+            .last_failure
+            .is_some_and(|at| now - at >= self.backoff.reset_after)
+        {
+            self.failures = 0;
+        }
+        self.failed = true;
+        self.failures = self.failures.saturating_add(1);
+        self.last_failure = Some(now);
+        self.delay = self.backoff.delay(self.failures);
+        error.context(WatchError {
+            failures: self.failures,
+            retry_in: self.delay,
+            index: self.index,
+        })
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<WatchUpdate, anyhow::Error>> {
+        futures_util::stream::unfold(self, |mut watch| async move {
+            match watch.next().await {
+                Err(e) if e.is::<Cancelled>() => None,
+                event => Some((event, watch)),
+            }
+        })
+    }
+
+    /// Like [`Watch::into_stream`], with failed polls as
+    /// [`WatchEvent::Error`] items, e.g. to alert on
+    /// [`WatchError::failures`].
+    pub fn events(self) -> impl Stream<Item = WatchEvent> {
+        self.into_stream().map(|event| match event {
+            Ok(update) => WatchEvent::Update(update),
+            Err(error) => WatchEvent::Error {
+                context: *error
+                    .downcast_ref::<WatchError>()
+                    .expect("poll errors carry a WatchError"),
+                error,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum KeyEvent {
+    /// Value at the time the watch started; `None` if the key did not exist.
+    Initial(Option<Record>),
+    Changed(Record),
+    Deleted,
+}
+
+/// Watch over a single KV key with configurable initial and delete semantics.
+pub struct KeyWatch {
+    watch: Watch,
+    emit_initial: bool,
+    emit_deletes: bool,
+    started: bool,
+    modify_index: Option<usize>,
+}
+
+impl KeyWatch {
+    pub fn new(watch: Watch) -> Self {
+        Self {
+            watch,
+            emit_initial: true,
+            emit_deletes: true,
+            started: false,
+            modify_index: None,
+        }
+    }
+
+    /// Whether the current value is emitted as [`KeyEvent::Initial`] (default)
+    /// or only subsequent changes are reported.
+    pub fn emit_initial(mut self, value: bool) -> Self {
+        self.emit_initial = value;
+        self
+    }
+
+    /// Whether deletions produce [`KeyEvent::Deleted`] (default) or are
+    /// silently skipped.
+    pub fn emit_deletes(mut self, value: bool) -> Self {
+        self.emit_deletes = value;
+        self
+    }
+
+    pub async fn next(&mut self) -> Result<KeyEvent, anyhow::Error> {
+        loop {
+            let rs = self.watch.next().await?.into_response();
+            let record = match rs.status {
+                404 => None,
+                _ => {
+                    let mut records: Vec<Record> = rs.error_for_status()?.try_into()?;
+                    records.pop()
+                }
+            };
+            let modify_index = record.as_ref().map(Record::modify_index);
+            if !self.started {
+                self.started = true;
+                self.modify_index = modify_index;
+                if self.emit_initial {
+                    return Ok(KeyEvent::Initial(record));
+                }
+                continue;
+            }
+            if modify_index == self.modify_index {
+                continue;
+            }
+            let existed = self.modify_index.is_some();
+            self.modify_index = modify_index;
+            match record {
+                Some(record) => return Ok(KeyEvent::Changed(record)),
+                None if existed && self.emit_deletes => return Ok(KeyEvent::Deleted),
+                None => continue,
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<KeyEvent, anyhow::Error>> {
+        futures_util::stream::unfold(self, |mut watch| async move {
+            match watch.next().await {
+                Err(e) if e.is::<Cancelled>() => None,
+                event => Some((event, watch)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    use bytes::Bytes;
+    use reqwest::Method;
+
+    fn record(index: u64, value: &str) -> Result<Response, anyhow::Error> {
+        let json = serde_json::json!([{
+            "CreateIndex": 1,
+            "Flags": 0,
+            "Key": "key",
+            "LockIndex": 0,
+            "ModifyIndex": index,
+            "Value": value,
+        }]);
+        Ok(Response {
+            method: Method::GET,
+            path: "/v1/kv/key".to_string(),
+            status: 200,
+            index: Some(index),
+            body: json.to_string().into(),
+            raw: false,
+            request_id: None,
+        })
+    }
+
+    fn missing(index: u64) -> Result<Response, anyhow::Error> {
+        Ok(Response {
+            method: Method::GET,
+            path: "/v1/kv/key".to_string(),
+            status: 404,
+            index: Some(index),
+            raw: false,
+            body: Bytes::new(),
+            request_id: None,
+        })
+    }
+
+    fn scripted(script: Vec<Result<Response, anyhow::Error>>) -> Watch {
+        let mut script = VecDeque::from(script);
+        Watch::new(move |_, _| {
+            let rs = script.pop_front().unwrap();
+            async move { rs }
+        })
+    }
+
+    fn response(index: u64) -> Result<Response, anyhow::Error> {
+        Ok(Response {
+            method: Method::GET,
+            path: "/v1/kv/key".to_string(),
+            status: 200,
+            index: Some(index),
+            raw: false,
+            body: Bytes::new(),
+            request_id: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn it_orders_events() {
+        let mut watch = scripted(vec![
+            response(10),
+            response(10),
+            response(12),
+            Err(anyhow::anyhow!("connection refused")),
+            response(20),
+            response(5),
+        ])
+        .retry(Duration::ZERO);
+
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.seq(), event.index(), event.gap()), (1, 10, None));
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.seq(), event.index(), event.gap()), (2, 12, None));
+        assert!(watch.next().await.is_err());
+        let event = watch.next().await.unwrap();
+        assert_eq!(event.seq(), 3);
+        assert_eq!(event.gap(), Some(Gap { from: 12, to: 20 }));
+        let event = watch.next().await.unwrap();
+        assert_eq!(event.seq(), 4);
+        assert!(event.gap().unwrap().is_reset());
+        assert_eq!(watch.index(), 5);
+    }
+
+    #[test]
+    fn it_backs_off_exponentially() {
+        let backoff = Backoff::new()
+            .initial(Duration::from_secs(1))
+            .max(Duration::from_secs(10));
+        for (failures, full) in [(1, 1), (2, 2), (3, 4), (4, 8), (5, 10), (30, 10)] {
+            let full = Duration::from_secs(full);
+            let delay = backoff.delay(failures);
+            assert!(delay >= full / 2 && delay <= full, "{failures}: {delay:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_reports_consecutive_failures() {
+        let mut watch = scripted(vec![
+            Err(anyhow::anyhow!("connection refused")),
+            Err(anyhow::anyhow!("connection refused")),
+            response(10),
+            Err(anyhow::anyhow!("connection refused")),
+        ])
+        .backoff(Backoff::new().reset_after(Duration::from_secs(600)));
+
+        let err = watch.next().await.unwrap_err();
+        let error = err.downcast_ref::<WatchError>().unwrap();
+        assert_eq!(error.failures(), 1);
+        assert!(format!("{err:#}").ends_with("connection refused"));
+        let err = watch.next().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<WatchError>().unwrap().failures(), 2);
+        assert_eq!(watch.next().await.unwrap().index(), 10);
+        let err = watch.next().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<WatchError>().unwrap().failures(), 1);
+        assert_eq!(err.downcast_ref::<WatchError>().unwrap().index(), 10);
+    }
+
+    #[tokio::test]
+    async fn it_emits_error_events() {
+        let watch = scripted(vec![
+            response(10),
+            Err(anyhow::anyhow!("connection refused")),
+            response(12),
+        ])
+        .retry(Duration::ZERO);
+        let mut events = Box::pin(watch.events());
+        assert!(matches!(events.next().await, Some(WatchEvent::Update(u)) if u.index() == 10));
+        match events.next().await {
+            Some(WatchEvent::Error { context, error }) => {
+                assert_eq!((context.failures(), context.index()), (1, 10));
+                assert!(format!("{error:#}").ends_with("connection refused"));
+            }
+            event => panic!("unexpected {event:?}"),
+        }
+        assert!(matches!(events.next().await, Some(WatchEvent::Update(u)) if u.index() == 12));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_resets_backoff() {
+        let mut watch = scripted(vec![
+            Err(anyhow::anyhow!("connection refused")),
+            Err(anyhow::anyhow!("connection refused")),
+        ])
+        .backoff(Backoff::new().reset_after(Duration::ZERO));
+        watch.next().await.unwrap_err();
+        let err = watch.next().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<WatchError>().unwrap().failures(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_cancels_pending_polls() {
+        use futures_util::StreamExt;
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let mut script = VecDeque::from([response(10)]);
+        let watch = Watch::new(move |_, _| {
+            let rs = script.pop_front();
+            async move {
+                match rs {
+                    Some(rs) => rs,
+                    None => std::future::pending().await,
+                }
+            }
+        })
+        .cancel_on(async move {
+            let _ = rx.await;
+        });
+        let mut events = Box::pin(watch.into_stream());
+        assert_eq!(events.next().await.unwrap().unwrap().index(), 10);
+        tx.send(()).unwrap();
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn it_controls_key_semantics() {
+        let script = || vec![missing(3), record(5, "MQ=="), missing(7), record(9, "Mg==")];
+
+        let mut watch = KeyWatch::new(scripted(script()));
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Initial(None))));
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Changed(r)) if r.modify_index() == 5));
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Deleted)));
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Changed(r)) if r.modify_index() == 9));
+
+        let mut watch = KeyWatch::new(scripted(script()))
+            .emit_initial(false)
+            .emit_deletes(false);
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Changed(r)) if r.modify_index() == 5));
+        assert!(matches!(watch.next().await, Ok(KeyEvent::Changed(r)) if r.modify_index() == 9));
+    }
+}
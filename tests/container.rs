@@ -0,0 +1,44 @@
+//! End-to-end tests against throwaway containers; needs a Docker daemon:
+//!
+//! ```sh
+//! cargo test --features testing --test container
+//! ```
+#![cfg(feature = "testing")]
+
+use consulite::Kv;
+use consulite::status::Status;
+use consulite::testing::Container;
+
+#[tokio::test]
+async fn dev_agent() {
+    let consul = Container::new().start().await.unwrap();
+    let client = consul.client().unwrap();
+    assert!(Status::leader(&client).await.unwrap().is_some());
+    Kv::new("container/key")
+        .put_string(&client, "value")
+        .await
+        .unwrap();
+    let record = Kv::new("container/key")
+        .get(&client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.value_as_string().unwrap(), "value");
+}
+
+#[tokio::test]
+async fn acl_agent() {
+    let consul = Container::new()
+        .management_token("container-root")
+        .start()
+        .await
+        .unwrap();
+    let client = consul.client().unwrap();
+    Kv::new("container/acl")
+        .put_string(&client, "value")
+        .await
+        .unwrap();
+    let anonymous = consulite::Client::new(consul.url()).unwrap();
+    let record = Kv::new("container/acl").get(&anonymous).await.unwrap();
+    assert!(record.is_some());
+}
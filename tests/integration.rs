@@ -0,0 +1,219 @@
+//! End-to-end tests against the cluster from `compose.integration.yaml`:
+//!
+//! ```sh
+//! docker compose -f compose.integration.yaml up -d
+//! cargo test --features integration --test integration
+//! ```
+//!
+//! Leadership transfers live in `tests/leader_transfer.rs`: cargo runs test
+//! binaries one after another, so they never race the tests here.
+#![cfg(feature = "integration")]
+
+use std::time::Duration;
+
+use consulite::acl::{Access, Acl, AlreadyBootstrapped, Rules};
+use consulite::agent::{Agent, TokenKind};
+use consulite::health::Health;
+use consulite::status::Status;
+use consulite::watch::KeyEvent;
+use consulite::{Client, Kv};
+
+const ROOT_TOKEN: &str = "integration-root";
+
+fn address() -> String {
+    std::env::var("CONSUL_HTTP_ADDR").unwrap_or_else(|_| "http://localhost:8500".into())
+}
+
+async fn client() -> Client {
+    let client = Client::new(address()).unwrap();
+    for _ in 0..60 {
+        if let Ok(Some(_)) = Status::leader(&client).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    panic!("cluster at {} has no leader", address());
+}
+
+async fn raw(
+    method: reqwest::Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let url = format!("{}/{path}", address());
+    let mut rq = reqwest::Client::new()
+        .request(method, url)
+        .header("X-Consul-Token", ROOT_TOKEN);
+    if let Some(body) = body {
+        rq = rq.json(&body);
+    }
+    let rs = rq.send().await.unwrap().error_for_status().unwrap();
+    rs.json().await.unwrap()
+}
+
+async fn create_session() -> String {
+    let rs = raw(
+        reqwest::Method::PUT,
+        "v1/session/create",
+        Some(serde_json::json!({"TTL": "30s"})),
+    )
+    .await;
+    rs["ID"].as_str().unwrap().to_string()
+}
+
+async fn destroy_session(id: &str) {
+    raw(
+        reqwest::Method::PUT,
+        &format!("v1/session/destroy/{id}"),
+        None,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn kv_roundtrip() {
+    let client = client().await;
+    for key in ["it/kv/a", "it/kv/b", "it/kv/c"] {
+        Kv::new(key)
+            .payload(serde_json::json!({"key": key}))
+            .flags(7)
+            .put(&client)
+            .await
+            .unwrap();
+    }
+    let records = Kv::new("it/kv/").list(&client).await.unwrap();
+    assert_eq!(records.len(), 3);
+    let record = Kv::new("it/kv/b").get(&client).await.unwrap().unwrap();
+    assert_eq!(record.flags(), 7);
+    assert_eq!(record.value().unwrap()["key"], "it/kv/b");
+    for key in ["it/kv/a", "it/kv/b", "it/kv/c"] {
+        Kv::new(key).delete(&client).await.unwrap();
+    }
+    assert!(Kv::new("it/kv/").list(&client).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn sessions_and_locks() {
+    let client = client().await;
+    let first = create_session().await;
+    let second = create_session().await;
+    let acquired = Kv::new("it/lock").acquire(&first).put(&client).await;
+    assert_eq!(acquired.unwrap().as_bool(), Some(true));
+    let contended = Kv::new("it/lock").acquire(&second).put(&client).await;
+    assert_eq!(contended.unwrap().as_bool(), Some(false));
+    let released = Kv::new("it/lock").release(&first).put(&client).await;
+    assert_eq!(released.unwrap().as_bool(), Some(true));
+    let acquired = Kv::new("it/lock").acquire(&second).put(&client).await;
+    assert_eq!(acquired.unwrap().as_bool(), Some(true));
+    destroy_session(&first).await;
+    destroy_session(&second).await;
+    Kv::new("it/lock").delete(&client).await.unwrap();
+}
+
+#[tokio::test]
+async fn key_watch() {
+    let client = client().await;
+    Kv::new("it/watch").delete(&client).await.unwrap();
+    let mut watch = Kv::new("it/watch").watch_key(&client).emit_initial(false);
+    let writer = client.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        Kv::new("it/watch")
+            .body(b"1".to_vec())
+            .put(&writer)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        Kv::new("it/watch").delete(&writer).await.unwrap();
+    });
+    let event = tokio::time::timeout(Duration::from_secs(10), watch.next());
+    match event.await.unwrap().unwrap() {
+        KeyEvent::Changed(record) => assert_eq!(record.value_as_slice().unwrap(), b"1"),
+        other => panic!("unexpected event {other:?}"),
+    }
+    let event = tokio::time::timeout(Duration::from_secs(10), watch.next());
+    assert!(matches!(event.await.unwrap().unwrap(), KeyEvent::Deleted));
+}
+
+#[tokio::test]
+async fn cluster_health() {
+    let client = client().await;
+    let servers = Health::service("consul")
+        .passing(true)
+        .get(&client)
+        .await
+        .unwrap();
+    assert_eq!(servers.len(), 3);
+    let members = Agent::members(&client, false).await.unwrap();
+    assert_eq!(members.iter().filter(|m| m.is_alive()).count(), 4);
+    assert_eq!(Status::peers(&client).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn acl_policy_rules() {
+    client().await;
+    let rules = Rules::new()
+        .key_prefix("it/acl/", Access::Write)
+        .service_prefix("", Access::Read);
+    let policy = raw(
+        reqwest::Method::PUT,
+        "v1/acl/policy",
+        Some(serde_json::json!({
+            "Name": "integration-rules",
+            "Rules": rules.to_hcl(),
+        })),
+    )
+    .await;
+    let id = policy["ID"].as_str().unwrap();
+    let read = raw(reqwest::Method::GET, &format!("v1/acl/policy/{id}"), None).await;
+    assert_eq!(read["Rules"], rules.to_hcl());
+    raw(
+        reqwest::Method::DELETE,
+        &format!("v1/acl/policy/{id}"),
+        None,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn agent_token_update() {
+    client().await;
+    let client = Client::builder(address())
+        .token(ROOT_TOKEN)
+        .build()
+        .unwrap();
+    Agent::update_token(&client, TokenKind::Agent, &ROOT_TOKEN.into())
+        .await
+        .unwrap();
+    let anonymous = Client::new(address()).unwrap();
+    assert!(
+        Agent::update_token(&anonymous, TokenKind::Agent, &ROOT_TOKEN.into())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn acl_bootstrap_done() {
+    let client = client().await;
+    let err = Acl::bootstrap(&client).await.unwrap_err();
+    let done = err.downcast_ref::<AlreadyBootstrapped>().unwrap();
+    assert!(done.reset_index() > 0);
+}
+
+#[tokio::test]
+async fn acl_token_self() {
+    client().await;
+    let client = Client::builder(address())
+        .token(ROOT_TOKEN)
+        .build()
+        .unwrap();
+    let token = Acl::token_self(&client).await.unwrap();
+    assert_eq!(token.secret_id().expose(), ROOT_TOKEN);
+    assert!(
+        token
+            .policies()
+            .iter()
+            .any(|p| p.name() == "global-management")
+    );
+}
@@ -0,0 +1,62 @@
+//! Raft leadership transfer against the cluster from
+//! `compose.integration.yaml`, in its own test binary so the other
+//! end-to-end tests never run while the cluster has no leader:
+//!
+//! ```sh
+//! cargo test --features integration --test leader_transfer
+//! ```
+#![cfg(feature = "integration")]
+
+use std::time::Duration;
+
+use consulite::Client;
+use consulite::operator::Operator;
+use consulite::status::Status;
+
+const ROOT_TOKEN: &str = "integration-root";
+
+fn address() -> String {
+    std::env::var("CONSUL_HTTP_ADDR").unwrap_or_else(|_| "http://localhost:8500".into())
+}
+
+async fn client() -> Client {
+    let client = Client::new(address()).unwrap();
+    for _ in 0..60 {
+        if let Ok(Some(_)) = Status::leader(&client).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    panic!("cluster at {} has no leader", address());
+}
+
+#[tokio::test]
+async fn raft_leader_transfer() {
+    client().await;
+    let client = Client::builder(address())
+        .token(ROOT_TOKEN)
+        .build()
+        .unwrap();
+    let raft = Operator::raft_configuration(&client).await.unwrap();
+    let leader = raft.leader().unwrap().id().to_string();
+    let target = raft
+        .servers()
+        .iter()
+        .find(|server| server.id() != leader && server.voter())
+        .unwrap()
+        .id()
+        .to_string();
+    Operator::transfer_leader(&client, Some(&target))
+        .await
+        .unwrap();
+    for _ in 0..30 {
+        // Reads fail while no server is leader.
+        if let Ok(raft) = Operator::raft_configuration(&client).await
+            && raft.leader().map(|server| server.id()) == Some(target.as_str())
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    panic!("leadership did not move to {target}");
+}